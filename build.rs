@@ -0,0 +1,108 @@
+//! Generates shell completions and a manpage for `smi` when the `cli` feature is enabled, and
+//! compiles the database of the `bundled` feature.
+//!
+//! Completions and the manpage are written to `$OUT_DIR`, or to `$SMI_ASSETS_DIR` when set so
+//! packagers can pick them up from a known location. The bundled database is compiled from
+//! `data/packages` into `$OUT_DIR/mime.cache` by the crate's own package compiler, whose modules
+//! are included below as they are in the library.
+
+fn main() {
+    #[cfg(feature = "cli")]
+    cli_assets::generate().expect("failed to generate CLI completions and manpage");
+    #[cfg(feature = "bundled")]
+    bundled::compile().expect("failed to compile the bundled database");
+}
+
+#[cfg(feature = "bundled")]
+#[allow(dead_code)]
+#[path = "src/error.rs"]
+mod error;
+#[cfg(feature = "bundled")]
+#[allow(dead_code)]
+#[path = "src/mime_type.rs"]
+mod mime_type;
+#[cfg(feature = "bundled")]
+#[allow(dead_code)]
+#[path = "src/model.rs"]
+mod model;
+#[cfg(feature = "bundled")]
+#[allow(dead_code)]
+#[path = "src/packages.rs"]
+mod packages;
+#[cfg(feature = "bundled")]
+#[allow(dead_code)]
+#[path = "src/reader.rs"]
+mod reader;
+#[cfg(all(feature = "bundled", feature = "serde"))]
+#[allow(dead_code)]
+#[path = "src/serialization.rs"]
+mod serialization;
+#[cfg(feature = "bundled")]
+#[allow(dead_code)]
+#[path = "src/xml.rs"]
+mod xml;
+
+// The paths the included modules use from the crate root
+#[cfg(feature = "bundled")]
+use error::{CacheSection, Corruption, Error};
+#[cfg(feature = "bundled")]
+use mime_type::{InvalidMimeType, MimeType};
+#[cfg(feature = "bundled")]
+use model::{
+    GlobRecord, MagicMatch, Matchlet, MimeDatabaseModel, NO_GLOBS_MARKER, NO_MAGIC_MARKER,
+    NamespaceRecord,
+};
+#[cfg(feature = "bundled")]
+use reader::MimeCacheHeader;
+
+#[cfg(feature = "bundled")]
+mod bundled {
+    use std::{env, fs, path::PathBuf};
+
+    use crate::{Error, MimeDatabaseModel};
+
+    pub fn compile() -> Result<(), Error> {
+        println!("cargo:rerun-if-changed=data/packages");
+
+        let data = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").expect("cargo sets it"));
+        let cache = MimeDatabaseModel::from_packages(&data.join("data"))?.to_cache_bytes();
+        // A cache the library couldn't read fails the build rather than the program
+        MimeDatabaseModel::from_cache_bytes(&cache)?;
+
+        let out = PathBuf::from(env::var_os("OUT_DIR").expect("cargo always sets OUT_DIR"));
+        let path = out.join("mime.cache");
+        fs::write(&path, cache).map_err(|e| Error::io(&path, e))
+    }
+}
+
+#[cfg(feature = "cli")]
+mod cli_assets {
+    use std::{env, fs, io, path::PathBuf};
+
+    use clap_complete::Shell;
+
+    mod cli {
+        include!("src/bin/smi/cli.rs");
+    }
+
+    pub fn generate() -> io::Result<()> {
+        println!("cargo:rerun-if-changed=src/bin/smi/cli.rs");
+        println!("cargo:rerun-if-env-changed=SMI_ASSETS_DIR");
+
+        let out_dir = env::var_os("SMI_ASSETS_DIR")
+            .or_else(|| env::var_os("OUT_DIR"))
+            .map(PathBuf::from)
+            .expect("cargo always sets OUT_DIR");
+
+        let completions = out_dir.join("completions");
+        fs::create_dir_all(&completions)?;
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            clap_complete::generate_to(shell, &mut cli::command(), "smi", &completions)?;
+        }
+
+        let man = out_dir.join("man");
+        fs::create_dir_all(&man)?;
+        clap_mangen::generate_to(cli::command(), &man)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+//! Strings packed into one buffer, so that an index of thousands of short patterns and type
+//! names is a handful of allocations rather than thousands.
+
+/// Where a string lies in a [`StrArena`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Span {
+    start: usize,
+    len: usize,
+}
+
+/// An append-only buffer of strings, each found again by the [`Span`] it was stored at.
+#[derive(Debug, Default)]
+pub(crate) struct StrArena {
+    text: String,
+}
+
+impl StrArena {
+    pub(crate) fn push(&mut self, s: &str) -> Span {
+        let span = Span {
+            start: self.text.len(),
+            len: s.len(),
+        };
+        self.text.push_str(s);
+        span
+    }
+
+    /// The string stored at `span`, which must come from this arena.
+    pub(crate) fn get(&self, span: Span) -> &str {
+        &self.text[span.start..span.start + span.len]
+    }
+
+    /// Gives back the room reserved for strings that were never pushed.
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.text.shrink_to_fit();
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.text.capacity()
+    }
+}
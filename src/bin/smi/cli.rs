@@ -0,0 +1,60 @@
+// The command line definition, kept free of any other code so build scripts can use it too.
+
+use clap::{Arg, ArgAction, Command};
+
+pub fn command() -> Command {
+    Command::new("smi")
+        .about("Query the shared mime info database")
+        .version(env!("CARGO_PKG_VERSION"))
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("daemon")
+                .about("Answer queries over a Unix socket with a warm database")
+                .long_about(
+                    "Answer queries over a Unix socket with a warm database.\n\n\
+                     Each request is one line, `filetype PATH`, `name FILENAME` or `icon MIMETYPE`, \
+                     and is answered by one line, `OK VALUE` or `ERR MESSAGE`.",
+                )
+                .arg(
+                    Arg::new("socket")
+                        .long("socket")
+                        .value_name("PATH")
+                        .required(true)
+                        .help("Where to create the socket"),
+                ),
+        )
+        .subcommand(
+            Command::new("doctor")
+                .about("Check the user's mime database for problems")
+                .long_about(
+                    "Check the user's mime database, $XDG_DATA_HOME/mime, for a missing, stale \
+                     or corrupt mime.cache, packages it doesn't cover and unreadable files.\n\n\
+                     Exits with failure if any problem is left.",
+                )
+                .arg(
+                    Arg::new("regenerate")
+                        .long("regenerate")
+                        .action(ArgAction::SetTrue)
+                        .help("Recompile mime.cache from the packages if it is out of date"),
+                ),
+        )
+        .subcommand(
+            Command::new("xdg-mime")
+                .about("Drop-in replacement for the queries of xdg-utils' xdg-mime")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("query")
+                        .subcommand_required(true)
+                        .subcommand(
+                            Command::new("filetype")
+                                .about("Print the mime type of a file")
+                                .arg(Arg::new("FILE").required(true)),
+                        )
+                        .subcommand(
+                            Command::new("default")
+                                .about("Print the default application for a mime type")
+                                .arg(Arg::new("MIMETYPE").required(true)),
+                        ),
+                ),
+        )
+}
@@ -0,0 +1,160 @@
+//! `smi daemon`, serving lookups from one long lived searcher over a Unix socket.
+//!
+//! The protocol is line based. Each request is `filetype PATH`, `name FILENAME` or
+//! `icon MIMETYPE`, answered by a single `OK VALUE` or `ERR MESSAGE` line. A connection can send
+//! any number of requests. Connections are served by a fixed set of threads, one per CPU, so
+//! that further clients wait to be accepted rather than each costing a thread.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::unix::{
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::ArgMatches;
+use shared_mime_info::{MimeSearcher, MimeType};
+
+pub fn run(matches: &ArgMatches) -> ExitCode {
+    let socket = PathBuf::from(matches.get_one::<String>("socket").unwrap());
+
+    let searcher = match MimeSearcher::new() {
+        Ok(searcher) => searcher,
+        Err(e) => {
+            eprintln!("smi: failed to load the mime database: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Clear out a socket left behind by a previous run, but never anything else
+    if socket
+        .symlink_metadata()
+        .is_ok_and(|metadata| metadata.file_type().is_socket())
+    {
+        let _ = std::fs::remove_file(&socket);
+    }
+    let listener = match UnixListener::bind(&socket) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("smi: cannot listen on {}: {e}", socket.display());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let workers = std::thread::available_parallelism().map_or(1, |n| n.get());
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| accept(&searcher, &listener));
+        }
+    });
+    ExitCode::SUCCESS
+}
+
+// Serves one connection after another, for as long as the listener accepts them
+fn accept(searcher: &MimeSearcher, listener: &UnixListener) {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => serve(searcher, stream),
+            Err(e) => eprintln!("smi: failed to accept connection: {e}"),
+        }
+    }
+}
+
+fn serve(searcher: &MimeSearcher, stream: UnixStream) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        let response = match answer(searcher, &line) {
+            Ok(value) => format!("OK {value}\n"),
+            Err(message) => format!("ERR {message}\n"),
+        };
+        if writer.write_all(response.as_bytes()).is_err() {
+            return;
+        }
+    }
+}
+
+fn answer(searcher: &MimeSearcher, request: &str) -> Result<String, String> {
+    let (command, argument) = request
+        .split_once(' ')
+        .ok_or_else(|| "expected `COMMAND ARGUMENT`".to_string())?;
+
+    match command {
+        "filetype" => {
+            let path = Path::new(argument);
+            if path.is_dir() {
+                return Ok(MimeType::INODE_DIRECTORY.to_string());
+            }
+            searcher
+                .sniff_file(path)
+                .map(|guess| guess.mime.to_string())
+                .map_err(|e| format!("{e}"))
+        }
+        "name" => searcher
+            .find_mimetype_from_filepath(Path::new(argument))
+            .map(|mime| mime.to_string())
+            .ok_or_else(|| "no match".to_string()),
+        "icon" => searcher
+            .find_icon_for_mimetype(argument.parse::<MimeType>().map_err(|e| format!("{e}"))?)
+            .map_err(|e| format!("{e}")),
+        _ => Err(format!("unknown command `{command}`")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::Shutdown;
+
+    use shared_mime_info::{GlobRecord, MimeDatabaseModel};
+
+    use super::*;
+
+    #[test]
+    fn each_request_gets_one_answer() {
+        let mime: MimeType = "text/x-smitest".parse().unwrap();
+        let searcher = MimeSearcher::builder()
+            .data_dirs(std::iter::empty::<PathBuf>())
+            .add_database(MimeDatabaseModel {
+                suffixes: vec![GlobRecord {
+                    pattern: "*.smitest".to_string(),
+                    mime: mime.clone(),
+                    weight: 50,
+                    case_sensitive: false,
+                }],
+                generic_icons: vec![(mime, "x-smitest".to_string())],
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+
+        let (client, server) = UnixStream::pair().unwrap();
+        std::thread::scope(|scope| {
+            scope.spawn(|| serve(&searcher, server));
+            (&client)
+                .write_all(b"name a.smitest\nicon text/x-smitest\nfiletype /\nname a.none\nhello\n")
+                .unwrap();
+            client.shutdown(Shutdown::Write).unwrap();
+            let answers: Vec<String> = BufReader::new(&client)
+                .lines()
+                .collect::<Result<_, _>>()
+                .unwrap();
+            assert_eq!(
+                answers,
+                [
+                    "OK text/x-smitest",
+                    "OK x-smitest",
+                    "OK inode/directory",
+                    "ERR no match",
+                    "ERR expected `COMMAND ARGUMENT`",
+                ]
+            );
+        });
+    }
+}
@@ -0,0 +1,22 @@
+//! `smi doctor`, checking the user's mime database and optionally recompiling it.
+
+use std::process::ExitCode;
+
+use clap::ArgMatches;
+
+pub fn run(matches: &ArgMatches) -> ExitCode {
+    match shared_mime_info::doctor(matches.get_flag("regenerate")) {
+        Ok(report) => {
+            println!("{report}");
+            if report.is_healthy() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        Err(e) => {
+            eprintln!("smi: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
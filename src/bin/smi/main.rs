@@ -0,0 +1,25 @@
+//! `smi`, a command line front end to the shared mime info database.
+
+use std::process::ExitCode;
+
+mod cli;
+#[cfg(unix)]
+mod daemon;
+mod doctor;
+mod xdg_mime;
+
+fn main() -> ExitCode {
+    let matches = cli::command().get_matches();
+    match matches.subcommand() {
+        #[cfg(unix)]
+        Some(("daemon", matches)) => daemon::run(matches),
+        #[cfg(not(unix))]
+        Some(("daemon", _)) => {
+            eprintln!("smi: the daemon needs Unix sockets");
+            ExitCode::FAILURE
+        }
+        Some(("doctor", matches)) => doctor::run(matches),
+        Some(("xdg-mime", matches)) => xdg_mime::run(matches),
+        _ => unreachable!("clap requires a subcommand"),
+    }
+}
@@ -0,0 +1,198 @@
+//! `smi xdg-mime`, answering the queries of xdg-utils' `xdg-mime` with identical output and exit
+//! codes so scripts can use either.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::ArgMatches;
+use shared_mime_info::{Error, MimeSearcher, MimeType};
+
+// Exit codes documented in xdg-mime(1)
+const EXIT_SYNTAX: u8 = 1;
+const EXIT_FILE_NOT_FOUND: u8 = 2;
+const EXIT_FAILURE: u8 = 4;
+const EXIT_NO_PERMISSION: u8 = 5;
+
+pub fn run(matches: &ArgMatches) -> ExitCode {
+    let Some(("query", query)) = matches.subcommand() else {
+        return fail(EXIT_SYNTAX, "invalid command");
+    };
+    match query.subcommand() {
+        Some(("filetype", args)) => filetype(Path::new(args.get_one::<String>("FILE").unwrap())),
+        Some(("default", args)) => default(args.get_one::<String>("MIMETYPE").unwrap()),
+        _ => fail(EXIT_SYNTAX, "invalid query"),
+    }
+}
+
+fn filetype(path: &Path) -> ExitCode {
+    let metadata = match path.metadata() {
+        Ok(metadata) => metadata,
+        Err(_) => {
+            return fail(
+                EXIT_FILE_NOT_FOUND,
+                &format!("file '{}' does not exist", path.display()),
+            );
+        }
+    };
+    if metadata.is_dir() {
+        println!("{}", MimeType::INODE_DIRECTORY);
+        return ExitCode::SUCCESS;
+    }
+
+    let searcher = match MimeSearcher::new() {
+        Ok(searcher) => searcher,
+        Err(e) => return fail(EXIT_FAILURE, &format!("{e}")),
+    };
+
+    match searcher.sniff_file(path) {
+        Ok(guess) => {
+            println!("{}", guess.mime);
+            ExitCode::SUCCESS
+        }
+        Err(Error::Io(_)) => fail(
+            EXIT_NO_PERMISSION,
+            &format!("no permission to read file '{}'", path.display()),
+        ),
+        Err(e) => fail(EXIT_FAILURE, &format!("{e}")),
+    }
+}
+
+// Follows the lookup order of xdg-mime's generic backend: desktop specific then plain
+// mimeapps.list in the config and applications directories, then defaults.list and
+// mimeinfo.cache. The first listed application that is actually installed wins. Like xdg-mime,
+// nothing is printed when there is no default.
+fn default(mime: &str) -> ExitCode {
+    let config_dirs = xdg_dirs("XDG_CONFIG_HOME", ".config", "XDG_CONFIG_DIRS", "/etc/xdg");
+    let application_dirs: Vec<PathBuf> = xdg_dirs(
+        "XDG_DATA_HOME",
+        ".local/share",
+        "XDG_DATA_DIRS",
+        "/usr/local/share:/usr/share",
+    )
+    .into_iter()
+    .map(|dir| dir.join("applications"))
+    .collect();
+
+    let desktops: Vec<String> = env::var("XDG_CURRENT_DESKTOP")
+        .unwrap_or_default()
+        .split(':')
+        .filter(|d| !d.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+
+    let mut lists = Vec::new();
+    for dir in config_dirs.iter().chain(&application_dirs) {
+        for desktop in &desktops {
+            lists.push((
+                dir.join(format!("{desktop}-mimeapps.list")),
+                "Default Applications",
+            ));
+        }
+        lists.push((dir.join("mimeapps.list"), "Default Applications"));
+    }
+    for dir in &application_dirs {
+        lists.push((dir.join("defaults.list"), "Default Applications"));
+    }
+    for dir in &application_dirs {
+        lists.push((dir.join("mimeinfo.cache"), "MIME Cache"));
+    }
+
+    let installed = |id: &str| application_dirs.iter().any(|dir| dir.join(id).is_file());
+    for (list, group) in lists {
+        let Ok(contents) = std::fs::read_to_string(&list) else {
+            continue;
+        };
+        if let Some(id) = desktop_ids(&contents, group, mime).find(|id| installed(id)) {
+            println!("{id}");
+            break;
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// The desktop ids listed for `mime` in `group` of a desktop entry style key file.
+fn desktop_ids<'a>(
+    contents: &'a str,
+    group: &'a str,
+    mime: &'a str,
+) -> impl Iterator<Item = &'a str> + 'a {
+    let mut in_group = false;
+    contents
+        .lines()
+        .filter_map(move |line| {
+            let line = line.trim();
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                in_group = name == group;
+                return None;
+            }
+            let (key, value) = line.split_once('=')?;
+            (in_group && key.trim() == mime).then_some(value)
+        })
+        .flat_map(|value| value.split(';'))
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+}
+
+/// `$home_var` (or `~/home_default`) followed by the entries of `$dirs_var` (or `dirs_default`).
+fn xdg_dirs(
+    home_var: &str,
+    home_default: &str,
+    dirs_var: &str,
+    dirs_default: &str,
+) -> Vec<PathBuf> {
+    let home = env::var_os(home_var)
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME")
+                .filter(|home| !home.is_empty())
+                .map(|home| Path::new(&home).join(home_default))
+        });
+    let dirs = env::var(dirs_var)
+        .ok()
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| dirs_default.to_string());
+
+    home.into_iter()
+        .chain(env::split_paths(&dirs))
+        .filter(|dir| dir.is_absolute())
+        .collect()
+}
+
+fn fail(code: u8, message: &str) -> ExitCode {
+    eprintln!("xdg-mime: {message}");
+    ExitCode::from(code)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn desktop_ids_of_a_group() {
+        let list = "[Added Associations]\n\
+                    text/plain=added.desktop\n\
+                    [Default Applications]\n\
+                    text/plain = first.desktop; second.desktop;;\n\
+                    text/html=browser.desktop\n";
+        let ids = |group, mime| desktop_ids(list, group, mime).collect::<Vec<_>>();
+        assert_eq!(
+            ids("Default Applications", "text/plain"),
+            ["first.desktop", "second.desktop"]
+        );
+        assert_eq!(ids("Added Associations", "text/plain"), ["added.desktop"]);
+        assert_eq!(ids("Default Applications", "image/png"), [] as [&str; 0]);
+    }
+
+    #[test]
+    fn filetype_exit_codes() {
+        assert_eq!(filetype(Path::new("/")), ExitCode::SUCCESS);
+        assert_eq!(
+            filetype(Path::new("/nonexistent/smi-test")),
+            ExitCode::from(EXIT_FILE_NOT_FOUND)
+        );
+    }
+}
@@ -0,0 +1,259 @@
+//! Configuring which parts of the databases a [`MimeSearcher`] loads and uses.
+
+#[cfg(feature = "magic")]
+use std::time::Duration;
+use std::{path::PathBuf, sync::Arc};
+
+use crate::{ConflictPolicy, Error, MimeDatabase, MimeSearcher, dirs};
+
+/// Builds a [`MimeSearcher`] that only loads and uses what a program needs.
+///
+/// ```no_run
+/// # #[cfg(all(feature = "magic", feature = "icons"))] {
+/// # use shared_mime_info as smi;
+/// // A one-shot tool that only maps file names to types
+/// let searcher = smi::MimeSearcher::builder()
+///     .with_magic(false)
+///     .with_icons(false)
+///     .build()
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MimeSearcherBuilder {
+    dirs: Option<Vec<PathBuf>>,
+    databases: Vec<Arc<dyn MimeDatabase>>,
+    options: Options,
+}
+
+/// What a searcher was built to use.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Options {
+    #[cfg(feature = "magic")]
+    pub(crate) magic: bool,
+    #[cfg(feature = "globs")]
+    pub(crate) globs: bool,
+    #[cfg(feature = "icons")]
+    pub(crate) icons: bool,
+    #[cfg(feature = "magic")]
+    pub(crate) sniff_limit: Option<usize>,
+    #[cfg(feature = "magic")]
+    pub(crate) sniff_timeout: Option<Duration>,
+    pub(crate) conflict_policy: ConflictPolicy,
+    pub(crate) text_heuristics: bool,
+    pub(crate) index_profile: IndexProfile,
+    #[cfg(feature = "mime_guess")]
+    pub(crate) mime_guess_fallback: bool,
+}
+
+/// How much a searcher copies out of its databases when it is built, trading memory and start
+/// up time for the speed of lookups.
+///
+/// Either way lookups give the same answers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum IndexProfile {
+    /// Build nothing up front. A mime.cache is searched in place on each lookup: its `*.ext`
+    /// globs through its suffix tree, and parents and icons by binary search. Only the globs
+    /// a cache can't look up quickly, and those of globs2 files and added databases, are
+    /// indexed, and only by the first file name lookup. This suits short-lived tools and
+    /// programs that make few lookups.
+    #[default]
+    Minimal,
+    /// Index every glob, parent and generic icon in hash maps while building, so each lookup
+    /// is a single hash lookup. This costs a few hundred kilobytes for a typical system
+    /// database and reads every database in full, which suits long-running programs making
+    /// many lookups, such as file managers and indexers.
+    Fast,
+}
+
+// Derivable when the features with on-by-default options are all off
+#[allow(clippy::derivable_impls)]
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            #[cfg(feature = "magic")]
+            magic: true,
+            #[cfg(feature = "globs")]
+            globs: true,
+            #[cfg(feature = "icons")]
+            icons: true,
+            #[cfg(feature = "magic")]
+            sniff_limit: None,
+            #[cfg(feature = "magic")]
+            sniff_timeout: None,
+            conflict_policy: ConflictPolicy::default(),
+            text_heuristics: false,
+            index_profile: IndexProfile::default(),
+            #[cfg(feature = "mime_guess")]
+            mime_guess_fallback: false,
+        }
+    }
+}
+
+impl Options {
+    /// Whether a searcher may be built without any database, to answer from `mime_guess`'s
+    /// extension table instead.
+    pub(crate) fn mime_guess_fallback(&self) -> bool {
+        #[cfg(feature = "mime_guess")]
+        return self.mime_guess_fallback;
+        #[cfg(not(feature = "mime_guess"))]
+        false
+    }
+}
+
+impl MimeSearcher {
+    /// Starts configuring a searcher. Everything is enabled and the XDG data directories are
+    /// used unless told otherwise, which is what [`MimeSearcher::new`] does.
+    pub fn builder() -> MimeSearcherBuilder {
+        MimeSearcherBuilder::default()
+    }
+}
+
+impl MimeSearcherBuilder {
+    /// Whether to sniff contents with the magic rules. Without them
+    /// [`find_mimetype_from_data`](MimeSearcher::find_mimetype_from_data) finds nothing and
+    /// [`max_magic_extent`](MimeSearcher::max_magic_extent) is 0.
+    #[cfg(feature = "magic")]
+    pub fn with_magic(mut self, magic: bool) -> Self {
+        self.options.magic = magic;
+        self
+    }
+
+    /// Whether to index file name globs. The index is only built by the first lookup that needs
+    /// it, but without it globs2 files aren't read at all and
+    /// [`find_mimetype_from_filepath`](MimeSearcher::find_mimetype_from_filepath) finds nothing.
+    #[cfg(feature = "globs")]
+    pub fn with_globs(mut self, globs: bool) -> Self {
+        self.options.globs = globs;
+        self
+    }
+
+    /// Whether to look up icon names. Without them
+    /// [`find_icon_for_mimetype`](MimeSearcher::find_icon_for_mimetype) always fails with
+    /// [`Error::NoIconFound`].
+    #[cfg(feature = "icons")]
+    pub fn with_icons(mut self, icons: bool) -> Self {
+        self.options.icons = icons;
+        self
+    }
+
+    /// Loads the databases in `dirs` instead of the XDG data directories, as for
+    /// [`MimeSearcher::with_dirs`].
+    pub fn data_dirs(mut self, dirs: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        self.dirs = Some(dirs.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Consults `database` before the ones in the data directories, and before any added
+    /// earlier, so its definitions override theirs as a more important directory's would.
+    ///
+    /// ```no_run
+    /// # use shared_mime_info as smi;
+    /// let mut overrides = smi::MimeDatabaseModel::default();
+    /// overrides.suffixes.push(smi::GlobRecord {
+    ///     pattern: "*.note".to_string(),
+    ///     mime: smi::MimeType::TEXT_PLAIN,
+    ///     weight: 50,
+    ///     case_sensitive: false,
+    /// });
+    /// let searcher = smi::MimeSearcher::builder()
+    ///     .add_database(overrides)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn add_database(mut self, database: impl MimeDatabase + 'static) -> Self {
+        self.databases.insert(0, Arc::new(database));
+        self
+    }
+
+    /// Only sniffs the first `bytes` of any data, even if magic rules look further. This caps
+    /// [`max_magic_extent`](MimeSearcher::max_magic_extent), so callers reading that much of a
+    /// file read less too, at the cost of missing rules that look past it. No method of the
+    /// searcher reads more than this of a file or stream.
+    #[cfg(feature = "magic")]
+    pub fn sniff_limit(mut self, bytes: usize) -> Self {
+        self.options.sniff_limit = Some(bytes);
+        self
+    }
+
+    /// Gives up reading a file or stream to sniff once `timeout` has passed, failing with
+    /// [`Error::SniffAborted`] rather than keeping a UI thread waiting on a slow network mount.
+    ///
+    /// This applies to [`sniff_file`](MimeSearcher::sniff_file),
+    /// [`sniff_reader`](MimeSearcher::sniff_reader) and their async versions, and to every
+    /// other lookup that reads files, such as [`explain`](MimeSearcher::explain) and
+    /// [`find_mimetypes_for_paths`](MimeSearcher::find_mimetypes_for_paths). They read at most
+    /// 16 KiB at a time and check the time after each read. A single read that never returns
+    /// still blocks.
+    #[cfg(feature = "magic")]
+    pub fn sniff_timeout(mut self, timeout: Duration) -> Self {
+        self.options.sniff_timeout = Some(timeout);
+        self
+    }
+
+    /// How [`guess`](MimeSearcher::guess) settles a file name and contents that disagree, see
+    /// [`MimeSearcher::set_conflict_policy`].
+    pub fn conflict_policy(mut self, policy: ConflictPolicy) -> Self {
+        self.options.conflict_policy = policy;
+        self
+    }
+
+    /// Whether [`guess`](MimeSearcher::guess) may refine `text/plain` for files without an
+    /// extension by looking for a `#!` line, an Emacs or Vim mode line, or a telltale keyword,
+    /// so that a `configure` script comes out as `application/x-shellscript`. Off by default,
+    /// as these clues can be wrong, and guesses made this way are marked
+    /// [`Heuristic`](crate::DetectionMethod::Heuristic).
+    pub fn with_text_heuristics(mut self, heuristics: bool) -> Self {
+        self.options.text_heuristics = heuristics;
+        self
+    }
+
+    /// Whether to build a searcher even when no database is installed, answering file name
+    /// lookups from the extension table of the `mime_guess` crate instead of failing with
+    /// [`Error::MimeCacheNotFound`]. Off by default.
+    ///
+    /// This keeps command line tools working in stripped-down containers. The table only knows
+    /// extensions, so contents aren't sniffed and types have no parents or icons. Guesses made
+    /// from it are marked [`ExtensionTable`](crate::DetectionMethod::ExtensionTable), and the
+    /// table is never consulted while any database is loaded, so never with the `bundled`
+    /// feature.
+    ///
+    /// ```no_run
+    /// # use shared_mime_info as smi;
+    /// let searcher = smi::MimeSearcher::builder()
+    ///     .with_mime_guess_fallback(true)
+    ///     .build()
+    ///     .unwrap();
+    /// let guess = searcher.guess("a.pdf".as_ref(), b"").unwrap();
+    /// ```
+    #[cfg(feature = "mime_guess")]
+    pub fn with_mime_guess_fallback(mut self, fallback: bool) -> Self {
+        self.options.mime_guess_fallback = fallback;
+        self
+    }
+
+    /// How much to index up front, [`IndexProfile::Minimal`] by default.
+    ///
+    /// ```no_run
+    /// # use shared_mime_info as smi;
+    /// // A file manager classifying whole directories
+    /// let searcher = smi::MimeSearcher::builder()
+    ///     .index_profile(smi::IndexProfile::Fast)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn index_profile(mut self, profile: IndexProfile) -> Self {
+        self.options.index_profile = profile;
+        self
+    }
+
+    /// Loads the databases.
+    ///
+    /// Fails like [`MimeSearcher::with_dirs`] does, and with the error of the first database
+    /// that can't be read in full when the profile is [`IndexProfile::Fast`].
+    pub fn build(self) -> Result<MimeSearcher, Error> {
+        let dirs = self.dirs.unwrap_or_else(dirs::mime_dirs);
+        MimeSearcher::load(self.databases, &dirs, self.options)
+    }
+}
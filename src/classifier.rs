@@ -0,0 +1,256 @@
+//! Sans-I/O classification of byte streams.
+
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use crate::{DetectionMethod, Error, Guess, MimeSearcher, inode};
+
+/// Classifies a stream from chunks the caller reads itself, for servers that get data from their
+/// own buffers (request bodies, uploads) and must not block on or over-read them.
+///
+/// Feed chunks as they arrive until [`ClassifierEvent::Classified`] is returned, or call
+/// [`finish`](Self::finish) when the stream ends early. At most
+/// [`max_magic_extent`](MimeSearcher::max_magic_extent) bytes are buffered, and when a file name
+/// settles the type on its own no bytes are needed at all.
+#[derive(Debug)]
+pub struct Classifier<'a> {
+    searcher: &'a MimeSearcher,
+    name: PathBuf,
+    buffer: Vec<u8>,
+    wanted: usize,
+    result: Option<Guess>,
+}
+
+/// What a [`Classifier`] needs after being fed a chunk.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum ClassifierEvent {
+    /// Up to this many more bytes could change the answer.
+    NeedMore(usize),
+    /// The type is known and further bytes are ignored.
+    Classified(Guess),
+}
+
+impl<'a> Classifier<'a> {
+    pub fn new(searcher: &'a MimeSearcher) -> Self {
+        Classifier {
+            searcher,
+            name: PathBuf::new(),
+            buffer: Vec::new(),
+            wanted: searcher.max_magic_extent(),
+            result: None,
+        }
+    }
+
+    /// Takes the file name of the stream into account, as [`MimeSearcher::guess`] does.
+    pub fn with_name(mut self, name: impl AsRef<Path>) -> Self {
+        self.name = name.as_ref().to_path_buf();
+        self
+    }
+
+    /// Buffers as much of `chunk` as can affect the result.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<ClassifierEvent, Error> {
+        if let Some(guess) = &self.result {
+            return Ok(ClassifierEvent::Classified(guess.clone()));
+        }
+
+        let take = chunk.len().min(self.wanted - self.buffer.len());
+        self.buffer.extend_from_slice(&chunk[..take]);
+        if self.buffer.len() < self.wanted
+            && self.searcher.unambiguous_name_match(&self.name).is_none()
+        {
+            return Ok(ClassifierEvent::NeedMore(self.wanted - self.buffer.len()));
+        }
+        self.finish().map(ClassifierEvent::Classified)
+    }
+
+    /// Classifies whatever has been fed so far, for streams shorter than the magic extent.
+    pub fn finish(&mut self) -> Result<Guess, Error> {
+        if let Some(guess) = &self.result {
+            return Ok(guess.clone());
+        }
+        let guess = self.searcher.guess(&self.name, &self.buffer)?;
+        self.result = Some(guess.clone());
+        Ok(guess)
+    }
+
+    /// The bytes buffered so far, so the caller can replay them after classification.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buffer
+    }
+}
+
+/// The most read from a stream at once. Magic rules rarely look further than this.
+const CHUNK: usize = 16 * 1024;
+
+/// Reads a stream into a [`Classifier`], leaving the reading itself to the caller so that the
+/// same steps serve blocking and async readers alike.
+///
+/// Fill the buffer [`next`](Self::next) asks for, report how much was read with
+/// [`filled`](Self::filled), and repeat until it has the answer.
+#[derive(Debug)]
+pub(crate) struct ReadDriver<'a> {
+    classifier: Classifier<'a>,
+    chunk: Vec<u8>,
+    event: ClassifierEvent,
+    /// When to give up, from the searcher's sniff timeout.
+    deadline: Option<Instant>,
+}
+
+/// What a [`ReadDriver`] needs next.
+pub(crate) enum ReadStep<'b> {
+    /// Read into this buffer.
+    Read(&'b mut [u8]),
+    Done(Guess),
+}
+
+impl<'a> ReadDriver<'a> {
+    pub(crate) fn new(mut classifier: Classifier<'a>) -> Result<Self, Error> {
+        // Without any data, in case the name settles it
+        let event = classifier.feed(&[])?;
+        let timeout = classifier.searcher.options.sniff_timeout;
+        Ok(ReadDriver {
+            classifier,
+            chunk: Vec::new(),
+            event,
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+        })
+    }
+
+    /// The answer if there is one, or else the buffer the next read should fill.
+    pub(crate) fn next(&mut self) -> ReadStep<'_> {
+        match &self.event {
+            ClassifierEvent::NeedMore(wanted) => {
+                self.chunk.resize((*wanted).min(CHUNK), 0);
+                ReadStep::Read(&mut self.chunk)
+            }
+            ClassifierEvent::Classified(guess) => ReadStep::Done(guess.clone()),
+        }
+    }
+
+    /// Takes the first `read` bytes of the buffer, 0 meaning the stream ended. Fails with
+    /// [`Error::SniffAborted`] if more is needed but the deadline has passed.
+    pub(crate) fn filled(&mut self, read: usize) -> Result<(), Error> {
+        self.event = match read {
+            0 => ClassifierEvent::Classified(self.classifier.finish()?),
+            read => self.classifier.feed(&self.chunk[..read])?,
+        };
+        let expired = self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline);
+        if expired && matches!(self.event, ClassifierEvent::NeedMore(_)) {
+            let read = self.classifier.buffered().len();
+            return Err(Error::SniffAborted { read });
+        }
+        Ok(())
+    }
+}
+
+impl MimeSearcher {
+    /// [`guess`](Self::guess) for the file at `path`, reading only as much of it as the magic
+    /// rules look at, and nothing at all when its name settles the type.
+    ///
+    /// Only regular files are read. Directories, FIFOs, sockets and devices get their `inode/*`
+    /// type, with [`DetectionMethod::Inode`], so that sniffing never waits on a pipe or
+    /// terminal.
+    ///
+    /// ```no_run
+    /// # use shared_mime_info as smi;
+    /// let searcher = smi::MimeSearcher::builder()
+    ///     .sniff_timeout(std::time::Duration::from_millis(200))
+    ///     .build()
+    ///     .unwrap();
+    /// match searcher.sniff_file("/mnt/share/report") {
+    ///     Ok(guess) => println!("{}", guess.mime),
+    ///     Err(smi::Error::SniffAborted { .. }) => println!("too slow to tell"),
+    ///     Err(e) => println!("{e}"),
+    /// }
+    /// ```
+    ///
+    /// Fails with [`Error::Io`] if the file has to be read and can't be, and
+    /// [`Error::SniffAborted`] if reading it outlasts the
+    /// [sniff timeout](crate::MimeSearcherBuilder::sniff_timeout).
+    pub fn sniff_file(&self, path: impl AsRef<Path>) -> Result<Guess, Error> {
+        let path = path.as_ref();
+        let mut driver = ReadDriver::new(Classifier::new(self).with_name(path))?;
+        if let ReadStep::Done(guess) = driver.next() {
+            return Ok(guess);
+        }
+        let metadata = fs::metadata(path).map_err(|e| Error::io(path, e))?;
+        if let Some(mime) = inode::non_regular_type(&metadata) {
+            return Ok(Guess::new(mime, DetectionMethod::Inode));
+        }
+        let file = File::open(path).map_err(|e| Error::io(path, e))?;
+        drive(driver, file, |e| Error::io(path, e))
+    }
+
+    /// Reads the first [`max_magic_extent`](Self::max_magic_extent) bytes of `path`, which the
+    /// caller has checked is a regular file, into `data` in place of what it held, for callers
+    /// that need the bytes as well as the guess. Reads and gives up as
+    /// [`sniff_file`](Self::sniff_file) does, failing with [`Error::Io`] if the file can't be
+    /// read and [`Error::SniffAborted`] if reading it outlasts the sniff timeout.
+    pub(crate) fn read_head(&self, path: &Path, data: &mut Vec<u8>) -> Result<(), Error> {
+        let deadline = self
+            .options
+            .sniff_timeout
+            .map(|timeout| Instant::now() + timeout);
+        let limit = self.max_magic_extent();
+        let mut file = File::open(path).map_err(|e| Error::io(path, e))?;
+        data.clear();
+        while data.len() < limit {
+            let start = data.len();
+            data.resize(limit.min(start + CHUNK), 0);
+            let read = file.read(&mut data[start..]);
+            data.truncate(start + read.as_ref().map_or(0, |&read| read));
+            match read {
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(Error::io(path, e)),
+                Ok(0) => break,
+                Ok(_) => {}
+            }
+            let expired = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            if expired && data.len() < limit {
+                return Err(Error::SniffAborted { read: data.len() });
+            }
+        }
+        Ok(())
+    }
+
+    /// [`guess`](Self::guess) for a stream without a name, reading only as much of it as the
+    /// magic rules look at. The rest is left unread in `reader`.
+    ///
+    /// ```no_run
+    /// # use shared_mime_info as smi;
+    /// let searcher = smi::MimeSearcher::new().unwrap();
+    /// let guess = searcher.sniff_reader(std::io::stdin().lock()).unwrap();
+    /// println!("{}", guess.mime);
+    /// ```
+    ///
+    /// Fails with [`Error::Io`] if reading fails, and [`Error::SniffAborted`] if it outlasts
+    /// the [sniff timeout](crate::MimeSearcherBuilder::sniff_timeout).
+    pub fn sniff_reader<R: Read>(&self, reader: R) -> Result<Guess, Error> {
+        let driver = ReadDriver::new(Classifier::new(self))?;
+        drive(driver, reader, Error::from)
+    }
+}
+
+fn drive<R: Read>(
+    mut driver: ReadDriver<'_>,
+    mut reader: R,
+    io_error: impl Fn(std::io::Error) -> Error,
+) -> Result<Guess, Error> {
+    loop {
+        let read = match driver.next() {
+            ReadStep::Read(buffer) => match reader.read(buffer) {
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                read => read.map_err(&io_error)?,
+            },
+            ReadStep::Done(guess) => return Ok(guess),
+        };
+        driver.filled(read)?;
+    }
+}
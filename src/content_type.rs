@@ -0,0 +1,144 @@
+//! `Content-Type` header values: a mime type plus parameters.
+
+use std::collections::BTreeMap;
+
+use crate::{Error, MimeType};
+
+/// A mime type with the parameters of a `Content-Type` header, such as
+/// `text/html; charset=utf-8`.
+///
+/// The type and parameter names are case-insensitive and stored lowercased, parameter values are
+/// kept as written with any quoting removed. Two values are equal when their types and parameters
+/// are, regardless of parameter order or quoting.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ContentType {
+    mime: MimeType,
+    params: BTreeMap<String, String>,
+}
+
+impl MimeType {
+    /// Parses a `Content-Type` header value.
+    ///
+    /// Fails with [`Error::InvalidMimeType`] holding the whole value if the type or a parameter
+    /// is malformed.
+    pub fn parse_content_type(value: &str) -> Result<ContentType, Error> {
+        let invalid = || Error::InvalidMimeType(value.to_string());
+
+        let (essence, mut rest) = value.split_once(';').unwrap_or((value, ""));
+        let mime =
+            MimeType::try_from(essence.trim().to_ascii_lowercase()).map_err(|_| invalid())?;
+
+        let mut params = BTreeMap::new();
+        while !rest.trim().is_empty() {
+            let (name, after_name) = rest.split_once('=').ok_or_else(invalid)?;
+            let name = name.trim().to_ascii_lowercase();
+            if !is_token(&name) {
+                return Err(invalid());
+            }
+
+            let after_name = after_name.trim_start();
+            let (param_value, after_value) = match after_name.strip_prefix('"') {
+                Some(quoted) => parse_quoted(quoted).ok_or_else(invalid)?,
+                None => {
+                    let end = after_name.find(';').unwrap_or(after_name.len());
+                    let token = after_name[..end].trim_end();
+                    if !is_token(token) {
+                        return Err(invalid());
+                    }
+                    (token.to_string(), &after_name[end..])
+                }
+            };
+
+            let after_value = after_value.trim_start();
+            rest = match after_value.strip_prefix(';') {
+                Some(next) => next,
+                None if after_value.is_empty() => after_value,
+                None => return Err(invalid()),
+            };
+            params.insert(name, param_value);
+        }
+
+        Ok(ContentType { mime, params })
+    }
+}
+
+impl ContentType {
+    pub fn new(mime: MimeType) -> Self {
+        ContentType {
+            mime,
+            params: BTreeMap::new(),
+        }
+    }
+
+    /// Adds or replaces the parameter `name`.
+    pub fn with_param(mut self, name: &str, value: &str) -> Self {
+        self.params
+            .insert(name.to_ascii_lowercase(), value.to_string());
+        self
+    }
+
+    pub fn mime(&self) -> &MimeType {
+        &self.mime
+    }
+
+    /// The value of the parameter `name`, which is matched case-insensitively.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    /// All parameters, sorted by name.
+    pub fn params(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Formats the value for a header, quoting parameter values where needed.
+    ///
+    /// Parsing the result gives back an equal value.
+    pub fn to_header_value(&self) -> String {
+        let mut header = self.mime.to_string();
+        for (name, value) in &self.params {
+            header.push_str("; ");
+            header.push_str(name);
+            header.push('=');
+            if is_token(value) {
+                header.push_str(value);
+            } else {
+                header.push('"');
+                for c in value.chars() {
+                    if matches!(c, '"' | '\\') {
+                        header.push('\\');
+                    }
+                    header.push(c);
+                }
+                header.push('"');
+            }
+        }
+        header
+    }
+}
+
+/// Whether `s` is an RFC 7230 token and so needs no quoting.
+fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// Reads a quoted string whose opening quote has been consumed, returning its unescaped contents
+/// and whatever follows the closing quote.
+fn parse_quoted(s: &str) -> Option<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &s[i + 1..])),
+            '\\' => value.push(chars.next()?.1),
+            c => value.push(c),
+        }
+    }
+    None
+}
@@ -0,0 +1,204 @@
+//! The interface between [`MimeSearcher`](crate::MimeSearcher) and the databases it consults.
+
+use std::fmt;
+
+#[cfg(feature = "globs")]
+use crate::GlobRecord;
+use crate::{CacheSection, Error, MimeCache, MimeDatabaseModel, MimeType};
+#[cfg(feature = "magic")]
+use crate::{MagicSource, MimeTypeRef};
+
+/// A source of type definitions, such as a compiled mime.cache.
+///
+/// A searcher consults its databases most important first. Globs are merged into one index
+/// when the searcher is built, so a database's globs hide the same patterns in less important
+/// ones. The other lookups are answered by the first database that knows the type, except magic,
+/// where the highest priority match over all databases wins.
+///
+/// A [`MimeCache`] is loaded for each data directory, and a [`MimeDatabaseModel`] can stand in
+/// as an in-memory one, which is handy in tests. Add databases with
+/// [`MimeSearcherBuilder::add_database`](crate::MimeSearcherBuilder::add_database).
+pub trait MimeDatabase: fmt::Debug + Send + Sync {
+    /// Every glob the database defines. A `__NOGLOBS__` pattern deletes the type's globs from
+    /// less important databases.
+    #[cfg(feature = "globs")]
+    fn globs(&self) -> Result<Vec<GlobRecord>, Error>;
+
+    /// The number of leading bytes any magic rule can look at.
+    #[cfg(feature = "magic")]
+    fn magic_extent(&self) -> Result<usize, Error>;
+
+    /// The highest priority magic match for `data`, along with its priority, ignoring types
+    /// that `overridden` returns true for because a more important database has rules for them.
+    #[cfg(feature = "magic")]
+    fn magic_lookup(
+        &self,
+        data: &mut dyn MagicSource,
+        overridden: &dyn Fn(&str) -> Result<bool, Error>,
+    ) -> Result<Option<(MimeTypeRef<'_>, u32)>, Error>;
+
+    /// Whether the database has any magic rules for `mime`, including a `__NOMAGIC__` rule
+    /// deleting them.
+    #[cfg(feature = "magic")]
+    fn has_magic_for(&self, mime: &str) -> Result<bool, Error>;
+
+    /// The generic icon name for `mime`.
+    #[cfg(feature = "icons")]
+    fn generic_icon(&self, mime: &MimeType) -> Result<Option<String>, Error>;
+
+    /// The type `alias` is an alias of.
+    fn resolve_alias(&self, alias: &MimeType) -> Result<Option<MimeType>, Error>;
+
+    /// The direct parents of `mime`.
+    #[cfg(feature = "hierarchy")]
+    fn parents(&self, mime: &MimeType) -> Result<Vec<MimeType>, Error>;
+
+    /// Everything the database defines, as an editable model.
+    fn to_model(&self) -> Result<MimeDatabaseModel, Error>;
+
+    /// The lists of [`to_model`](Self::to_model) held by `sections`, the others possibly left
+    /// empty. The default gives the whole model.
+    ///
+    /// A [`MimeCache`] parses each section the first time it is asked for and keeps it, so
+    /// callers needing only a few lists pay for those alone, and a corrupt section only fails
+    /// the calls that ask for it.
+    fn to_model_of(&self, _sections: &[CacheSection]) -> Result<MimeDatabaseModel, Error> {
+        self.to_model()
+    }
+}
+
+impl MimeDatabase for MimeCache {
+    // The glob list, then the literal list and reverse suffix tree, so the cache alone is as
+    // complete as globs2. See model.rs for their layout.
+    #[cfg(feature = "globs")]
+    fn globs(&self) -> Result<Vec<GlobRecord>, Error> {
+        self.globs_where(|_| true)
+    }
+
+    #[cfg(feature = "magic")]
+    fn magic_extent(&self) -> Result<usize, Error> {
+        self.magic_max_extent()
+    }
+
+    #[cfg(feature = "magic")]
+    fn magic_lookup(
+        &self,
+        data: &mut dyn MagicSource,
+        overridden: &dyn Fn(&str) -> Result<bool, Error>,
+    ) -> Result<Option<(MimeTypeRef<'_>, u32)>, Error> {
+        MimeCache::magic_lookup(self, data, overridden)
+    }
+
+    #[cfg(feature = "magic")]
+    fn has_magic_for(&self, mime: &str) -> Result<bool, Error> {
+        MimeCache::has_magic_for(self, mime)
+    }
+
+    #[cfg(feature = "icons")]
+    fn generic_icon(&self, mime: &MimeType) -> Result<Option<String>, Error> {
+        match self.find_icon_for_mimetype(mime.clone()) {
+            Err(Error::NoIconFound) => Ok(None),
+            result => result.map(Some),
+        }
+    }
+
+    fn resolve_alias(&self, alias: &MimeType) -> Result<Option<MimeType>, Error> {
+        MimeCache::resolve_alias(self, alias)
+    }
+
+    #[cfg(feature = "hierarchy")]
+    fn parents(&self, mime: &MimeType) -> Result<Vec<MimeType>, Error> {
+        MimeCache::parents(self, mime)
+    }
+
+    fn to_model(&self) -> Result<MimeDatabaseModel, Error> {
+        self.to_model_of(&CacheSection::ALL)
+    }
+
+    fn to_model_of(&self, sections: &[CacheSection]) -> Result<MimeDatabaseModel, Error> {
+        let mut model = MimeDatabaseModel::default();
+        for section in CacheSection::ALL
+            .into_iter()
+            .filter(|s| sections.contains(s))
+        {
+            model.merge(self.section(section)?.clone());
+        }
+        Ok(model)
+    }
+}
+
+/// Answers lookups straight from the model's sections, without compiling it to a cache.
+impl MimeDatabase for MimeDatabaseModel {
+    #[cfg(feature = "globs")]
+    fn globs(&self) -> Result<Vec<GlobRecord>, Error> {
+        Ok([&self.globs, &self.literals, &self.suffixes]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect())
+    }
+
+    #[cfg(feature = "magic")]
+    fn magic_extent(&self) -> Result<usize, Error> {
+        Ok(self.magic.iter().map(|m| m.extent()).max().unwrap_or(0) as usize)
+    }
+
+    // Same-priority rules keep their order, as they do in a compiled cache
+    #[cfg(feature = "magic")]
+    fn magic_lookup(
+        &self,
+        data: &mut dyn MagicSource,
+        overridden: &dyn Fn(&str) -> Result<bool, Error>,
+    ) -> Result<Option<(MimeTypeRef<'_>, u32)>, Error> {
+        let mut best: Option<(MimeTypeRef<'_>, u32)> = None;
+        for rule in &self.magic {
+            if rule.is_no_magic_marker()
+                || best
+                    .as_ref()
+                    .is_some_and(|(_, best)| rule.priority <= *best)
+            {
+                continue;
+            }
+            if rule.matches(data)? && !overridden(rule.mime.essence_str())? {
+                best = Some(((&rule.mime).into(), rule.priority));
+            }
+        }
+        Ok(best)
+    }
+
+    #[cfg(feature = "magic")]
+    fn has_magic_for(&self, mime: &str) -> Result<bool, Error> {
+        Ok(self.magic.iter().any(|m| m.mime.essence_str() == mime))
+    }
+
+    #[cfg(feature = "icons")]
+    fn generic_icon(&self, mime: &MimeType) -> Result<Option<String>, Error> {
+        Ok(self
+            .generic_icons
+            .iter()
+            .find(|(m, _)| m == mime)
+            .map(|(_, icon)| icon.clone()))
+    }
+
+    fn resolve_alias(&self, alias: &MimeType) -> Result<Option<MimeType>, Error> {
+        Ok(self
+            .aliases
+            .iter()
+            .find(|(a, _)| a == alias)
+            .map(|(_, mime)| mime.clone()))
+    }
+
+    #[cfg(feature = "hierarchy")]
+    fn parents(&self, mime: &MimeType) -> Result<Vec<MimeType>, Error> {
+        Ok(self
+            .parents
+            .iter()
+            .find(|(m, _)| m == mime)
+            .map(|(_, parents)| parents.clone())
+            .unwrap_or_default())
+    }
+
+    fn to_model(&self) -> Result<MimeDatabaseModel, Error> {
+        Ok(self.clone())
+    }
+}
@@ -0,0 +1,148 @@
+//! Human-readable descriptions of types, from the per-type XML files update-mime-database
+//! writes next to mime.cache, such as `image/png.xml`.
+
+use std::{env, fs};
+
+use crate::{
+    MimeSearcher, MimeType,
+    xml::{Event, Reader},
+};
+
+impl MimeSearcher {
+    /// The description of `mime`, such as `PDF document`, in the language of the user's locale
+    /// if it has been translated into it, and untranslated otherwise.
+    ///
+    /// The locale is the first of `LC_ALL`, `LC_MESSAGES` and `LANG` that is set, as for
+    /// [`comment_for_locale`](Self::comment_for_locale).
+    pub fn comment(&self, mime: &MimeType) -> Option<String> {
+        self.comment_for_locale(mime, &user_locale())
+    }
+
+    /// The description of `mime` in `locale`, a POSIX locale name such as `de_DE.UTF-8` or
+    /// `sr_RS@latin`. Dashes are accepted in place of underscores, so `pt-BR` works too.
+    ///
+    /// Translations are tried from most to least specific, so `de_DE` falls back to `de` and
+    /// then to the untranslated description, which is also what `C` and `POSIX` get. The
+    /// description comes from `<media>/<subtype>.xml` in the most important database directory
+    /// that has one, with aliases resolved. `None` if no directory has one, which is also the
+    /// case for databases [added](crate::MimeSearcherBuilder::add_database) without a directory.
+    ///
+    /// ```no_run
+    /// # use shared_mime_info as smi;
+    /// let searcher = smi::MimeSearcher::new().unwrap();
+    /// let pdf = smi::MimeType::new("application/pdf").unwrap();
+    /// println!("{:?}", searcher.comment_for_locale(&pdf, "de_DE.UTF-8"));
+    /// ```
+    pub fn comment_for_locale(&self, mime: &MimeType, locale: &str) -> Option<String> {
+        self.localized(mime, "comment", locale)
+    }
+
+    /// The acronym the type's name is usually written as, such as `PDF`, for types that have
+    /// one. Looked up like [`comment`](Self::comment), so a translation is used if there is one.
+    pub fn acronym(&self, mime: &MimeType) -> Option<String> {
+        self.localized(mime, "acronym", &user_locale())
+    }
+
+    /// What the [`acronym`](Self::acronym) of `mime` stands for, such as
+    /// `Portable Document Format`, in the user's language if it has been translated.
+    pub fn expanded_acronym(&self, mime: &MimeType) -> Option<String> {
+        self.localized(mime, "expanded-acronym", &user_locale())
+    }
+
+    /// The text of `element` for `mime` in `locale` or the closest fallback, from the first
+    /// database directory with a file for the type.
+    fn localized(&self, mime: &MimeType, element: &str, locale: &str) -> Option<String> {
+        let mime = self.canonical(mime).unwrap_or_else(|_| mime.clone());
+        let file = format!("{}.xml", mime.subtype());
+        let translations = self.sources.iter().find_map(|source| {
+            let xml = fs::read_to_string(source.dir.join(mime.media()).join(&file)).ok()?;
+            Some(elements(&xml, element))
+        })?;
+        locale_fallbacks(locale)
+            .iter()
+            .map(Some)
+            .chain([None])
+            .find_map(|lang| {
+                translations
+                    .iter()
+                    .find(|(text_lang, _)| text_lang.as_ref() == lang)
+                    .map(|(_, text)| text.clone())
+            })
+    }
+}
+
+/// The locale messages are shown in: the first of `LC_ALL`, `LC_MESSAGES` and `LANG` that is
+/// set, or `C`.
+fn user_locale() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .find_map(|var| env::var(var).ok().filter(|locale| !locale.is_empty()))
+        .unwrap_or_else(|| "C".to_string())
+}
+
+/// The `xml:lang` values to look for in `locale`, most specific first:
+/// `de_DE.UTF-8@euro` gives `de_DE@euro`, `de_DE`, `de@euro` and `de`. Empty for `C` and
+/// `POSIX`.
+fn locale_fallbacks(locale: &str) -> Vec<String> {
+    let (locale, modifier) = match locale.split_once('@') {
+        Some((locale, modifier)) => (locale, Some(modifier)),
+        None => (locale, None),
+    };
+    let locale = locale
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .replace('-', "_");
+    if matches!(locale.as_str(), "" | "C" | "POSIX") {
+        return Vec::new();
+    }
+    let language = locale.split('_').next().unwrap_or_default();
+    let mut names = vec![locale.clone()];
+    if language != locale {
+        names.push(language.to_string());
+    }
+    let mut fallbacks = Vec::new();
+    for name in names {
+        if let Some(modifier) = modifier {
+            fallbacks.push(format!("{name}@{modifier}"));
+        }
+        fallbacks.push(name);
+    }
+    fallbacks
+}
+
+/// The text of each `element` directly inside the root `<mime-type>` of a per-type file, with
+/// its `xml:lang`. Reading stops at the first malformed part.
+fn elements(xml: &str, element: &str) -> Vec<(Option<String>, String)> {
+    let mut found = Vec::new();
+    let mut depth = 0;
+    let mut current: Option<(Option<String>, String)> = None;
+    for event in Reader::new(xml) {
+        let Ok(event) = event else {
+            break;
+        };
+        match event {
+            Event::Start { name, attributes } => {
+                depth += 1;
+                if depth == 2 && name == element {
+                    let lang = attributes
+                        .into_iter()
+                        .find_map(|(key, value)| (key == "xml:lang").then_some(value));
+                    current = Some((lang, String::new()));
+                }
+            }
+            Event::Text(text) => {
+                if let Some((_, content)) = &mut current {
+                    content.push_str(&text);
+                }
+            }
+            Event::End { .. } => {
+                depth -= 1;
+                if depth == 1 {
+                    found.extend(current.take());
+                }
+            }
+        }
+    }
+    found
+}
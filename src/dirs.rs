@@ -0,0 +1,73 @@
+//! Locating mime databases per the [XDG base directory spec](https://specifications.freedesktop.org/basedir-spec/latest/).
+
+use std::{
+    env,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+const DEFAULT_DATA_DIRS: &str = "/usr/local/share/:/usr/share/";
+
+/// Variable holding a `:` separated list of mime directories to use instead of the XDG ones.
+const MIME_DIR_VAR: &str = "SMI_MIME_DIR";
+
+/// The `mime` directories to load, most important first.
+///
+/// That is `$XDG_DATA_HOME/mime` followed by `mime` under each entry of `$XDG_DATA_DIRS`, using
+/// the spec's defaults when a variable is unset or empty. Relative paths are ignored as the spec
+/// requires. If `$SMI_MIME_DIR` is set it replaces all of that, and its entries are used as is.
+pub(crate) fn mime_dirs() -> Vec<PathBuf> {
+    mime_dirs_from(|var| env::var_os(var), home_dir().as_deref())
+}
+
+/// The user's own `mime` directory, `$XDG_DATA_HOME/mime`, if there is a home to put it in.
+#[cfg(feature = "xml")]
+pub(crate) fn user_mime_dir() -> Option<PathBuf> {
+    let var = |name: &str| env::var_os(name).filter(|v| !v.is_empty());
+    data_home_from(var, home_dir().as_deref())
+        .filter(|dir| dir.is_absolute())
+        .map(|dir| dir.join("mime"))
+}
+
+// `$HOME`, which the spec's defaults are relative to, if set
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+// `$XDG_DATA_HOME`, defaulting to `~/.local/share`
+fn data_home_from(var: impl Fn(&str) -> Option<OsString>, home: Option<&Path>) -> Option<PathBuf> {
+    var("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.map(|home| home.join(".local/share")))
+}
+
+pub(crate) fn mime_dirs_from(
+    var: impl Fn(&str) -> Option<OsString>,
+    home: Option<&Path>,
+) -> Vec<PathBuf> {
+    let var = |name: &str| var(name).filter(|v| !v.is_empty());
+
+    if let Some(dirs) = var(MIME_DIR_VAR) {
+        return env::split_paths(&dirs)
+            .filter(|dir| dir.is_absolute())
+            .collect();
+    }
+
+    let data_home = data_home_from(var, home);
+    let data_dirs = var("XDG_DATA_DIRS").unwrap_or_else(|| DEFAULT_DATA_DIRS.into());
+
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for dir in data_home
+        .into_iter()
+        .chain(env::split_paths(&data_dirs))
+        .filter(|dir| dir.is_absolute())
+        .map(|dir| dir.join("mime"))
+    {
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    dirs
+}
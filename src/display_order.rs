@@ -0,0 +1,75 @@
+//! The order settings UIs list types in.
+
+use std::cmp::Ordering;
+
+use crate::MimeType;
+
+/// Orders types for display: grouped by media class (`application`, `audio`, `image`, ...) in
+/// alphabetical order, then by description, then by name so that types sharing a description
+/// still sort the same way every time.
+///
+/// [`new`](Self::new) compares descriptions ignoring case. With the `collation` feature,
+/// [`for_locale`](Self::for_locale) compares them the way the locale sorts words instead, so
+/// `Éditeur` sorts among the `E`s in French and `ä` after `z` in Swedish.
+///
+/// ```
+/// # use shared_mime_info as smi;
+/// let order = smi::DisplayOrder::new();
+/// let mut types = vec![
+///     (smi::MimeType::TEXT_PLAIN, "plain text"),
+///     (smi::MimeType::IMAGE_PNG, "PNG image"),
+///     (smi::MimeType::IMAGE_GIF, "GIF image"),
+/// ];
+/// types.sort_by(|a, b| order.compare((&a.0, a.1), (&b.0, b.1)));
+/// assert_eq!(types[0].1, "GIF image");
+/// ```
+#[derive(Debug, Default)]
+pub struct DisplayOrder {
+    #[cfg(feature = "collation")]
+    collator: Option<icu_collator::Collator>,
+}
+
+impl DisplayOrder {
+    /// Compares descriptions case insensitively, by character.
+    pub fn new() -> Self {
+        DisplayOrder::default()
+    }
+
+    /// Compares descriptions with the collation rules of `locale`, a BCP 47 tag such as `de` or
+    /// `sv-SE`. `None` if the tag can't be parsed. Locales without rules of their own use the
+    /// Unicode root collation.
+    #[cfg(feature = "collation")]
+    pub fn for_locale(locale: &str) -> Option<Self> {
+        let locale: icu_locid::Locale = locale.parse().ok()?;
+        let collator = icu_collator::Collator::try_new(
+            &(&locale).into(),
+            icu_collator::CollatorOptions::new(),
+        )
+        .ok()?;
+        Some(DisplayOrder {
+            collator: Some(collator),
+        })
+    }
+
+    /// Compares two types, each given with its description.
+    pub fn compare(
+        &self,
+        (a, a_description): (&MimeType, &str),
+        (b, b_description): (&MimeType, &str),
+    ) -> Ordering {
+        a.media()
+            .cmp(b.media())
+            .then_with(|| self.compare_descriptions(a_description, b_description))
+            .then_with(|| a.cmp(b))
+    }
+
+    fn compare_descriptions(&self, a: &str, b: &str) -> Ordering {
+        #[cfg(feature = "collation")]
+        if let Some(collator) = &self.collator {
+            return collator.compare(a, b);
+        }
+        let folded_a = a.chars().flat_map(char::to_lowercase);
+        let folded_b = b.chars().flat_map(char::to_lowercase);
+        folded_a.cmp(folded_b).then_with(|| a.cmp(b))
+    }
+}
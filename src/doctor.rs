@@ -0,0 +1,318 @@
+//! Checking a database directory for the problems that quietly make lookups wrong.
+
+use std::{
+    collections::HashSet,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    Error, MimeCache, MimeDatabaseModel, MimeType, dirs,
+    packages::{package_files, read_package},
+};
+
+/// What [`doctor`] found wrong with a database directory, and whether it fixed it.
+///
+/// Displays as the directory followed by one line per problem, for printing from a CLI.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub struct DoctorReport {
+    pub dir: PathBuf,
+    pub problems: Vec<Problem>,
+    /// Whether mime.cache, globs2 and types were rewritten from the packages.
+    pub regenerated: bool,
+}
+
+/// A problem with a database directory.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum Problem {
+    /// Packages are installed but were never compiled into a mime.cache.
+    MissingCache,
+    /// mime.cache is older than a package or the `packages` directory, so it misses changes.
+    StaleCache,
+    /// mime.cache can't be parsed.
+    CorruptCache(Error),
+    /// The package at `path` defines types that mime.cache knows nothing about, usually because
+    /// it was added without running update-mime-database.
+    OrphanedPackage {
+        path: PathBuf,
+        missing: Vec<MimeType>,
+    },
+    /// A package is malformed, as an [`Error::InvalidPackage`].
+    InvalidPackage(Error),
+    /// A file or directory exists but couldn't be read.
+    Unreadable { path: PathBuf, error: Error },
+    /// Regenerating was asked for and needed, but writing the files failed.
+    RegenerationFailed(Error),
+}
+
+/// The files regenerating writes, replacing whatever was there.
+const REGENERATED_FILES: [&str; 3] = ["mime.cache", "globs2", "types"];
+
+impl DoctorReport {
+    /// Whether nothing is wrong, or everything that was wrong has been regenerated away.
+    ///
+    /// Malformed packages, and files that couldn't be read other than those regenerating
+    /// replaces, count even after regenerating.
+    pub fn is_healthy(&self) -> bool {
+        self.problems
+            .iter()
+            .all(|problem| self.regenerated && problem.fixed_by_regenerating(&self.dir))
+    }
+}
+
+impl Problem {
+    /// Whether the cache misses what the packages define, which regenerating fixes.
+    fn is_outdated(&self) -> bool {
+        matches!(
+            self,
+            Problem::MissingCache
+                | Problem::StaleCache
+                | Problem::CorruptCache(_)
+                | Problem::OrphanedPackage { .. }
+        )
+    }
+
+    fn fixed_by_regenerating(&self, dir: &Path) -> bool {
+        match self {
+            Problem::Unreadable { path, .. } => {
+                REGENERATED_FILES.iter().any(|name| *path == dir.join(name))
+            }
+            problem => problem.is_outdated(),
+        }
+    }
+}
+
+/// Checks the user's own database at `$XDG_DATA_HOME/mime` (`~/.local/share/mime` by default),
+/// the one applications install types into without root and forget to recompile. See
+/// [`doctor_dir`].
+///
+/// Fails with [`Error::MimeCacheNotFound`] if there is no home directory to find it in.
+///
+/// ```no_run
+/// let report = shared_mime_info::doctor(false).unwrap();
+/// if !report.is_healthy() {
+///     eprintln!("{report}");
+/// }
+/// ```
+pub fn doctor(regenerate: bool) -> Result<DoctorReport, Error> {
+    let dir = dirs::user_mime_dir().ok_or(Error::MimeCacheNotFound)?;
+    Ok(doctor_dir(&dir, regenerate))
+}
+
+/// Checks the database directory `dir` for a missing, stale or corrupt mime.cache, packages it
+/// doesn't cover, malformed packages and unreadable files.
+///
+/// With `regenerate`, a cache that is missing, stale, corrupt or incomplete is compiled afresh
+/// from the packages with [`MimeDatabaseModel::from_packages`], and written along with globs2
+/// and types as update-mime-database would. Nothing is written while any package is malformed.
+/// A directory that doesn't exist has no problems.
+pub fn doctor_dir(dir: &Path, regenerate: bool) -> DoctorReport {
+    let mut problems = Vec::new();
+    let mut unreadable = |path: &Path, e: io::Error| {
+        problems.push(Problem::Unreadable {
+            path: path.to_path_buf(),
+            error: Error::io(path, e),
+        })
+    };
+
+    let mut compiled = MimeDatabaseModel::default();
+    let mut packages = Vec::new();
+    let mut sound = true;
+    match package_files(dir) {
+        Ok(files) => {
+            for path in files {
+                match fs::read_to_string(&path) {
+                    Ok(xml) => packages.push((path, xml)),
+                    Err(e) => {
+                        unreadable(&path, e);
+                        sound = false;
+                    }
+                }
+            }
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => {
+            unreadable(&dir.join("packages"), e);
+            sound = false;
+        }
+    }
+
+    let cache_path = dir.join("mime.cache");
+    let cache = match fs::read(&cache_path) {
+        Ok(bytes) => Some(bytes),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => {
+            unreadable(&cache_path, e);
+            None
+        }
+    };
+    for name in ["globs2", "types"] {
+        let path = dir.join(name);
+        if let Err(e) = fs::File::open(&path) {
+            if e.kind() != io::ErrorKind::NotFound {
+                unreadable(&path, e);
+            }
+        }
+    }
+
+    let mut defined = Vec::new();
+    for (path, xml) in &packages {
+        match read_package(xml, &mut compiled) {
+            Ok(types) => defined.push((path, types)),
+            Err((line, problem)) => {
+                problems.push(Problem::InvalidPackage(Error::InvalidPackage {
+                    path: path.clone(),
+                    line,
+                    problem,
+                }));
+                sound = false;
+            }
+        }
+    }
+
+    let cached = cache.as_deref().map(MimeDatabaseModel::from_cache_bytes);
+    match &cached {
+        None if !packages.is_empty() && !cache_path.exists() => {
+            problems.push(Problem::MissingCache)
+        }
+        None => {}
+        Some(Err(e)) => problems.push(Problem::CorruptCache(e.clone())),
+        Some(Ok(model)) => {
+            if MimeCache::is_stale(dir) {
+                problems.push(Problem::StaleCache);
+            }
+            let known = known_types(model, &dir.join("types"));
+            for (path, types) in defined {
+                let missing: Vec<_> = types.into_iter().filter(|t| !known.contains(t)).collect();
+                if !missing.is_empty() {
+                    problems.push(Problem::OrphanedPackage {
+                        path: path.clone(),
+                        missing,
+                    });
+                }
+            }
+        }
+    }
+
+    let outdated = problems.iter().any(Problem::is_outdated);
+    let mut regenerated = false;
+    if regenerate && outdated && sound {
+        match write_database(dir, &compiled) {
+            Ok(()) => regenerated = true,
+            Err(e) => problems.push(Problem::RegenerationFailed(e)),
+        }
+    }
+    DoctorReport {
+        dir: dir.to_path_buf(),
+        problems,
+        regenerated,
+    }
+}
+
+/// Every type the cache, or the types list written next to it, mentions.
+fn known_types(model: &MimeDatabaseModel, types_file: &Path) -> HashSet<MimeType> {
+    let mut known = mentioned_types(model);
+    let listed = fs::read_to_string(types_file).unwrap_or_default();
+    known.extend(
+        listed
+            .lines()
+            .filter_map(|line| MimeType::new(line.trim()).ok()),
+    );
+    known
+}
+
+/// Every type with a glob, magic rule, alias, parent, namespace or icon in `model`.
+fn mentioned_types(model: &MimeDatabaseModel) -> HashSet<MimeType> {
+    let mut known = HashSet::new();
+    let globs = [&model.literals, &model.suffixes, &model.globs];
+    known.extend(globs.into_iter().flatten().map(|glob| glob.mime.clone()));
+    known.extend(model.magic.iter().map(|rule| rule.mime.clone()));
+    known.extend(model.aliases.iter().map(|(_, mime)| mime.clone()));
+    known.extend(model.parents.iter().map(|(mime, _)| mime.clone()));
+    known.extend(model.namespaces.iter().map(|ns| ns.mime.clone()));
+    known.extend(model.icons.iter().map(|(mime, _)| mime.clone()));
+    known.extend(model.generic_icons.iter().map(|(mime, _)| mime.clone()));
+    known
+}
+
+// Writes mime.cache, globs2 and types for `model`, each through a temporary file renamed into
+// place so that readers never see half of one
+fn write_database(dir: &Path, model: &MimeDatabaseModel) -> Result<(), Error> {
+    let mut globs: Vec<_> = [&model.literals, &model.suffixes, &model.globs]
+        .into_iter()
+        .flatten()
+        .collect();
+    globs.sort_by(|a, b| b.weight.cmp(&a.weight).then_with(|| a.cmp(b)));
+    let mut globs2 = String::from("# This file was automatically generated from packages\n");
+    for glob in globs {
+        let flags = if glob.case_sensitive { ":cs" } else { "" };
+        globs2.push_str(&format!(
+            "{}:{}:{}{flags}\n",
+            glob.weight, glob.mime, glob.pattern
+        ));
+    }
+
+    let mut types: Vec<MimeType> = mentioned_types(model).into_iter().collect();
+    types.sort();
+    let types: String = types.iter().map(|mime| format!("{mime}\n")).collect();
+
+    let contents = [
+        model.to_cache_bytes(),
+        globs2.into_bytes(),
+        types.into_bytes(),
+    ];
+    for (name, contents) in REGENERATED_FILES.into_iter().zip(contents) {
+        let path = dir.join(name);
+        let temporary = dir.join(format!("{name}.new"));
+        fs::write(&temporary, contents)
+            .and_then(|()| fs::rename(&temporary, &path))
+            .map_err(|e| Error::io(&path, e))?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for DoctorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.dir.display())?;
+        match self.problems.len() {
+            0 => return f.write_str("no problems found"),
+            1 => f.write_str("1 problem")?,
+            n => write!(f, "{n} problems")?,
+        }
+        for problem in &self.problems {
+            write!(f, "\n  {problem}")?;
+        }
+        if self.regenerated {
+            f.write_str("\nregenerated mime.cache from the packages")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::MissingCache => {
+                f.write_str("packages are installed but there's no mime.cache")
+            }
+            Problem::StaleCache => f.write_str("mime.cache is older than its packages"),
+            Problem::CorruptCache(e) => write!(f, "{e}"),
+            Problem::OrphanedPackage { path, missing } => {
+                write!(
+                    f,
+                    "{} defines types missing from mime.cache:",
+                    path.display()
+                )?;
+                for (i, mime) in missing.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { "," };
+                    write!(f, "{separator} {mime}")?;
+                }
+                Ok(())
+            }
+            Problem::InvalidPackage(e) | Problem::Unreadable { error: e, .. } => write!(f, "{e}"),
+            Problem::RegenerationFailed(e) => write!(f, "couldn't regenerate mime.cache: {e}"),
+        }
+    }
+}
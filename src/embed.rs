@@ -0,0 +1,65 @@
+//! Databases baked into the program at build time, for systems without `/usr/share/mime`.
+
+#[cfg(feature = "codegen")]
+use std::{fs, path::Path};
+
+use crate::{Error, MimeCache, MimeSearcher};
+#[cfg(feature = "codegen")]
+use crate::{MimeDatabaseModel, MimeType};
+
+impl MimeSearcher {
+    /// A searcher using only the mime.cache in `cache`, typically one written by
+    /// [`embed_database`] in a build script and baked in with [`include_bytes!`].
+    ///
+    /// No data directory is read, not even those in `$SMI_MIME_DIR`, so lookups give the same
+    /// answers on every machine, including containers and embedded devices without a database
+    /// installed. Lookups read the tables in place, so the cache is never copied.
+    ///
+    /// ```ignore
+    /// # use shared_mime_info as smi;
+    /// static MIME_CACHE: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/mime.cache"));
+    ///
+    /// let searcher = smi::MimeSearcher::embedded(MIME_CACHE).unwrap();
+    /// ```
+    ///
+    /// Fails with [`Error::MissingHeader`] or [`Error::UnsupportedCacheVersion`] if `cache`
+    /// isn't a mime.cache this crate can read.
+    pub fn embedded(cache: &'static [u8]) -> Result<MimeSearcher, Error> {
+        MimeSearcher::builder()
+            .data_dirs(std::iter::empty::<std::path::PathBuf>())
+            .add_database(MimeCache::from_storage(cache)?)
+            .build()
+    }
+}
+
+/// Writes the database of the directory `dir`, such as `/usr/share/mime`, to the file `out` for
+/// [`MimeSearcher::embedded`], keeping only `types` if given.
+///
+/// Meant for build scripts: it tells cargo to rerun the script when the database changes. The
+/// cache is parsed and compiled again rather than copied, so a corrupt one fails the build, and
+/// the same database always gives the same bytes. Keeping only the types a program deals with,
+/// as [`MimeDatabaseModel::extract_subset`] does, shrinks the binary.
+///
+/// ```no_run
+/// // build.rs
+/// # use shared_mime_info as smi;
+/// let out = std::path::PathBuf::from(std::env::var_os("OUT_DIR").unwrap()).join("mime.cache");
+/// smi::embed_database("/usr/share/mime".as_ref(), None, &out).unwrap();
+/// ```
+///
+/// Fails with [`Error::MimeCacheNotFound`] if `dir` has no mime.cache, and [`Error::Io`] if
+/// `out` can't be written.
+#[cfg(feature = "codegen")]
+pub fn embed_database(dir: &Path, types: Option<&[MimeType]>, out: &Path) -> Result<(), Error> {
+    println!(
+        "cargo:rerun-if-changed={}",
+        dir.join("mime.cache").display()
+    );
+    let cache = MimeCache::load(dir)?;
+    let model = MimeDatabaseModel::from_cache_bytes(cache.bytes())?;
+    let model = match types {
+        Some(types) => model.extract_subset(types),
+        None => model,
+    };
+    fs::write(out, model.to_cache_bytes()).map_err(|e| Error::io(out, e))
+}
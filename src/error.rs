@@ -0,0 +1,305 @@
+//! The crate's error type.
+
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::InvalidMimeType;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum Error {
+    /// None of the database directories has a mime.cache or globs2, and the `bundled` feature
+    /// is off.
+    MimeCacheNotFound,
+    Globs2NotFound,
+    Globs2BadLine(String),
+    InvalidGlob,
+    NotANumber,
+    MissingHeader,
+    MissingGenericIconsList,
+    NoIconFound,
+    CstrUnterminated,
+    InvalidUTF8,
+    /// Reading a file failed for a reason other than it not existing where that is expected.
+    Io(IoError),
+    /// A string that is not a `media/subtype` pair was used as a mime type.
+    InvalidMimeType(String),
+    /// The mime.cache has a version whose layout is unknown.
+    UnsupportedCacheVersion {
+        major: u16,
+        minor: u16,
+    },
+    /// The mime.cache is damaged at byte `offset`, while reading `section`, and `entry` of it
+    /// where the section is a list.
+    CorruptCache {
+        section: CacheSection,
+        entry: Option<usize>,
+        offset: usize,
+        problem: Corruption,
+    },
+    /// A package file under `packages/` is malformed, at `line`.
+    InvalidPackage {
+        path: PathBuf,
+        line: usize,
+        problem: String,
+    },
+    /// Reading a file or stream to sniff took longer than the
+    /// [sniff timeout](crate::MimeSearcherBuilder::sniff_timeout), and was given up after
+    /// `read` bytes. Unlike a stream whose contents match nothing, this says nothing about the
+    /// type.
+    SniffAborted {
+        read: usize,
+    },
+    /// Several database files failed to load, each with its own error, in search path order.
+    /// Never nested, and never holds fewer than two errors.
+    Multiple(Vec<Error>),
+}
+
+/// A part of a mime.cache, as laid out in its header.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub enum CacheSection {
+    AliasList,
+    ParentList,
+    LiteralList,
+    ReverseSuffixTree,
+    GlobList,
+    MagicList,
+    NamespaceList,
+    IconsList,
+    GenericIconsList,
+}
+
+/// What is wrong with a mime.cache at the offset named by [`Error::CorruptCache`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub enum Corruption {
+    /// Data is referred to past the end of the file, usually because it was truncated.
+    OutOfRange,
+    UnterminatedString,
+    InvalidUtf8,
+    /// A suffix tree character that is not a unicode scalar value.
+    InvalidCharacter,
+    /// The suffix tree or magic rules nest implausibly deep, usually because an entry refers
+    /// back to one of its parents.
+    TooDeep,
+}
+
+/// An [`io::Error`], shared so that [`Error`] stays cheap to clone. Two of these compare equal
+/// if their kinds do.
+#[derive(Debug, Clone)]
+pub struct IoError(Arc<io::Error>);
+
+impl Error {
+    /// Wraps an error from reading `path`, naming the path in the message.
+    pub(crate) fn io(path: &Path, error: io::Error) -> Self {
+        let error = io::Error::new(error.kind(), format!("{}: {error}", path.display()));
+        Error::Io(IoError(Arc::new(error)))
+    }
+
+    /// One error for the failures of independent sources: `None` if there were none, the error
+    /// itself if there was one, and [`Error::Multiple`] otherwise.
+    pub(crate) fn combine(errors: impl IntoIterator<Item = Error>) -> Option<Self> {
+        let mut errors: Vec<Error> = errors
+            .into_iter()
+            .flat_map(|error| match error {
+                Error::Multiple(errors) => errors,
+                error => vec![error],
+            })
+            .collect();
+        match errors.len() {
+            0 => None,
+            1 => errors.pop(),
+            _ => Some(Error::Multiple(errors)),
+        }
+    }
+
+    /// The individual errors, which is just this one unless it is [`Error::Multiple`].
+    pub fn errors(&self) -> &[Error] {
+        match self {
+            Error::Multiple(errors) => errors,
+            error => std::slice::from_ref(error),
+        }
+    }
+
+    /// Whether a database or file was missing. For [`Error::Multiple`], whether any was.
+    pub fn is_not_found(&self) -> bool {
+        self.errors().iter().any(|error| match error {
+            Error::MimeCacheNotFound | Error::Globs2NotFound => true,
+            Error::Io(e) => e.kind() == io::ErrorKind::NotFound,
+            _ => false,
+        })
+    }
+
+    /// Whether a database or file could not be read for lack of permissions. For
+    /// [`Error::Multiple`], whether any couldn't.
+    pub fn is_permission_denied(&self) -> bool {
+        self.errors().iter().any(
+            |error| matches!(error, Error::Io(e) if e.kind() == io::ErrorKind::PermissionDenied),
+        )
+    }
+
+    /// Whether a database or argument was read but is malformed. For [`Error::Multiple`],
+    /// whether any was.
+    pub fn is_parse_failure(&self) -> bool {
+        self.errors().iter().any(Error::is_single_parse_failure)
+    }
+
+    fn is_single_parse_failure(&self) -> bool {
+        matches!(
+            self,
+            Error::Globs2BadLine(_)
+                | Error::InvalidGlob
+                | Error::NotANumber
+                | Error::MissingHeader
+                | Error::MissingGenericIconsList
+                | Error::CstrUnterminated
+                | Error::InvalidUTF8
+                | Error::InvalidMimeType(_)
+                | Error::UnsupportedCacheVersion { .. }
+                | Error::CorruptCache { .. }
+                | Error::InvalidPackage { .. }
+        )
+    }
+}
+
+/// For errors from sources that have no path, such as a
+/// [`RangeReader`](crate::RangeReader).
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(IoError(Arc::new(error)))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::MimeCacheNotFound => f.write_str("no mime database found"),
+            Error::Globs2NotFound => f.write_str("no globs2 file found"),
+            Error::Globs2BadLine(line) => write!(f, "malformed globs2 line `{line}`"),
+            Error::InvalidGlob => f.write_str("invalid glob pattern"),
+            Error::NotANumber => f.write_str("glob weight is not a number"),
+            Error::MissingHeader => f.write_str("mime.cache is too short to have a header"),
+            Error::MissingGenericIconsList => f.write_str("mime.cache has no generic icons list"),
+            Error::NoIconFound => f.write_str("no icon for this type"),
+            Error::CstrUnterminated => f.write_str("mime.cache has an unterminated string"),
+            Error::InvalidUTF8 => f.write_str("mime.cache has a string that is not UTF-8"),
+            Error::Io(e) => e.0.fmt(f),
+            Error::InvalidMimeType(mime) => write!(f, "`{mime}` is not a valid mime type"),
+            Error::UnsupportedCacheVersion { major, minor } => {
+                write!(f, "unsupported mime.cache version {major}.{minor}")
+            }
+            Error::CorruptCache {
+                section,
+                entry,
+                offset,
+                problem,
+            } => {
+                write!(
+                    f,
+                    "mime.cache is corrupt: {problem} at offset {offset:#x} in {section}"
+                )?;
+                match entry {
+                    Some(entry) => write!(f, " entry {entry}"),
+                    None => Ok(()),
+                }
+            }
+            Error::InvalidPackage {
+                path,
+                line,
+                problem,
+            } => write!(f, "{}:{line}: {problem}", path.display()),
+            Error::SniffAborted { read } => {
+                write!(f, "sniffing timed out after reading {read} bytes")
+            }
+            Error::Multiple(errors) => {
+                write!(f, "{} database files failed to load", errors.len())?;
+                for error in errors {
+                    write!(f, "; {error}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl CacheSection {
+    /// Every section, in the order the header lists them.
+    pub(crate) const ALL: [CacheSection; 9] = [
+        CacheSection::AliasList,
+        CacheSection::ParentList,
+        CacheSection::LiteralList,
+        CacheSection::ReverseSuffixTree,
+        CacheSection::GlobList,
+        CacheSection::MagicList,
+        CacheSection::NamespaceList,
+        CacheSection::IconsList,
+        CacheSection::GenericIconsList,
+    ];
+}
+
+impl fmt::Display for CacheSection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CacheSection::AliasList => "the alias list",
+            CacheSection::ParentList => "the parent list",
+            CacheSection::LiteralList => "the literal list",
+            CacheSection::ReverseSuffixTree => "the suffix tree",
+            CacheSection::GlobList => "the glob list",
+            CacheSection::MagicList => "the magic list",
+            CacheSection::NamespaceList => "the namespace list",
+            CacheSection::IconsList => "the icons list",
+            CacheSection::GenericIconsList => "the generic icons list",
+        })
+    }
+}
+
+impl fmt::Display for Corruption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Corruption::OutOfRange => "offset past the end of the file",
+            Corruption::UnterminatedString => "unterminated string",
+            Corruption::InvalidUtf8 => "string that is not UTF-8",
+            Corruption::InvalidCharacter => "invalid character",
+            Corruption::TooDeep => "entries nested too deeply",
+        })
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(&*e.0),
+            _ => None,
+        }
+    }
+}
+
+impl IoError {
+    pub fn kind(&self) -> io::ErrorKind {
+        self.0.kind()
+    }
+
+    pub fn get_ref(&self) -> &io::Error {
+        &self.0
+    }
+}
+
+impl PartialEq for IoError {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind() == other.kind()
+    }
+}
+
+impl Eq for IoError {}
+
+impl fmt::Display for InvalidMimeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` is not a valid mime type", self.0)
+    }
+}
+
+impl std::error::Error for InvalidMimeType {}
@@ -0,0 +1,163 @@
+//! Tracing how a file's type was arrived at.
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{ConflictPolicy, DetectionMethod, Error, Guess, MimeSearcher, MimeType, inode};
+
+/// What [`MimeSearcher::explain`] found: the steps [`MimeSearcher::guess`] took, in order, and
+/// the guess they led to.
+///
+/// Displays as one numbered line per step followed by the result, for printing from a CLI.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Explanation {
+    pub steps: Vec<Step>,
+    pub guess: Guess,
+}
+
+/// One step of a [`MimeSearcher::guess`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Step {
+    /// A glob matched the file name. `source` is the mime.cache or globs2 defining it, `None`
+    /// for a database added without a file.
+    GlobMatched {
+        pattern: String,
+        mime: MimeType,
+        weight: u8,
+        source: Option<PathBuf>,
+    },
+    /// No glob matched the file name.
+    NoGlobMatched,
+    /// No database is installed, and the table of the `mime_guess` crate gave `mime` for the
+    /// file's extension.
+    ExtensionTableMatched { mime: MimeType },
+    /// These types share the highest weight for the file's extension, so the name alone can't
+    /// decide.
+    AmbiguousName { candidates: Vec<MimeType> },
+    /// A magic rule with this priority matched the contents. `mime` is after any
+    /// [`ContentProbe`](crate::ContentProbe) refined the match.
+    MagicMatched { mime: MimeType, priority: u32 },
+    /// No magic rule matched the contents.
+    NoMagicMatched,
+    /// The name and contents pointed at different types and `policy` picked `winner`.
+    ConflictResolved {
+        policy: ConflictPolicy,
+        winner: DetectionMethod,
+    },
+    /// Nothing matched, and the contents did or didn't look like text.
+    Fallback { text: bool },
+    /// Text heuristics took `clue`, such as the interpreter of a `#!` line, to mean `mime`.
+    Heuristic { mime: MimeType, clue: String },
+    /// The path is a directory, FIFO, socket or device rather than a regular file, so it wasn't
+    /// read and its type is `mime`.
+    NotRegularFile { mime: MimeType },
+}
+
+impl MimeSearcher {
+    /// Explains how [`guess`](Self::guess) classifies the file at `path`, reading its first
+    /// [`max_magic_extent`](Self::max_magic_extent) bytes.
+    ///
+    /// The steps show which glob matched, with its weight and the file defining it, whether the
+    /// contents were sniffed and what matched, and how the winner was picked. A path that isn't
+    /// a regular file isn't read, and gets its `inode/*` type. Fails with [`Error::Io`] if the
+    /// file can't be read.
+    ///
+    /// ```no_run
+    /// # use shared_mime_info as smi;
+    /// let searcher = smi::MimeSearcher::new().unwrap();
+    /// println!("{}", searcher.explain("report.pdf".as_ref()).unwrap());
+    /// ```
+    pub fn explain(&self, path: &Path) -> Result<Explanation, Error> {
+        let metadata = fs::metadata(path).map_err(|e| Error::io(path, e))?;
+        if let Some(mime) = inode::non_regular_type(&metadata) {
+            return Ok(Explanation {
+                steps: vec![Step::NotRegularFile { mime: mime.clone() }],
+                guess: Guess::new(mime, DetectionMethod::Inode),
+            });
+        }
+        let mut data = Vec::new();
+        self.read_head(path, &mut data)?;
+        self.explain_data(path, &data)
+    }
+
+    /// Explains how [`guess`](Self::guess) classifies a file named `path` with contents
+    /// starting with `data`, without touching the file system.
+    pub fn explain_data(&self, path: &Path, data: &[u8]) -> Result<Explanation, Error> {
+        let mut steps = Vec::new();
+        let guess = self.guess_traced(path, data, Some(&mut steps))?;
+        Ok(Explanation { steps, guess })
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, step) in self.steps.iter().enumerate() {
+            writeln!(f, "{}. {step}", i + 1)?;
+        }
+        let certainty = if self.guess.certain {
+            ""
+        } else {
+            ", uncertain"
+        };
+        write!(
+            f,
+            "=> {} (by {:?}{certainty})",
+            self.guess.mime, self.guess.method
+        )
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::GlobMatched {
+                pattern,
+                mime,
+                weight,
+                source,
+            } => {
+                write!(f, "glob `{pattern}` matched {mime} with weight {weight}")?;
+                match source {
+                    Some(source) => write!(f, ", from {}", source.display()),
+                    None => f.write_str(", from an added database"),
+                }
+            }
+            Step::NoGlobMatched => f.write_str("no glob matched the name"),
+            Step::ExtensionTableMatched { mime } => {
+                write!(
+                    f,
+                    "no database is installed, mime_guess's table gave {mime}"
+                )
+            }
+            Step::AmbiguousName { candidates } => {
+                f.write_str("the name is ambiguous between")?;
+                for (i, mime) in candidates.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { "," };
+                    write!(f, "{separator} {mime}")?;
+                }
+                Ok(())
+            }
+            Step::MagicMatched { mime, priority } => {
+                write!(f, "magic matched {mime} with priority {priority}")
+            }
+            Step::NoMagicMatched => f.write_str("no magic rule matched the contents"),
+            Step::ConflictResolved { policy, winner } => {
+                write!(f, "name and contents disagree, {policy:?} picks {winner:?}")
+            }
+            Step::Fallback { text: true } => {
+                f.write_str("nothing matched, contents look like text")
+            }
+            Step::Fallback { text: false } => {
+                f.write_str("nothing matched, contents don't look like text")
+            }
+            Step::Heuristic { mime, clue } => write!(f, "heuristically {mime}, as the {clue}"),
+            Step::NotRegularFile { mime } => write!(f, "not a regular file, so not read: {mime}"),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Going from a type back to the file extensions that name it.
+
+use std::collections::HashSet;
+
+use crate::{MimeSearcher, MimeType};
+
+/// The extension files of a type conventionally get, for types whose equally weighted globs
+/// include older, rarer or platform-specific ones that
+/// [`preferred_extension`](MimeSearcher::preferred_extension) would otherwise pick. Sorted by
+/// type, and only canonical names are listed.
+const CONVENTIONAL_EXTENSIONS: &[(&str, &str)] = &[
+    ("application/javascript", "js"),
+    ("application/vnd.ms-excel", "xls"),
+    ("application/x-compressed-tar", "tar.gz"),
+    ("application/x-perl", "pl"),
+    ("application/x-yaml", "yaml"),
+    ("application/xml", "xml"),
+    ("audio/midi", "mid"),
+    ("audio/mp4", "m4a"),
+    ("audio/mpeg", "mp3"),
+    ("audio/ogg", "ogg"),
+    ("audio/x-vorbis+ogg", "ogg"),
+    ("image/jpeg", "jpg"),
+    ("text/html", "html"),
+    ("text/plain", "txt"),
+    ("text/x-c++hdr", "hpp"),
+    ("text/x-c++src", "cpp"),
+    ("text/x-tex", "tex"),
+    ("video/mp4", "mp4"),
+    ("video/mpeg", "mpeg"),
+    ("video/quicktime", "mov"),
+];
+
+impl MimeSearcher {
+    /// The extensions, without the leading dot, that file name lookups associate with `mime`,
+    /// highest glob weight first and then by name. Aliases are resolved, so `image/jpg` gives
+    /// the extensions of `image/jpeg`.
+    ///
+    /// Derived from the same `*.ext` globs as the lookups, so extensions a more important
+    /// database takes over or deletes are left out. Extensions shared with other types are
+    /// included; [`types_for_extension`](Self::types_for_extension) tells whether `mime` wins
+    /// them. Globs that aren't plain extensions, like `Makefile` or `*.[1-9]`, are not.
+    pub fn extensions_for(&self, mime: &MimeType) -> Vec<String> {
+        let mime = self.canonical(mime).unwrap_or_else(|_| mime.clone());
+        self.weighted_extensions(&mime)
+            .into_iter()
+            .map(|(_, ext)| ext)
+            .collect()
+    }
+
+    /// The one extension to give a file of type `mime`, such as `jpg` for `image/jpeg`, for
+    /// save dialogs and downloads named after a `Content-Type`. `None` if no `*.ext` glob maps
+    /// to the type.
+    ///
+    /// Of the extensions with the highest glob weight, the conventional one for well known types
+    /// wins, then the simplest: one made of letters and digits only, as short as possible,
+    /// with ties going to the first by name.
+    pub fn preferred_extension(&self, mime: &MimeType) -> Option<String> {
+        let mime = self.canonical(mime).unwrap_or_else(|_| mime.clone());
+        let conventional = CONVENTIONAL_EXTENSIONS
+            .binary_search_by_key(&mime.essence_str(), |(mime, _)| mime)
+            .ok()
+            .map(|i| CONVENTIONAL_EXTENSIONS[i].1);
+        let extensions = self.weighted_extensions(&mime);
+        let top = extensions.first()?.0;
+        extensions
+            .into_iter()
+            .take_while(|(weight, _)| *weight == top)
+            .map(|(_, ext)| ext)
+            .min_by_key(|ext| {
+                let simple = ext.bytes().all(|b| b.is_ascii_alphanumeric());
+                (Some(ext.as_str()) != conventional, !simple, ext.len())
+            })
+    }
+
+    // The extensions lookups associate with the canonical type `mime`, along with their
+    // weights, highest first and then by name
+    fn weighted_extensions(&self, mime: &MimeType) -> Vec<(u8, String)> {
+        let mut seen = HashSet::new();
+        let mut extensions = Vec::new();
+        for layer in self.globber().layers() {
+            let cached = layer.caches.iter().flat_map(|layer| {
+                // A corrupt tree has no extensions to list, as lookups find none in it either
+                layer.cache.extensions().unwrap_or_default()
+            });
+            for ext in layer.indexed_extensions().map(str::to_string).chain(cached) {
+                let ext = ext.to_ascii_lowercase();
+                if !seen.insert(ext.clone()) {
+                    continue;
+                }
+                let entries = self.globber().lookup_extension(&ext);
+                if let Some(entry) = entries.into_iter().find(|entry| entry.mime == *mime) {
+                    extensions.push((entry.weight, ext));
+                }
+            }
+        }
+        extensions.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        extensions
+    }
+}
@@ -0,0 +1,378 @@
+//! Combining file name and content evidence into a single answer.
+
+use std::path::Path;
+
+use crate::{Error, MimeSearcher, MimeType, Step, heuristics};
+
+/// The type [`MimeSearcher::guess`] settled on for a file.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Guess {
+    pub mime: MimeType,
+    /// Which evidence decided the type.
+    pub method: DetectionMethod,
+    /// What the other kind of evidence said, when it disagreed and lost under the searcher's
+    /// [`ConflictPolicy`].
+    pub overruled: Option<Evidence>,
+    /// Whether the evidence agreed on one type, like the inverse of the `result_uncertain` flag
+    /// of GIO's `g_content_type_guess`. A guess is uncertain when it is a
+    /// [`Fallback`](DetectionMethod::Fallback), when the name and contents disagreed, and when
+    /// only the name was matched but other types share its weight. A name whose type subclasses
+    /// the contents' type, as a .docx does the zip its contents show, agrees with them. Callers
+    /// can save deeper inspection for uncertain guesses.
+    pub certain: bool,
+}
+
+/// One kind of evidence about a file's type.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Evidence {
+    pub mime: MimeType,
+    /// [`Glob`](DetectionMethod::Glob) or [`Magic`](DetectionMethod::Magic).
+    pub method: DetectionMethod,
+    /// The glob's weight or the magic rule's priority.
+    pub strength: u32,
+}
+
+/// How [`MimeSearcher::guess`] decides between a file name and contents that point at
+/// different types. A name whose type subclasses the contents' type is not in conflict with
+/// them, and keeps its type under every policy.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum ConflictPolicy {
+    /// A glob match wins unless other types share its weight, in which case the contents
+    /// decide.
+    #[default]
+    GlobFirst,
+    /// A magic match always wins.
+    MagicFirst,
+    /// A magic match wins if its rule's priority is above this, a glob match otherwise.
+    MagicAbovePriority(u32),
+    /// A glob match wins if its weight is above this, a magic match otherwise.
+    GlobAboveWeight(u8),
+}
+
+/// How a [`Guess`] was arrived at.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum DetectionMethod {
+    /// A glob matched the file name.
+    Glob,
+    /// A magic rule (or a [`ContentProbe`](crate::ContentProbe) refining one) matched the
+    /// contents.
+    Magic,
+    /// Neither matched, and the type was picked by whether the contents look like text.
+    Fallback,
+    /// The file has no extension and its contents looked like text, and a `#!` line, mode line
+    /// or keyword suggested a language. Only with
+    /// [`with_text_heuristics`](crate::MimeSearcherBuilder::with_text_heuristics), and never
+    /// [certain](Guess::certain) as such clues can mislead.
+    Heuristic,
+    /// The path isn't a regular file, and what it is, such as a directory or FIFO, gave its
+    /// `inode/*` type without anything being read from it.
+    Inode,
+    /// No database is installed, and the file's extension was found in the table of the
+    /// `mime_guess` crate. Only with the `mime_guess` feature and `with_mime_guess_fallback`,
+    /// and never [certain](Guess::certain) as the table has no weights to tell types apart.
+    ExtensionTable,
+}
+
+impl Guess {
+    /// A guess with nothing overruled, which is certain unless it is a
+    /// [`Fallback`](DetectionMethod::Fallback), [`Heuristic`](DetectionMethod::Heuristic) or
+    /// [`ExtensionTable`](DetectionMethod::ExtensionTable).
+    pub fn new(mime: MimeType, method: DetectionMethod) -> Self {
+        Guess {
+            mime,
+            method,
+            overruled: None,
+            certain: !matches!(
+                method,
+                DetectionMethod::Fallback
+                    | DetectionMethod::Heuristic
+                    | DetectionMethod::ExtensionTable
+            ),
+        }
+    }
+
+    pub fn mime(&self) -> &MimeType {
+        &self.mime
+    }
+
+    pub fn method(&self) -> DetectionMethod {
+        self.method
+    }
+
+    pub fn overruled(&self) -> Option<&Evidence> {
+        self.overruled.as_ref()
+    }
+
+    pub fn certain(&self) -> bool {
+        self.certain
+    }
+}
+
+impl Evidence {
+    pub fn new(mime: MimeType, method: DetectionMethod, strength: u32) -> Self {
+        Evidence {
+            mime,
+            method,
+            strength,
+        }
+    }
+}
+
+impl MimeSearcher {
+    /// Finds the mimetype of a file from both its name and its leading bytes.
+    ///
+    /// When only one of the name and the magic rules gives a type, that type wins. When they
+    /// give different ones, the [`ConflictPolicy`] picks, by default the glob unless other
+    /// types share its weight, and the loser is kept in [`Guess::overruled`]. With neither the
+    /// guess is `text/plain` for data that looks like text and `application/octet-stream` for
+    /// anything else. `data` only needs to hold the first
+    /// [`max_magic_extent`](Self::max_magic_extent) bytes.
+    pub fn guess(&self, path: &Path, data: &[u8]) -> Result<Guess, Error> {
+        self.guess_traced(path, data, None)
+    }
+
+    /// [`guess`](Self::guess), noting each step taken in `trace` for
+    /// [`explain`](Self::explain).
+    pub(crate) fn guess_traced(
+        &self,
+        path: &Path,
+        data: &[u8],
+        mut trace: Option<&mut Vec<Step>>,
+    ) -> Result<Guess, Error> {
+        let guess = self.weigh_evidence(path, data, trace.as_deref_mut())?;
+        let plain_text = guess.mime == MimeType::TEXT_PLAIN && path.extension().is_none();
+        if !(self.options.text_heuristics && plain_text) {
+            return Ok(guess);
+        }
+        let Some((mime, clue)) = heuristics::sniff_text(data) else {
+            return Ok(guess);
+        };
+        // Resolved in case the database knows the type under a newer name
+        let mime = MimeType::unchecked(mime);
+        let mime = self.canonical(&mime).unwrap_or(mime);
+        if let Some(trace) = trace {
+            trace.push(Step::Heuristic {
+                mime: mime.clone(),
+                clue,
+            });
+        }
+        Ok(Guess::new(mime, DetectionMethod::Heuristic))
+    }
+
+    // The glob and magic evidence for `path` and `data`, and how it was weighed
+    fn weigh_evidence(
+        &self,
+        path: &Path,
+        data: &[u8],
+        mut trace: Option<&mut Vec<Step>>,
+    ) -> Result<Guess, Error> {
+        let mut note = |step: &dyn Fn() -> Step| {
+            if let Some(trace) = trace.as_deref_mut() {
+                trace.push(step());
+            }
+        };
+        let policy = self.options.conflict_policy;
+        let (glob, ambiguous_name) = self.glob_evidence(path, &mut note);
+
+        #[cfg(feature = "magic")]
+        let magic = self
+            .sniff(data)?
+            .map(|(mime, priority)| Evidence::new(mime, DetectionMethod::Magic, priority));
+        #[cfg(not(feature = "magic"))]
+        let magic: Option<Evidence> = None;
+        note(&|| match &magic {
+            Some(magic) => Step::MagicMatched {
+                mime: magic.mime.clone(),
+                priority: magic.strength,
+            },
+            None => Step::NoMagicMatched,
+        });
+
+        let (winner, loser, agreed) = match (glob, magic) {
+            (Some(glob), Some(magic)) => {
+                let agreed = self.refines(&glob.mime, &magic.mime);
+                // The policy only settles conflicts, so a name more specific than the contents
+                // keeps its type
+                let narrower = agreed && glob.mime != magic.mime;
+                let magic_wins = !narrower
+                    && match policy {
+                        ConflictPolicy::GlobFirst => ambiguous_name,
+                        ConflictPolicy::MagicFirst => true,
+                        ConflictPolicy::MagicAbovePriority(priority) => magic.strength > priority,
+                        ConflictPolicy::GlobAboveWeight(weight) => glob.strength <= weight.into(),
+                    };
+                if magic_wins {
+                    (magic, glob, agreed)
+                } else {
+                    (glob, magic, agreed)
+                }
+            }
+            (Some(only), None) | (None, Some(only)) => {
+                let mut guess = Guess::new(only.mime, only.method);
+                guess.certain &= !(only.method == DetectionMethod::Glob && ambiguous_name);
+                return Ok(guess);
+            }
+            (None, None) => {
+                let guess = fallback(data);
+                note(&|| Step::Fallback {
+                    text: guess.mime == MimeType::TEXT_PLAIN,
+                });
+                return Ok(guess);
+            }
+        };
+        if !agreed {
+            note(&|| Step::ConflictResolved {
+                policy,
+                winner: winner.method,
+            });
+        }
+        Ok(Guess {
+            overruled: (!agreed).then_some(loser),
+            mime: winner.mime,
+            method: winner.method,
+            certain: agreed,
+        })
+    }
+
+    // Whether the glob's type is the magic type, under any alias, or a subclass of it, as a
+    // .docx is of application/zip, so that the name only says more than the contents do
+    fn refines(&self, glob: &MimeType, magic: &MimeType) -> bool {
+        let magic = self.canonical(magic).unwrap_or_else(|_| magic.clone());
+        #[cfg(feature = "hierarchy")]
+        let ancestry = self.ancestry(glob).unwrap_or_else(|_| vec![glob.clone()]);
+        #[cfg(not(feature = "hierarchy"))]
+        let ancestry = [self.canonical(glob).unwrap_or_else(|_| glob.clone())];
+        ancestry.contains(&magic)
+    }
+
+    // The glob match for `path` as evidence, and whether other types share its weight
+    #[cfg(feature = "globs")]
+    fn glob_evidence(
+        &self,
+        path: &Path,
+        note: &mut dyn FnMut(&dyn Fn() -> Step),
+    ) -> (Option<Evidence>, bool) {
+        let ambiguous_name = self.is_ambiguous_name(path);
+        let glob_match = self.glob_match(path);
+        note(&|| match &glob_match {
+            Some((entry, matched)) => Step::GlobMatched {
+                pattern: matched.pattern().into_owned(),
+                mime: self.intern(entry.mime),
+                weight: entry.weight,
+                source: entry.source.map(Path::to_path_buf),
+            },
+            None => Step::NoGlobMatched,
+        });
+        if ambiguous_name {
+            note(&|| Step::AmbiguousName {
+                candidates: self.tied_types(path),
+            });
+        }
+        let glob = match glob_match {
+            Some((entry, _)) => Some(Evidence::new(
+                self.intern(entry.mime),
+                DetectionMethod::Glob,
+                entry.weight.into(),
+            )),
+            None => self.extension_table_match(path).map(|mime| {
+                note(&|| Step::ExtensionTableMatched { mime: mime.clone() });
+                Evidence::new(mime, DetectionMethod::ExtensionTable, 50)
+            }),
+        };
+        (glob, ambiguous_name)
+    }
+
+    // Without globs there is no name evidence to weigh
+    #[cfg(not(feature = "globs"))]
+    fn glob_evidence(
+        &self,
+        _path: &Path,
+        _note: &mut dyn FnMut(&dyn Fn() -> Step),
+    ) -> (Option<Evidence>, bool) {
+        (None, false)
+    }
+
+    /// Sets how [`guess`](Self::guess) decides between a file name and contents that point at
+    /// different types.
+    pub fn set_conflict_policy(&mut self, policy: ConflictPolicy) {
+        self.options.conflict_policy = policy;
+    }
+
+    /// The glob match for `path`, unless its extension is claimed by several equally weighted
+    /// types and the contents have to decide.
+    #[cfg(feature = "globs")]
+    pub(crate) fn unambiguous_name_match(&self, path: &Path) -> Option<MimeType> {
+        if self.is_ambiguous_name(path) {
+            return None;
+        }
+        self.find_mimetype_from_filepath(path)
+    }
+
+    #[cfg(not(feature = "globs"))]
+    pub(crate) fn unambiguous_name_match(&self, _path: &Path) -> Option<MimeType> {
+        None
+    }
+
+    /// The type the `mime_guess` crate gives for the extension of `path`, when the searcher
+    /// was built to fall back on it and has no database.
+    #[cfg(feature = "mime_guess")]
+    pub(crate) fn extension_table_match(&self, path: &Path) -> Option<MimeType> {
+        let no_database = self.databases.is_empty() && self.sources.is_empty();
+        if !(self.options.mime_guess_fallback && no_database) {
+            return None;
+        }
+        MimeType::new(mime_guess::from_path(path).first_raw()?).ok()
+    }
+
+    #[cfg(all(feature = "globs", not(feature = "mime_guess")))]
+    pub(crate) fn extension_table_match(&self, _path: &Path) -> Option<MimeType> {
+        None
+    }
+
+    // Whether the extension of `path` is claimed by several equally weighted types
+    #[cfg(feature = "globs")]
+    fn is_ambiguous_name(&self, path: &Path) -> bool {
+        self.globber()
+            .matched_extension(path)
+            .is_some_and(|ext| self.is_ambiguous_extension(&ext))
+    }
+
+    // The types sharing the highest weight for the extension of `path`
+    #[cfg(feature = "globs")]
+    fn tied_types(&self, path: &Path) -> Vec<MimeType> {
+        let ext = self.globber().matched_extension(path);
+        let matches = self.types_for_extension(ext.as_deref().unwrap_or_default());
+        let top = matches.first().map(|m| m.weight);
+        matches
+            .into_iter()
+            .take_while(|m| Some(m.weight) == top)
+            .map(|m| m.mime)
+            .collect()
+    }
+}
+
+fn fallback(data: &[u8]) -> Guess {
+    let fallback = if looks_like_text(data) {
+        MimeType::TEXT_PLAIN
+    } else {
+        MimeType::APPLICATION_OCTET_STREAM
+    };
+    Guess::new(fallback, DetectionMethod::Fallback)
+}
+
+// `data` is usually a truncated prefix, so a multi-byte character cut off at the end is fine.
+fn looks_like_text(data: &[u8]) -> bool {
+    !data.contains(&0)
+        && match std::str::from_utf8(data) {
+            Ok(_) => true,
+            Err(e) => e.error_len().is_none(),
+        }
+}
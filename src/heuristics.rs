@@ -0,0 +1,126 @@
+//! Guessing the language of extensionless text files from what's written in them.
+//!
+//! These are the clues an editor uses when it has no file name to go on: the interpreter in a
+//! `#!` line, an Emacs or Vim mode line, and a few unmistakable keywords. Unlike magic rules
+//! they can be fooled, so results are only ever reported as
+//! [`Heuristic`](crate::DetectionMethod::Heuristic).
+
+/// How many lines at each end of the data to look for mode lines and keywords in.
+const LINES: usize = 5;
+
+/// The type `data` looks like, along with a description of the clue that gave it away.
+pub(crate) fn sniff_text(data: &[u8]) -> Option<(&'static str, String)> {
+    let text = String::from_utf8_lossy(data);
+    let lines: Vec<&str> = text.lines().collect();
+    let first = lines.first()?;
+
+    if let Some(command) = first.strip_prefix("#!") {
+        let interpreter = interpreter(command)?;
+        let mime = language(&interpreter)?;
+        return Some((mime, format!("#! line runs `{interpreter}`")));
+    }
+
+    let ends = lines
+        .iter()
+        .take(LINES)
+        .chain(lines.iter().rev().take(LINES));
+    for line in ends.clone() {
+        let Some(mode) = emacs_mode(line).or_else(|| vim_filetype(line)) else {
+            continue;
+        };
+        if let Some(mime) = language(&mode) {
+            return Some((mime, format!("mode line names `{mode}`")));
+        }
+    }
+
+    for line in ends {
+        let line = line.trim();
+        let found = KEYWORDS
+            .iter()
+            .find(|(starts, ends, _)| line.starts_with(starts) && line.ends_with(ends));
+        if let Some((starts, _, mime)) = found {
+            return Some((mime, format!("line starting with `{starts}`")));
+        }
+    }
+    None
+}
+
+/// Lines starting and ending with these only appear in one language.
+const KEYWORDS: &[(&str, &str, &str)] = &[
+    ("<?php", "", "application/x-php"),
+    ("#include <", ">", "text/x-csrc"),
+    ("#include \"", "\"", "text/x-csrc"),
+    ("from __future__ import ", "", "text/x-python3"),
+    ("if __name__ == ", ":", "text/x-python3"),
+    ("def ", "):", "text/x-python3"),
+];
+
+/// The type for an interpreter or editor mode name, ignoring any version suffix.
+fn language(name: &str) -> Option<&'static str> {
+    let name = name.to_ascii_lowercase();
+    let base = name.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    Some(match base {
+        "python" if name.starts_with("python2") => "text/x-python",
+        "python" => "text/x-python3",
+        "sh" | "bash" | "dash" | "zsh" | "ksh" | "ash" | "mksh" | "shell-script" => {
+            "application/x-shellscript"
+        }
+        "perl" | "cperl" => "application/x-perl",
+        "ruby" => "application/x-ruby",
+        "node" | "nodejs" | "javascript" | "js" => "application/javascript",
+        "lua" => "text/x-lua",
+        "php" => "application/x-php",
+        "awk" | "gawk" | "mawk" => "application/x-awk",
+        "make" | "makefile" => "text/x-makefile",
+        "c" => "text/x-csrc",
+        "cpp" | "c++" => "text/x-c++src",
+        "markdown" => "text/markdown",
+        "yaml" => "application/x-yaml",
+        "rust" => "text/rust",
+        _ => return None,
+    })
+}
+
+/// The program a `#!` line runs, looking through `env` and its options.
+fn interpreter(command: &str) -> Option<String> {
+    let mut words = command.split_whitespace();
+    let basename = |word: &str| word.rsplit('/').next().unwrap_or(word).to_string();
+    let program = basename(words.next()?);
+    if program != "env" {
+        return Some(program);
+    }
+    words
+        .find(|word| !word.starts_with('-') && !word.contains('='))
+        .map(basename)
+}
+
+/// The mode of an Emacs `-*- mode: python -*-` or `-*- python -*-` line.
+fn emacs_mode(line: &str) -> Option<String> {
+    let (_, rest) = line.split_once("-*-")?;
+    let (vars, _) = rest.split_once("-*-")?;
+    let vars = vars.trim();
+    if !vars.contains(':') {
+        return Some(vars.to_string());
+    }
+    vars.split(';').find_map(|var| {
+        let (name, value) = var.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("mode")
+            .then(|| value.trim().to_string())
+    })
+}
+
+/// The file type of a Vim `vim: set ft=python:` or `vi: filetype=sh` line.
+fn vim_filetype(line: &str) -> Option<String> {
+    let start = ["vim:", "vi:", "ex:"].iter().find_map(|marker| {
+        let at = line.find(marker)?;
+        let preceded_by_space = line[..at].ends_with(char::is_whitespace);
+        (at == 0 || preceded_by_space).then(|| at + marker.len())
+    })?;
+    line[start..]
+        .split(|c: char| c.is_whitespace() || c == ':')
+        .find_map(|option| {
+            let (name, value) = option.split_once('=')?;
+            matches!(name, "ft" | "filetype" | "syntax").then(|| value.to_string())
+        })
+}
@@ -0,0 +1,138 @@
+//! Alias and subclass lookups against the sorted lists in mime.cache.
+
+use crate::{CacheSection, Error, MimeCache, MimeSearcher, MimeType};
+
+impl MimeCache {
+    // AliasList:
+    // 4			CARD32		N_ALIASES
+    // 8*N_ALIASES	AliasListEntry
+    //
+    // AliasListEntry:
+    // 4			CARD32		ALIAS_OFFSET
+    // 4			CARD32		MIME_TYPE_OFFSET
+    pub(crate) fn resolve_alias(&self, alias: &MimeType) -> Result<Option<MimeType>, Error> {
+        let data = self.reader(CacheSection::AliasList);
+        let start = self.cache_header.alias_list_offset as usize;
+
+        let Some(ptr) = data.bsearch_list(start, 8, alias.essence_str())? else {
+            return Ok(None);
+        };
+        Ok(Some(MimeType::unchecked(data.cstr_at(ptr + 4)?)))
+    }
+
+    // ParentList:
+    // 4			CARD32		N_ENTRIES
+    // 8*N_ENTRIES	ParentListEntry
+    //
+    // ParentListEntry:
+    // 4			CARD32		MIME_TYPE_OFFSET
+    // 4			CARD32		PARENTS_OFFSET
+    //
+    // Parents:
+    // 4			CARD32		N_PARENTS
+    // 4*N_PARENTS	CARD32		MIME_TYPE_OFFSET
+    #[cfg(feature = "hierarchy")]
+    pub(crate) fn parents(&self, mime: &MimeType) -> Result<Vec<MimeType>, Error> {
+        let data = self.reader(CacheSection::ParentList);
+        let start = self.cache_header.parent_list_offset as usize;
+
+        let Some(ptr) = data.bsearch_list(start, 8, mime.essence_str())? else {
+            return Ok(Vec::new());
+        };
+        let parents = data.usize(ptr + 4)?;
+        (0..data.usize(parents)?)
+            .map(|i| {
+                let offset = data.nth(parents + 4, i, 4)?;
+                Ok(MimeType::unchecked(data.cstr_at(offset)?))
+            })
+            .collect()
+    }
+}
+
+impl MimeSearcher {
+    /// The canonical name of `mime`, which is `mime` itself unless it is a known alias.
+    pub(crate) fn canonical(&self, mime: &MimeType) -> Result<MimeType, Error> {
+        for database in self.databases.iter() {
+            if let Some(canonical) = database.resolve_alias(mime)? {
+                return Ok(canonical);
+            }
+        }
+        Ok(mime.clone())
+    }
+
+    /// Whether `a` and `b` name the same type once aliases are resolved, so `text/xml` equals
+    /// `application/xml`. Names are compared ignoring case.
+    pub fn types_equal(&self, a: &MimeType, b: &MimeType) -> bool {
+        let canonical = |mime: &MimeType| self.canonical(mime).unwrap_or_else(|_| mime.clone());
+        canonical(a)
+            .essence_str()
+            .eq_ignore_ascii_case(canonical(b).essence_str())
+    }
+
+    /// The direct parents of `mime` from the first database that lists any.
+    #[cfg(feature = "hierarchy")]
+    pub(crate) fn direct_parents(&self, mime: &MimeType) -> Result<Vec<MimeType>, Error> {
+        if let Some(index) = &self.type_index {
+            return Ok(index.parents(mime));
+        }
+        for database in self.databases.iter() {
+            let parents = database.parents(mime)?;
+            if !parents.is_empty() {
+                return Ok(parents);
+            }
+        }
+        Ok(Vec::new())
+    }
+
+    /// `mime` (resolved through aliases) followed by all of its ancestors, nearest first.
+    #[cfg(feature = "hierarchy")]
+    pub(crate) fn ancestry(&self, mime: &MimeType) -> Result<Vec<MimeType>, Error> {
+        let mut ancestry = vec![self.canonical(mime)?];
+        let mut next = 0;
+        while let Some(current) = ancestry.get(next).cloned() {
+            for parent in self.direct_parents(&current)? {
+                let parent = self.canonical(&parent)?;
+                if !ancestry.contains(&parent) {
+                    ancestry.push(parent);
+                }
+            }
+            next += 1;
+        }
+        Ok(ancestry)
+    }
+
+    /// The types in `offered` that a consumer accepting `accepted_patterns` can take, in the
+    /// order they were offered, as needed to negotiate a drag-and-drop or clipboard transfer.
+    ///
+    /// Patterns are full types, `media/*` wildcards or `*/*`. Aliases are resolved on both sides,
+    /// and a type is accepted when it or any of its ancestors matches, so a target accepting
+    /// `text/plain` takes `text/x-python`.
+    #[cfg(feature = "hierarchy")]
+    pub fn common_targets(
+        &self,
+        offered: &[MimeType],
+        accepted_patterns: &[&str],
+    ) -> Vec<MimeType> {
+        let accepted: Vec<String> = accepted_patterns
+            .iter()
+            .map(|pattern| match MimeType::try_from(*pattern) {
+                Ok(mime) if !pattern.ends_with("/*") => self
+                    .canonical(&mime)
+                    .map_or_else(|_| pattern.to_string(), |mime| mime.to_string()),
+                _ => pattern.to_string(),
+            })
+            .collect();
+
+        let mut targets: Vec<MimeType> = Vec::new();
+        for mime in offered {
+            let ancestry = self.ancestry(mime).unwrap_or_else(|_| vec![mime.clone()]);
+            let acceptable = ancestry
+                .iter()
+                .any(|ancestor| accepted.iter().any(|pattern| ancestor.matches(pattern)));
+            if acceptable && !targets.contains(mime) {
+                targets.push(mime.clone());
+            }
+        }
+        targets
+    }
+}
@@ -0,0 +1,33 @@
+//! Compact, portable identifiers for mime types.
+
+use crate::{Error, MimeSearcher, MimeType};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+impl MimeSearcher {
+    /// A 64 bit identifier for `mime` that is the same in every process and on every platform.
+    ///
+    /// The id is the 64 bit FNV-1a hash of the UTF-8 bytes of the canonical name (aliases
+    /// resolved, ASCII lowercased), so aliases of a type share its id, and other implementations
+    /// can compute it too. Use [`type_for_stable_id`](Self::type_for_stable_id) to go back.
+    pub fn stable_id(&self, mime: &MimeType) -> u64 {
+        let canonical = self.canonical(mime).unwrap_or_else(|_| mime.clone());
+        canonical
+            .essence_str()
+            .bytes()
+            .map(|b| b.to_ascii_lowercase())
+            .fold(FNV_OFFSET_BASIS, |hash, b| {
+                (hash ^ b as u64).wrapping_mul(FNV_PRIME)
+            })
+    }
+
+    /// The known type whose [`stable_id`](Self::stable_id) is `id`, if the loaded databases
+    /// have one.
+    pub fn type_for_stable_id(&self, id: u64) -> Result<Option<MimeType>, Error> {
+        Ok(self
+            .known_types()?
+            .into_iter()
+            .find(|mime| self.stable_id(mime) == id))
+    }
+}
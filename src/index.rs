@@ -0,0 +1,51 @@
+//! Lookup tables copied out of the databases up front, for searchers built with
+//! [`IndexProfile::Fast`](crate::IndexProfile::Fast).
+
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{CacheSection, Error, MimeDatabase, MimeType};
+
+/// The parents and generic icons of every type, as the searcher's databases define them with
+/// the more important ones winning, so that looking one up is a single hash lookup.
+#[derive(Debug, Default)]
+pub(crate) struct TypeIndex {
+    #[cfg(feature = "hierarchy")]
+    parents: HashMap<MimeType, Vec<MimeType>>,
+    #[cfg(feature = "icons")]
+    generic_icons: HashMap<MimeType, String>,
+}
+
+impl TypeIndex {
+    /// Reads every database in full, most important first. Fails with the error of the first
+    /// that can't be read.
+    pub(crate) fn new(databases: &[Arc<dyn MimeDatabase>]) -> Result<Self, Error> {
+        let mut index = TypeIndex::default();
+        for database in databases {
+            let sections = [CacheSection::ParentList, CacheSection::GenericIconsList];
+            let model = database.to_model_of(&sections)?;
+            // A database listing no parents for a type leaves it to the next one, as lookups do
+            #[cfg(feature = "hierarchy")]
+            for (mime, parents) in model.parents {
+                if !parents.is_empty() {
+                    index.parents.entry(mime).or_insert(parents);
+                }
+            }
+            #[cfg(feature = "icons")]
+            for (mime, icon) in model.generic_icons {
+                index.generic_icons.entry(mime).or_insert(icon);
+            }
+        }
+        Ok(index)
+    }
+
+    /// The direct parents of `mime`, empty if no database lists any.
+    #[cfg(feature = "hierarchy")]
+    pub(crate) fn parents(&self, mime: &MimeType) -> Vec<MimeType> {
+        self.parents.get(mime).cloned().unwrap_or_default()
+    }
+
+    #[cfg(feature = "icons")]
+    pub(crate) fn generic_icon(&self, mime: &MimeType) -> Option<String> {
+        self.generic_icons.get(mime).cloned()
+    }
+}
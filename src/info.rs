@@ -0,0 +1,121 @@
+//! Everything known about one type, gathered for properties dialogs and the like.
+
+#[cfg(feature = "globs")]
+use crate::NO_GLOBS_MARKER;
+use crate::{CacheSection, GlobRecord, MimeSearcher, MimeType};
+
+/// What the loaded databases say about a type, from [`MimeSearcher::info`].
+///
+/// Anything a database doesn't define, or the searcher wasn't built to load, is left `None` or
+/// empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct MimeInfo {
+    /// The type the information is about, with aliases resolved.
+    pub mime: MimeType,
+    /// The type's description, such as `PNG image`, in the user's language if it has been
+    /// translated, as [`comment`](MimeSearcher::comment) finds it.
+    pub comment: Option<String>,
+    /// The acronym the type's name is usually written as, such as `PDF`.
+    pub acronym: Option<String>,
+    /// What [`acronym`](Self::acronym) stands for, such as `Portable Document Format`.
+    pub expanded_acronym: Option<String>,
+    /// An icon of the type's own, which few databases have.
+    pub icon: Option<String>,
+    /// The generic icon, as [`find_icon_for_mimetype`](MimeSearcher::find_icon_for_mimetype)
+    /// finds it.
+    pub generic_icon: Option<String>,
+    /// The globs naming the type, sorted by pattern.
+    pub globs: Vec<GlobRecord>,
+    /// Other names of the type, sorted.
+    pub aliases: Vec<MimeType>,
+    /// The types it is a subclass of, as the most important database that lists any has them.
+    pub parents: Vec<MimeType>,
+}
+
+impl MimeSearcher {
+    /// Gathers the description, acronyms, icons, globs, aliases and parents of `mime` in one go.
+    ///
+    /// This is the sum of several lookups, some of which read whole databases, so it suits
+    /// showing one type to the user better than being called for every file. A database that
+    /// can't be read is skipped, and anything found in the others is still returned.
+    ///
+    /// ```no_run
+    /// # use shared_mime_info as smi;
+    /// let searcher = smi::MimeSearcher::new().unwrap();
+    /// let info = searcher.info(&smi::MimeType::new("image/x-png").unwrap());
+    /// assert_eq!(info.mime, smi::MimeType::IMAGE_PNG);
+    /// println!("{}", info.comment.unwrap_or_default());
+    /// ```
+    pub fn info(&self, mime: &MimeType) -> MimeInfo {
+        let mime = self.canonical(mime).unwrap_or_else(|_| mime.clone());
+        let models: Vec<_> = self
+            .databases
+            .iter()
+            .filter_map(|database| {
+                let sections = [
+                    CacheSection::AliasList,
+                    CacheSection::IconsList,
+                    CacheSection::GenericIconsList,
+                ];
+                database.to_model_of(&sections).ok()
+            })
+            .collect();
+
+        let mut aliases: Vec<_> = models
+            .iter()
+            .flat_map(|model| &model.aliases)
+            .filter(|(_, target)| *target == mime)
+            .map(|(alias, _)| alias.clone())
+            .collect();
+        aliases.sort();
+        aliases.dedup();
+
+        #[cfg(feature = "icons")]
+        let (icon, generic_icon) = {
+            let first = |icons: fn(&crate::MimeDatabaseModel) -> &Vec<(MimeType, String)>| {
+                models.iter().find_map(|model| {
+                    icons(model)
+                        .iter()
+                        .find(|(icon_type, _)| *icon_type == mime)
+                        .map(|(_, icon)| icon.clone())
+                })
+            };
+            if self.options.icons {
+                (first(|m| &m.icons), first(|m| &m.generic_icons))
+            } else {
+                (None, None)
+            }
+        };
+        #[cfg(not(feature = "icons"))]
+        let (icon, generic_icon) = (None, None);
+
+        #[cfg(feature = "hierarchy")]
+        let parents = self.direct_parents(&mime).unwrap_or_default();
+        #[cfg(not(feature = "hierarchy"))]
+        let parents = Vec::new();
+
+        #[cfg(feature = "globs")]
+        let globs = self
+            .all_globs()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|glob| glob.mime == mime && glob.pattern != NO_GLOBS_MARKER)
+            .collect();
+        #[cfg(not(feature = "globs"))]
+        let globs = Vec::new();
+
+        MimeInfo {
+            comment: self.comment(&mime),
+            acronym: self.acronym(&mime),
+            expanded_acronym: self.expanded_acronym(&mime),
+            icon,
+            generic_icon,
+            globs,
+            aliases,
+            parents,
+            mime,
+        }
+    }
+}
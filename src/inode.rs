@@ -0,0 +1,45 @@
+//! The types of paths that aren't regular files, which are never opened to be sniffed: reading
+//! a FIFO or terminal can block forever, and reading a device has side effects.
+
+use std::fs::Metadata;
+
+use crate::MimeType;
+
+/// The `inode/*` type of a path with `metadata` if it isn't a regular file, `None` if it is.
+///
+/// On platforms without FIFOs, sockets and device nodes, anything but a directory counts as a
+/// regular file.
+pub(crate) fn non_regular_type(metadata: &Metadata) -> Option<MimeType> {
+    if metadata.is_dir() {
+        return Some(MimeType::INODE_DIRECTORY);
+    }
+    if metadata.is_file() {
+        None
+    } else {
+        special_file_type(metadata)
+    }
+}
+
+/// The type of a device, FIFO or socket, `None` for anything else.
+#[cfg(unix)]
+pub(crate) fn special_file_type(metadata: &Metadata) -> Option<MimeType> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let kind = metadata.file_type();
+    Some(MimeType::constant(if kind.is_char_device() {
+        "inode/chardevice"
+    } else if kind.is_block_device() {
+        "inode/blockdevice"
+    } else if kind.is_fifo() {
+        "inode/fifo"
+    } else if kind.is_socket() {
+        "inode/socket"
+    } else {
+        return None;
+    }))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn special_file_type(_metadata: &Metadata) -> Option<MimeType> {
+    None
+}
@@ -0,0 +1,63 @@
+//! Sharing one copy of each type name between the results of every lookup.
+
+use std::{
+    collections::HashSet,
+    sync::{PoisonError, RwLock},
+};
+
+use crate::{MimeSearcher, MimeType, MimeTypeRef};
+
+/// The type names a searcher has handed out, so that each is allocated only once however many
+/// lookups return it.
+#[derive(Debug, Default)]
+pub(crate) struct Interner {
+    names: RwLock<HashSet<MimeType>>,
+}
+
+impl Interner {
+    /// The shared copy of `mime`, made now if this is the first time it is needed.
+    pub(crate) fn get(&self, mime: MimeTypeRef<'_>) -> MimeType {
+        let essence = mime.essence_str();
+        if let Some(interned) = self
+            .names
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(essence)
+        {
+            return interned.clone();
+        }
+        let mut names = self.names.write().unwrap_or_else(PoisonError::into_inner);
+        // Another thread may have added it between the two locks
+        if let Some(interned) = names.get(essence) {
+            return interned.clone();
+        }
+        let interned = mime.to_mime_type();
+        names.insert(interned.clone());
+        interned
+    }
+}
+
+impl MimeSearcher {
+    /// A copy of `mime` that shares its string with every other copy this searcher hands out.
+    ///
+    /// File name and content lookups already return interned types, so a file indexer
+    /// classifying millions of files holds one `application/pdf` string rather than millions,
+    /// and cloning a result never allocates. Comparing two interned types mostly comes down to
+    /// comparing pointers. Interning a type of your own, such as one parsed from a config
+    /// file, makes comparisons against lookup results just as cheap.
+    ///
+    /// The interned names are kept for the life of the searcher, and shared with searchers
+    /// [derived](Self::derive) from it. There is one per distinct type ever returned, so they
+    /// stay few.
+    ///
+    /// ```no_run
+    /// # use shared_mime_info as smi;
+    /// let searcher = smi::MimeSearcher::new().unwrap();
+    /// let pdf = searcher.intern(&smi::MimeType::new("application/pdf").unwrap());
+    /// // Usually a pointer comparison against what lookups return
+    /// let is_pdf = |found: &smi::MimeType| *found == pdf;
+    /// ```
+    pub fn intern<'a>(&self, mime: impl Into<MimeTypeRef<'a>>) -> MimeType {
+        self.names.get(mime.into())
+    }
+}
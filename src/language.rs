@@ -0,0 +1,125 @@
+//! Editor language identifiers for text types.
+
+use crate::{MimeSearcher, MimeType};
+
+/// Mime types and the [language identifier](https://code.visualstudio.com/docs/languages/identifiers)
+/// editors and language servers use for them. Only canonical names need to be listed, aliases
+/// and subclasses are resolved through the database.
+const LANGUAGE_IDS: &[(&str, &str)] = &[
+    ("application/javascript", "javascript"),
+    ("application/json", "json"),
+    ("application/sql", "sql"),
+    ("application/toml", "toml"),
+    ("application/x-perl", "perl"),
+    ("application/x-php", "php"),
+    ("application/x-ruby", "ruby"),
+    ("application/x-shellscript", "shellscript"),
+    ("application/x-yaml", "yaml"),
+    ("application/xml", "xml"),
+    ("text/css", "css"),
+    ("text/html", "html"),
+    ("text/markdown", "markdown"),
+    ("text/rust", "rust"),
+    ("text/x-c++hdr", "cpp"),
+    ("text/x-c++src", "cpp"),
+    ("text/x-chdr", "c"),
+    ("text/x-csharp", "csharp"),
+    ("text/x-csrc", "c"),
+    ("text/x-dart", "dart"),
+    ("text/x-go", "go"),
+    ("text/x-haskell", "haskell"),
+    ("text/x-java", "java"),
+    ("text/x-kotlin", "kotlin"),
+    ("text/x-lua", "lua"),
+    ("text/x-makefile", "makefile"),
+    ("text/x-python", "python"),
+    ("text/x-rust", "rust"),
+    ("text/x-scala", "scala"),
+    ("text/x-sql", "sql"),
+    ("text/x-swift", "swift"),
+    ("text/x-tex", "latex"),
+    ("text/x-typescript", "typescript"),
+    ("text/x-zig", "zig"),
+];
+
+impl MimeSearcher {
+    /// The editor language identifier (`"rust"`, `"python"`, ...) for files of type `mime`.
+    ///
+    /// Aliases are resolved first, then the nearest ancestor with a known language is used, so
+    /// `text/x-python3` maps to `"python"` through its parent `text/x-python`. Returns `None` for
+    /// types that are not source code, including plain text.
+    pub fn language_hint_for(&self, mime: &MimeType) -> Option<&'static str> {
+        let ancestry = self.ancestry(mime).unwrap_or_else(|_| vec![mime.clone()]);
+        ancestry.iter().find_map(|mime| {
+            LANGUAGE_IDS
+                .binary_search_by_key(&mime.essence_str(), |(mime, _)| mime)
+                .ok()
+                .map(|i| LANGUAGE_IDS[i].1)
+        })
+    }
+}
+
+/// Language identifiers from [`LANGUAGE_IDS`] with the matching syntect syntax name (as in its
+/// default syntax set) and tree-sitter grammar name, when those exist.
+#[cfg(feature = "syntax_names")]
+const SYNTAX_NAMES: &[(&str, Option<&str>, Option<&str>)] = &[
+    ("c", Some("C"), Some("c")),
+    ("cpp", Some("C++"), Some("cpp")),
+    ("csharp", Some("C#"), Some("c_sharp")),
+    ("css", Some("CSS"), Some("css")),
+    ("dart", None, Some("dart")),
+    ("go", Some("Go"), Some("go")),
+    ("haskell", Some("Haskell"), Some("haskell")),
+    ("html", Some("HTML"), Some("html")),
+    ("java", Some("Java"), Some("java")),
+    ("javascript", Some("JavaScript"), Some("javascript")),
+    ("json", Some("JSON"), Some("json")),
+    ("kotlin", None, Some("kotlin")),
+    ("latex", Some("LaTeX"), Some("latex")),
+    ("lua", Some("Lua"), Some("lua")),
+    ("makefile", Some("Makefile"), Some("make")),
+    ("markdown", Some("Markdown"), Some("markdown")),
+    ("perl", Some("Perl"), Some("perl")),
+    ("php", Some("PHP"), Some("php")),
+    ("python", Some("Python"), Some("python")),
+    ("ruby", Some("Ruby"), Some("ruby")),
+    ("rust", Some("Rust"), Some("rust")),
+    ("scala", Some("Scala"), Some("scala")),
+    (
+        "shellscript",
+        Some("Bourne Again Shell (bash)"),
+        Some("bash"),
+    ),
+    ("sql", Some("SQL"), Some("sql")),
+    ("swift", None, Some("swift")),
+    ("toml", None, Some("toml")),
+    ("typescript", None, Some("typescript")),
+    ("xml", Some("XML"), Some("xml")),
+    ("yaml", Some("YAML"), Some("yaml")),
+    ("zig", None, Some("zig")),
+];
+
+#[cfg(feature = "syntax_names")]
+impl MimeSearcher {
+    /// The name of the syntect syntax for files of type `mime`, for use with
+    /// `SyntaxSet::find_syntax_by_name`.
+    ///
+    /// Resolved through [`language_hint_for`](Self::language_hint_for), so aliases and
+    /// subclasses are handled the same way.
+    pub fn syntect_syntax_for(&self, mime: &MimeType) -> Option<&'static str> {
+        syntax_names(self.language_hint_for(mime)?).1
+    }
+
+    /// The tree-sitter grammar name for files of type `mime`, as used by the `tree-sitter-<name>`
+    /// crates.
+    pub fn tree_sitter_language_for(&self, mime: &MimeType) -> Option<&'static str> {
+        syntax_names(self.language_hint_for(mime)?).2
+    }
+}
+
+#[cfg(feature = "syntax_names")]
+fn syntax_names(language: &str) -> (&str, Option<&'static str>, Option<&'static str>) {
+    SYNTAX_NAMES
+        .binary_search_by_key(&language, |(language, _, _)| language)
+        .map_or((language, None, None), |i| SYNTAX_NAMES[i])
+}
@@ -1,11 +1,13 @@
 //! An implementation of the [shared mime info spec](https://www.freedesktop.org/wiki/Software/shared-mime-info/)
 //!
-//! See `MimeSearcher` functions for full list of operations.
+//! See `MimeSearcher` functions for full list of operations. Libraries that only need the odd
+//! lookup can share the searcher returned by [`system`] rather than loading their own.
 //!
 //! # Example
 //! Find icon name for file from filename:
 //!
 //! ```
+//! # #[cfg(all(feature = "globs", feature = "icons"))] {
 //! use shared_mime_info as smi;
 //!
 //! let searcher = smi::MimeSearcher::new().unwrap();
@@ -13,94 +15,496 @@
 //! let mime_type =
 //! searcher.find_mimetype_from_filepath(&std::path::PathBuf::from("foo.pdf")).unwrap();
 //! let icon_name = searcher.find_icon_for_mimetype(mime_type).unwrap();
+//! # }
 //! ```
 //!
+//! # Features
+//! Each subsystem sits behind a cargo feature, so that embedded users only compile what they
+//! use. Without one, its lookups and the methods and types serving them are left out of the
+//! crate entirely, rather than switched off as with [`MimeSearcherBuilder`].
+//!
+//! - `globs` (default): file name lookups, and the glob evidence of [`MimeSearcher::guess`].
+//! - `magic` (default): content sniffing, probes and the streaming `Classifier`. Without it
+//!   `guess` goes by the name alone.
+//! - `icons` (default): icon name lookups.
+//! - `hierarchy` (default): subclass lookups such as `common_targets` and language hints.
+//!   Aliases are always resolved.
+//! - `xml`: compiling package files and `doctor`.
+//! - `scanner`: walking directory trees with `Scanner`.
+//! - `complex_globs`: wildcard globs beyond `*.ext`, using the `glob` crate.
+//! - `memmap`: maps mime.cache files into memory instead of reading them onto the heap.
+//! - `codegen`: `embed_database`, for build scripts baking a database into the program to load
+//!   with [`MimeSearcher::embedded`].
+//! - `tokio`: `sniff_file_async` and `sniff_async_reader`, classifying files and streams from
+//!   async code without blocking the runtime. Implies `magic`.
+//! - `futures`: `sniff_futures_reader`, the same for streams of any runtime reading through the
+//!   `futures` traits, such as async-std or smol. Implies `magic`.
+//! - `notify`: `MimeSearcher::watch`, reloading a searcher when its databases change on disk.
+//! - `serde`: `Serialize` and `Deserialize` for mime types, which go as their plain string,
+//!   and for results such as `Guess`, `GlobMatch`, `MimeInfo` and `MimeDatabaseModel`.
+//! - `mime_guess`: `with_mime_guess_fallback`, answering file name lookups from the extension
+//!   table of the `mime_guess` crate when no database is installed. Implies `globs`.
+//! - `bundled`: compiles in the database of shared-mime-info 2.2, which searchers use when none
+//!   is installed or readable, so that [`MimeSearcher::new`] works in scratch containers too. Adds about
+//!   150 KiB to the binary.
+//! - `syntax_names`, `rayon`, `collation` and `cli` add to the above.
+//!
+//! # Ordering
+//! Every API returning a list does so in a documented, deterministic order (usually sorted by
+//! mime type name, or by weight and then name for ranked results) that never depends on hash map
+//! iteration or directory enumeration order. Snapshot tests built on this crate are stable across
+//! runs and platforms.
+//!
 
 // https://specifications.freedesktop.org/shared-mime-info/0.21/ar01s02.html
 
-use std::{cmp::Ordering, collections::HashMap, ffi::CStr, path::Path, str::FromStr};
+#[cfg(feature = "globs")]
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    ffi::OsStr,
+    ops::Range,
+    sync::Mutex,
+};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+};
 
-/// String wrapper. Used to make typing clearer
-#[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord)]
-pub struct MimeType(pub String);
+#[cfg(feature = "globs")]
+mod arena;
+mod batch;
+mod builder;
+#[cfg(feature = "bundled")]
+mod bundled;
+#[cfg(feature = "magic")]
+mod classifier;
+mod content_type;
+mod database;
+mod description;
+mod dirs;
+mod display_order;
+#[cfg(feature = "xml")]
+mod doctor;
+mod embed;
+mod error;
+mod explain;
+#[cfg(feature = "globs")]
+mod extensions;
+mod guess;
+mod heuristics;
+mod hierarchy;
+mod ids;
+#[cfg(any(feature = "hierarchy", feature = "icons"))]
+mod index;
+mod info;
+mod inode;
+mod intern;
+#[cfg(feature = "hierarchy")]
+mod language;
+mod listing;
+#[cfg(any(feature = "globs", feature = "icons"))]
+mod lru;
+#[cfg(feature = "magic")]
+mod magic;
+#[cfg(feature = "magic")]
+mod memo;
+mod mime_ref;
+mod mime_type;
+mod model;
+#[cfg(feature = "magic")]
+mod ole;
+#[cfg(feature = "xml")]
+mod packages;
+#[cfg(feature = "magic")]
+mod probe;
+#[cfg(feature = "magic")]
+mod range;
+mod reader;
+#[cfg(feature = "scanner")]
+mod scanner;
+mod search;
+#[cfg(feature = "serde")]
+mod serialization;
+mod shared;
+mod snapshot;
+#[cfg(any(feature = "tokio", feature = "futures"))]
+mod sniff_async;
+mod sources;
+mod stats;
+mod storage;
+mod system;
+#[cfg(feature = "globs")]
+mod telemetry;
+mod temporary;
+#[cfg(feature = "notify")]
+mod watch;
+mod xml;
 
-impl From<String> for MimeType {
-    fn from(value: String) -> Self {
-        MimeType(value)
-    }
-}
+#[cfg(feature = "globs")]
+use arena::{Span, StrArena};
+pub use builder::{IndexProfile, MimeSearcherBuilder};
+#[cfg(feature = "magic")]
+pub use classifier::{Classifier, ClassifierEvent};
+pub use content_type::ContentType;
+pub use database::MimeDatabase;
+pub use display_order::DisplayOrder;
+#[cfg(feature = "xml")]
+pub use doctor::{DoctorReport, Problem, doctor, doctor_dir};
+#[cfg(feature = "codegen")]
+pub use embed::embed_database;
+pub use error::{CacheSection, Corruption, Error, IoError};
+pub use explain::{Explanation, Step};
+pub use guess::{ConflictPolicy, DetectionMethod, Evidence, Guess};
+pub use info::MimeInfo;
+pub use mime_ref::MimeTypeRef;
+pub use mime_type::{InvalidMimeType, MimeType};
+use model::NO_GLOBS_MARKER;
+#[cfg(any(feature = "magic", feature = "xml"))]
+use model::NO_MAGIC_MARKER;
+pub use model::{GlobRecord, MagicMatch, Matchlet, MimeDatabaseModel, NamespaceRecord};
+#[cfg(feature = "magic")]
+pub use ole::OleProbe;
+#[cfg(feature = "magic")]
+pub use probe::ContentProbe;
+#[cfg(feature = "magic")]
+pub use range::{MagicSource, RangeReader};
+use reader::{CacheReader, MimeCacheHeader};
+#[cfg(feature = "scanner")]
+pub use scanner::{ScanEntry, Scanner};
+pub use shared::SharedMimeSearcher;
+pub use sources::DatabaseSource;
+pub use stats::Stats;
+pub use storage::CacheStorage;
+pub use system::system;
+pub use temporary::TemporaryType;
+#[cfg(feature = "notify")]
+pub use watch::WatchedSearcher;
 
 /// The mime type searcher, loads all data from file system when created.
-#[derive(Debug)]
+///
+/// Databases are looked up in `$XDG_DATA_HOME/mime` and the `mime` directory of each entry in
+/// `$XDG_DATA_DIRS`, with earlier directories taking precedence: a glob pattern, the magic rules,
+/// parents or icon of a type, or an alias defined in a more important directory replaces the
+/// definition in any directory after it. Setting `$SMI_MIME_DIR` to a `:`
+/// separated list of database directories replaces that search, which lets tests and containers
+/// point the crate at a fixture database.
+///
+/// A searcher is `Send` and `Sync`, and cloning one is cheap whatever the size of the
+/// databases: clones share the loaded databases, indexes and caches, so a searcher can be
+/// handed to each worker of a thread pool as is. Changing a clone through a `&mut self` method,
+/// such as [`register_temporary_type`](Self::register_temporary_type), doesn't affect the
+/// others.
+#[derive(Clone)]
 pub struct MimeSearcher {
-    mime_cache: MimeCache,
-    globber: Globber,
+    /// Added databases, then one cache per database directory that has one, most important
+    /// first. May be empty, in which case only file name lookups work. Shared with any searchers
+    /// [derived](Self::derive) from this one.
+    databases: Arc<Vec<Arc<dyn MimeDatabase>>>,
+    /// The directories the databases were loaded from, including a parent searcher's, most
+    /// important first.
+    sources: Arc<[DatabaseSource]>,
+    /// Shared with any searchers derived from this one, which may be what builds it.
+    #[cfg(feature = "globs")]
+    glob_index: Arc<LazyGlobber>,
+    /// Databases [registered temporarily](Self::register_temporary_type), oldest first, which
+    /// are also the first of `databases` in reverse.
+    temporary: Arc<Vec<(u64, Arc<dyn MimeDatabase>)>>,
+    #[cfg(feature = "magic")]
+    probes: Arc<Vec<Arc<dyn ContentProbe>>>,
+    #[cfg(feature = "magic")]
+    sniff_memo: Option<Arc<memo::SniffMemo>>,
+    #[cfg(feature = "globs")]
+    unknown_extensions: Option<Arc<telemetry::UnknownExtensions>>,
+    #[cfg(any(feature = "globs", feature = "icons"))]
+    lookup_cache: Option<Arc<lru::LookupCache>>,
+    /// Parents and icons copied out of `databases`, for [`IndexProfile::Fast`].
+    #[cfg(any(feature = "hierarchy", feature = "icons"))]
+    type_index: Option<Arc<index::TypeIndex>>,
+    warnings: Arc<[Warning]>,
+    options: builder::Options,
+    /// The type names lookups return, shared with any searchers derived from this one.
+    names: Arc<intern::Interner>,
+    origin: Arc<Origin>,
+}
+
+/// What a searcher's databases were loaded from, so that [`reload`](MimeSearcher::reload) can
+/// load them again.
+#[derive(Clone, Default)]
+struct Origin {
+    /// Databases [added](MimeSearcherBuilder::add_database), most important first.
+    added: Vec<Arc<dyn MimeDatabase>>,
+    /// The data directories, most important first, including any that had no database.
+    dirs: Vec<PathBuf>,
+    /// What a [derived](MimeSearcher::derive) searcher took from its parent, as it was then.
+    inherited: Option<Arc<Inherited>>,
 }
 
+/// The part of a searcher that searchers derived from it share.
+struct Inherited {
+    databases: Arc<Vec<Arc<dyn MimeDatabase>>>,
+    sources: Arc<[DatabaseSource]>,
+    #[cfg(feature = "globs")]
+    glob_index: Arc<LazyGlobber>,
+    warnings: Arc<[Warning]>,
+}
+
+impl std::fmt::Debug for MimeSearcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("MimeSearcher");
+        debug
+            .field("databases", &self.databases)
+            .field("sources", &self.sources);
+        #[cfg(feature = "globs")]
+        debug.field("glob_index", &self.glob_index);
+        debug.field("temporary", &self.temporary.len());
+        #[cfg(feature = "magic")]
+        debug
+            .field("probes", &self.probes.len())
+            .field("sniff_memo", &self.sniff_memo);
+        #[cfg(feature = "globs")]
+        debug.field("unknown_extensions", &self.unknown_extensions);
+        #[cfg(any(feature = "globs", feature = "icons"))]
+        debug.field("lookup_cache", &self.lookup_cache);
+        debug
+            .field("warnings", &self.warnings)
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
+/// A compiled mime.cache, the [`MimeDatabase`] behind each data directory.
+///
+/// Searchers load these themselves. Build one directly to add a cache from elsewhere with
+/// [`MimeSearcherBuilder::add_database`], or to control where its bytes live through
+/// [`CacheStorage`].
 #[derive(Debug)]
-struct MimeCache {
+pub struct MimeCache {
     cache_header: MimeCacheHeader,
-    cache_data: Vec<u8>,
+    cache_data: Box<dyn CacheStorage>,
+    /// Each section as parsed by the first call that needed it, by [`CacheSection`].
+    sections: [OnceLock<Result<MimeDatabaseModel, Error>>; CacheSection::ALL.len()],
 }
 
-#[derive(Debug, PartialEq, Eq)]
-struct MimeCacheHeader {
-    major_version: u16,
-    minor_version: u16,
-    alias_list_offset: u32,
-    parent_list_offset: u32,
-    literal_list_offset: u32,
-    reverse_suffix_tree_offset: u32,
-    glob_list_offset: u32,
-    magic_list_offset: u32,
-    namespace_list_offset: u32,
-    icons_list_offset: u32,
-    generic_icons_list_offset: u32,
+/// The glob index of a searcher.
+///
+/// The `*.ext` globs of each loaded mime.cache, by far the most numerous, are looked up in the
+/// cache's suffix tree where they lie rather than copied out. Only the globs of globs2 files and
+/// added databases, and the few literals and wildcard globs of caches, are indexed here.
+#[cfg(feature = "globs")]
+#[derive(Debug, Default)]
+struct Globber {
+    /// The patterns, extensions and type names of the indexed globs, each type stored once.
+    names: StrArena,
+    complex_globs: Vec<(Span, GlobEntry)>,
+    /// The caches whose suffix trees `*.ext` globs are looked up in, most important first.
+    caches: Vec<CacheLayer>,
+    /// Every indexed extension, sorted, with its entries in `extension_entries`.
+    extensions: Vec<IndexedExtension>,
+    /// The types claiming each extension, highest weight first, then by name.
+    extension_entries: Vec<GlobEntry>,
+    /// The globs of a parent searcher, overridden by the ones above as if its databases came
+    /// after this one's.
+    parent: Option<Arc<LazyGlobber>>,
+    /// Types whose globs are hidden by a `__NOGLOBS__` entry, with the rank of the most
+    /// important directory deleting them. Their globs in `parent` are hidden entirely.
+    deleted: HashMap<MimeType, usize>,
 }
 
+/// An extension some `*.ext` globs of a [`Globber`] claim.
+#[cfg(feature = "globs")]
 #[derive(Debug)]
-struct Globber {
-    globs2_data: String,
-    complex_globs: Vec<(String, GlobEntry)>,
-    simple_globbing_map: HashMap<String, GlobEntry>,
+struct IndexedExtension {
+    ext: Span,
+    /// The rank of the directory the globs came from.
+    rank: usize,
+    entries: Range<usize>,
+}
+
+/// A mime.cache answering `*.ext` lookups from its suffix tree.
+#[cfg(feature = "globs")]
+#[derive(Debug)]
+struct CacheLayer {
+    /// The position of its directory, most important first.
+    rank: usize,
+    cache: Arc<MimeCache>,
+    source: Option<Arc<Path>>,
+}
+
+/// A [`Globber`] built the first time a file name lookup needs it, so that programs which
+/// never look up a file name don't pay for indexing every glob.
+#[cfg(feature = "globs")]
+struct LazyGlobber {
+    /// What to index, taken when the index is built.
+    layers: Mutex<Vec<Layer>>,
+    parent: Option<Arc<LazyGlobber>>,
+    index: OnceLock<Globber>,
 }
 
+/// A database and the globs2 of the same directory, either of which may be missing.
+struct Layer {
+    /// Where both came from, or `None` for an [added](MimeSearcherBuilder::add_database)
+    /// database.
+    source: Option<DatabaseSource>,
+    database: Option<Arc<dyn MimeDatabase>>,
+    /// The same database when it is a loaded mime.cache, whose suffix tree the glob index
+    /// reads directly.
+    #[cfg(feature = "globs")]
+    cache: Option<Arc<MimeCache>>,
+    #[cfg(feature = "globs")]
+    globs2: Option<Vec<GlobRecord>>,
+}
+
+/// An indexed glob, with its type in the [`Globber`]'s arena, or a type of its own while the
+/// index is being built.
+#[cfg(feature = "globs")]
 #[derive(Debug)]
-struct GlobEntry {
+struct GlobEntry<M = Span> {
+    weight: u8,
+    mime: M,
+    case_sensitive: bool,
+    /// The file defining the glob, shared by all of its globs.
+    source: Option<Arc<Path>>,
+}
+
+/// A glob found by a lookup, borrowed from the index or the cache holding it.
+#[cfg(feature = "globs")]
+#[derive(Debug, Clone, Copy)]
+struct GlobHit<'a> {
     weight: u8,
-    mime: MimeType,
+    mime: MimeTypeRef<'a>,
+    case_sensitive: bool,
+    source: Option<&'a Path>,
+}
+
+/// A candidate type for a file name, along with the weight of the glob that produced it.
+#[cfg(feature = "globs")]
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct WeightedMatch {
+    pub mime: MimeType,
+    pub weight: u8,
+}
+
+#[cfg(feature = "globs")]
+impl WeightedMatch {
+    pub fn new(mime: MimeType, weight: u8) -> Self {
+        WeightedMatch { mime, weight }
+    }
+}
+
+/// The glob a file name matched, as found by [`MimeSearcher::find_glob_match`].
+#[cfg(feature = "globs")]
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct GlobMatch {
+    /// The pattern as written in the database, such as `*.tar.gz` or the literal `Makefile`.
+    pub pattern: String,
+    pub mime: MimeType,
+    pub weight: u8,
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum Error {
-    MimeCacheNotFound,
-    Globs2NotFound,
-    Globs2BadLine(String),
-    InvalidGlob,
-    NotANumber,
-    MissingHeader,
-    MissingGenericIconsList,
-    NoIconFound,
-    CstrUnterminated,
-    InvalidUTF8,
+#[cfg(feature = "globs")]
+impl GlobMatch {
+    /// The extension the pattern matches, without the leading dot, if it is a plain `*.ext`
+    /// glob.
+    pub fn extension(&self) -> Option<&str> {
+        self.pattern
+            .strip_prefix("*.")
+            .filter(|ext| !ext.contains(['*', '?', '[']))
+    }
+}
+
+/// A problem with a loaded database that does not stop lookups but may make them wrong.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Warning {
+    /// The `mime.cache` in this database directory is older than the package files it is built
+    /// from, so `update-mime-database` needs to be run before new definitions take effect.
+    DatabaseStale(PathBuf),
+    /// A database that couldn't be read, with the error it gave, when the bundled database
+    /// stood in for it.
+    #[cfg(feature = "bundled")]
+    DatabaseUnreadable(String),
 }
 
 impl MimeCache {
-    fn new() -> Result<Self, Error> {
-        let cache_contents =
-            std::fs::read("/usr/share/mime/mime.cache").map_err(|_| Error::MimeCacheNotFound)?;
+    /// Loads `mime.cache` from the database directory `dir` into memory, or maps it with the
+    /// `memmap` feature so that processes share the page cache's copy instead of each keeping
+    /// their own.
+    ///
+    /// Fails with [`Error::MimeCacheNotFound`] if there is none, and [`Error::Io`] if it exists
+    /// but can't be read.
+    pub fn load(dir: &Path) -> Result<Self, Error> {
+        let path = dir.join("mime.cache");
+        let not_found = |e: std::io::Error| match e.kind() {
+            std::io::ErrorKind::NotFound => Error::MimeCacheNotFound,
+            _ => Error::io(&path, e),
+        };
+        #[cfg(feature = "memmap")]
+        {
+            let file = std::fs::File::open(&path).map_err(not_found)?;
+            // SAFETY: update-mime-database writes a new cache and renames it over the old one,
+            // so the mapped file is never changed underneath us
+            let map = unsafe { memmap2::Mmap::map(&file) }.map_err(not_found)?;
+            Self::from_storage(map)
+        }
+        #[cfg(not(feature = "memmap"))]
+        Self::from_storage(std::fs::read(&path).map_err(not_found)?)
+    }
+
+    /// Uses the mime.cache held by `storage`, reading from it for as long as the cache lives.
+    ///
+    /// Only the header is checked up front. Corruption elsewhere is reported by the lookups
+    /// that run into it, as [`Error::CorruptCache`].
+    pub fn from_storage(storage: impl CacheStorage + 'static) -> Result<Self, Error> {
         Ok(MimeCache {
-            cache_header: MimeCacheHeader::read_header(
-                cache_contents
-                    .get(0..40)
-                    .ok_or(Error::MissingHeader)?
-                    .try_into()
-                    .expect("cant fail"),
-            ),
-            cache_data: cache_contents,
+            cache_header: MimeCacheHeader::parse(storage.as_ref())?,
+            cache_data: Box::new(storage),
+            sections: Default::default(),
         })
     }
 
+    /// `section` parsed into a model holding only its list, parsed now if this is the first
+    /// time it is needed. A section that can't be parsed keeps failing with the same error
+    /// without affecting the others.
+    fn section(&self, section: CacheSection) -> Result<&MimeDatabaseModel, Error> {
+        self.sections[section as usize]
+            .get_or_init(|| {
+                MimeDatabaseModel::read_section(self.bytes(), &self.cache_header, section)
+            })
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    /// The raw contents of the cache.
+    pub(crate) fn bytes(&self) -> &[u8] {
+        (*self.cache_data).as_ref()
+    }
+
+    /// Whether `dir/mime.cache` predates the `packages` directory next to it or anything in it,
+    /// the same check xdgmime makes.
+    fn is_stale(dir: &Path) -> bool {
+        let modified = |path: &Path| path.metadata().and_then(|m| m.modified()).ok();
+        let Some(cache) = modified(&dir.join("mime.cache")) else {
+            return false;
+        };
+        let packages = dir.join("packages");
+        std::fs::read_dir(&packages)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| modified(&entry.ok()?.path()))
+            .chain(modified(&packages))
+            .any(|package| package > cache)
+    }
+
+    fn reader(&self, section: CacheSection) -> CacheReader<'_> {
+        CacheReader::new(self.bytes(), section)
+    }
+
     // GenericIconsList:
     // IconsList:
     // 4			CARD32		N_ICONS
@@ -109,302 +513,4344 @@ impl MimeCache {
     // IconListEntry:
     // 4			CARD32		MIME_TYPE_OFFSET
     // 4			CARD32		ICON_NAME_OFFSET
+    #[cfg(feature = "icons")]
     fn find_icon_for_mimetype(&self, mime_type: MimeType) -> Result<String, Error> {
         // Takes in a mimetype, e.g:
         // application/pdf -> x-office-document
 
-        const STRIDE: usize = 8;
-
+        let data = self.reader(CacheSection::GenericIconsList);
         let start = self.cache_header.generic_icons_list_offset as usize;
 
-        let num_icons = get_u32_panics(self.cache_data.as_slice(), start);
-
-        let list_start = start + 4;
-
         // The given list is sorted, meaning a binary search can be done
+        let ptr = data
+            .bsearch_list(start, 8, mime_type.essence_str())?
+            .ok_or(Error::NoIconFound)?;
+        // Only load icon name if we have matched
+        Ok(data.cstr_at(ptr + 4)?.to_string())
+    }
 
-        let mut min_index: usize = 0;
-        let mut max_index: usize = num_icons as usize;
-        let mut index: usize = max_index / 2;
-
-        loop {
-            let ptr = list_start + index * STRIDE;
-
-            let mime_type_offset = get_u32_panics(self.cache_data.as_slice(), ptr) as usize;
-            let found_mime_type: MimeType =
-                CStr::from_bytes_until_nul(self.cache_data.get(mime_type_offset..).unwrap())
-                    .map_err(|_e| Error::CstrUnterminated)?
-                    .to_str()
-                    .map_err(|_| Error::InvalidUTF8)?
-                    .to_string()
-                    .into();
-
-            let ord = mime_type.cmp(&found_mime_type);
-            if ord == Ordering::Less {
-                max_index = index;
-                index = (max_index + min_index) / 2;
-            } else if ord == Ordering::Greater {
-                min_index = index;
-                index = (max_index + min_index) / 2;
-            } else {
-                debug_assert_eq!(found_mime_type, mime_type);
-                // Only load icon name if we have matched
-                let icon_name_offset = get_u32_panics(self.cache_data.as_slice(), ptr + 4) as usize;
-                let icon_name =
-                    CStr::from_bytes_until_nul(self.cache_data.get(icon_name_offset..).unwrap())
-                        .map_err(|_e| Error::CstrUnterminated)?
-                        .to_str()
-                        .map_err(|_| Error::InvalidUTF8)?;
+    /// The globs a glob index has to copy out of this cache: all but the `*.ext` globs of the
+    /// suffix tree, which [`extension_globs`](Self::extension_globs) finds in place.
+    #[cfg(feature = "globs")]
+    fn globs_to_index(&self) -> Result<Vec<GlobRecord>, Error> {
+        // The tree holds suffixes backwards, so those of `*.ext` globs end in the dot
+        self.globs_where(|reversed| reversed.last() != Some(&'.'))
+    }
 
-                return Ok(icon_name.to_string());
-            }
+    /// The glob list, then the literal list, then the globs of the suffix tree whose suffix,
+    /// read backwards, `keep_suffix` accepts.
+    #[cfg(feature = "globs")]
+    fn globs_where(&self, keep_suffix: impl Fn(&[char]) -> bool) -> Result<Vec<GlobRecord>, Error> {
+        let header = &self.cache_header;
+        let mut globs =
+            model::read_glob_list(self.reader(CacheSection::GlobList), header.glob_list_offset)?;
+        globs.extend(model::read_glob_list(
+            self.reader(CacheSection::LiteralList),
+            header.literal_list_offset,
+        )?);
+        globs.extend(model::read_suffix_tree_where(
+            self.reader(CacheSection::ReverseSuffixTree),
+            header.reverse_suffix_tree_offset,
+            keep_suffix,
+        )?);
+        Ok(globs)
+    }
 
-            if index == max_index || index == min_index {
-                break;
-            }
-        }
+    /// The type, weight and case sensitivity of each `*.ext` glob for exactly `ext`, from the
+    /// suffix tree.
+    #[cfg(feature = "globs")]
+    fn extension_globs(&self, ext: &str) -> Result<Vec<(MimeTypeRef<'_>, u8, bool)>, Error> {
+        let found = model::lookup_suffix(
+            self.reader(CacheSection::ReverseSuffixTree),
+            self.cache_header.reverse_suffix_tree_offset,
+            ext.chars().rev().chain(['.']),
+        )?;
+        Ok(found
+            .into_iter()
+            .map(|(mime, weight, case_sensitive)| {
+                (MimeTypeRef::unchecked(mime), weight, case_sensitive)
+            })
+            .collect())
+    }
 
-        Err(Error::NoIconFound)
+    /// Every extension the suffix tree has a `*.ext` glob for.
+    #[cfg(feature = "globs")]
+    fn extensions(&self) -> Result<Vec<String>, Error> {
+        let globs = model::read_suffix_tree_where(
+            self.reader(CacheSection::ReverseSuffixTree),
+            self.cache_header.reverse_suffix_tree_offset,
+            |reversed| reversed.last() == Some(&'.'),
+        )?;
+        Ok(globs
+            .into_iter()
+            .filter_map(|glob| Some(glob.pattern.strip_prefix("*.")?.to_string()))
+            .collect())
     }
 }
 
+#[cfg(feature = "globs")]
 impl Globber {
-    fn new(cache: &MimeCache) -> Result<Self, Error> {
-        let mut simple_globbing_map = HashMap::new();
-        let mut complex_globs = Vec::new();
-
-        let globs2_data =
-            std::fs::read_to_string("/usr/share/mime/globs2").map_err(|_| Error::Globs2NotFound)?;
+    /// Builds the index from the mime.cache and globs2 of each database directory, most important
+    /// directory first.
+    ///
+    /// A pattern defined by a directory hides the same pattern in all less important ones, so a
+    /// user's `*.foo` replaces the system's rather than competing with it. A `__NOGLOBS__` entry
+    /// hides every glob of its type in less important directories.
+    ///
+    /// The `*.ext` globs of loaded caches aren't indexed, lookups apply the same rules to them
+    /// as they find them.
+    ///
+    /// Fails with the errors of every database or globs2 that can't be read.
+    fn new(layers: &[Layer]) -> Result<Self, Error> {
+        let mut caches = Vec::new();
 
-        for (k, v) in Self::get_globs_from_cache(cache)?
-            .into_iter()
-            .chain(Self::get_globs2_data(&globs2_data)?.into_iter())
+        let mut globs = Vec::new();
+        let mut claimed: HashMap<String, usize> = HashMap::new();
+        let mut deleted: HashMap<MimeType, usize> = HashMap::new();
+        let mut errors = Vec::new();
+        for (
+            rank,
+            Layer {
+                source: from,
+                database,
+                cache,
+                globs2,
+            },
+        ) in layers.iter().enumerate()
         {
-            if let Some(k) = k.strip_prefix("*.")
-                && !(k.contains('?') || k.contains('[') || k.contains("*"))
-            {
-                simple_globbing_map.insert(k.to_string(), v);
+            let source = |file: &str| from.as_ref().map(|from| Arc::from(from.dir.join(file)));
+            let mut layer = Vec::new();
+            let found = match cache {
+                Some(cache) => {
+                    caches.push(CacheLayer {
+                        rank,
+                        cache: cache.clone(),
+                        source: source("mime.cache"),
+                    });
+                    Some(cache.globs_to_index())
+                }
+                None => database.as_ref().map(|database| database.globs()),
+            };
+            let entries = |globs: Vec<GlobRecord>, source: Option<Arc<Path>>| {
+                globs.into_iter().map(move |glob| {
+                    let entry = GlobEntry {
+                        weight: glob.weight,
+                        mime: glob.mime,
+                        case_sensitive: glob.case_sensitive,
+                        source: source.clone(),
+                    };
+                    (glob.pattern, entry)
+                })
+            };
+            match found {
+                Some(Ok(globs)) => layer.extend(entries(globs, source("mime.cache"))),
+                Some(Err(e)) => errors.push(e),
+                None => {}
+            }
+            if let Some(globs) = globs2 {
+                layer.extend(entries(globs.clone(), source("globs2")));
+            }
+            // __NOGLOBS__ marks a type whose globs this directory replaces entirely, so
+            // drop that type's globs from all less important directories
+            let (markers, layer): (Vec<_>, Vec<_>) =
+                layer.into_iter().partition(|(k, _)| k == NO_GLOBS_MARKER);
+            for (k, v) in layer {
+                if deleted.get(&v.mime).is_some_and(|&r| r < rank) {
+                    continue;
+                }
+                if *claimed.entry(k.clone()).or_insert(rank) == rank {
+                    globs.push((rank, k, v));
+                }
+            }
+            for (_, v) in markers {
+                deleted.entry(v.mime).or_insert(rank);
+            }
+        }
+        if let Some(error) = Error::combine(errors) {
+            return Err(error);
+        }
+
+        // Extensions are keyed lowercased unless their glob is case sensitive, as lookups try
+        // the extension as written and lowercased
+        let mut simple: BTreeMap<Cow<'_, str>, (usize, Vec<GlobEntry<MimeType>>)> = BTreeMap::new();
+        let mut complex = Vec::new();
+        for (rank, k, v) in &globs {
+            let ext = k
+                .strip_prefix("*.")
+                .filter(|ext| !(ext.contains('?') || ext.contains('[') || ext.contains("*")));
+            if let Some(ext) = ext {
+                let mut ext = Cow::Borrowed(ext);
+                if !v.case_sensitive {
+                    ext = ascii_lowercase(ext);
+                }
+                let (_, entries) = simple.entry(ext).or_insert_with(|| (*rank, Vec::new()));
+                add_entry(entries, v);
             } else {
-                complex_globs.push((k, v));
+                complex.push((k, v));
             };
         }
-        // println!("glob hashmap: {:#?}", hashmap);
+
+        // Everything is copied into one arena, types once however many globs they have
+        let mut names = StrArena::default();
+        let mut types: HashMap<MimeType, Span> = HashMap::new();
+        let mut entry = |names: &mut StrArena, v: &GlobEntry<MimeType>| GlobEntry {
+            weight: v.weight,
+            mime: *types
+                .entry(v.mime.clone())
+                .or_insert_with(|| names.push(v.mime.essence_str())),
+            case_sensitive: v.case_sensitive,
+            source: v.source.clone(),
+        };
+        let mut extensions = Vec::with_capacity(simple.len());
+        let mut extension_entries = Vec::new();
+        for (ext, (rank, entries)) in simple {
+            let start = extension_entries.len();
+            extension_entries.extend(entries.iter().map(|v| entry(&mut names, v)));
+            extensions.push(IndexedExtension {
+                ext: names.push(&ext),
+                rank,
+                entries: start..extension_entries.len(),
+            });
+        }
+        let complex_globs = complex
+            .into_iter()
+            .map(|(k, v)| (names.push(k), entry(&mut names, v)))
+            .collect();
+        names.shrink_to_fit();
+        extension_entries.shrink_to_fit();
 
         Ok(Globber {
-            globs2_data,
-            simple_globbing_map,
+            names,
             complex_globs,
+            caches,
+            extensions,
+            extension_entries,
+            parent: None,
+            deleted,
         })
     }
 
-    fn lookup_filename(&self, name: &std::path::Path) -> Option<MimeType> {
-        if let Some(ext) = name.extension()
-            && let Some(entry) = self
-                .simple_globbing_map
-                .get(&ext.to_str()?.to_ascii_lowercase())
-        {
-            return Some(entry.mime.clone());
+    /// The rank and entries of `*.ext` in the index, matching `ext` exactly.
+    fn indexed_extension(&self, ext: &str) -> Option<(usize, &[GlobEntry])> {
+        let i = self
+            .extensions
+            .binary_search_by(|indexed| self.names.get(indexed.ext).cmp(ext))
+            .ok()?;
+        let indexed = &self.extensions[i];
+        Some((
+            indexed.rank,
+            &self.extension_entries[indexed.entries.clone()],
+        ))
+    }
+
+    /// Every extension in the index, sorted.
+    fn indexed_extensions(&self) -> impl Iterator<Item = &str> {
+        self.extensions
+            .iter()
+            .map(|indexed| self.names.get(indexed.ext))
+    }
+
+    /// The type of every indexed glob, once per glob.
+    fn indexed_types(&self) -> impl Iterator<Item = MimeTypeRef<'_>> {
+        let complex = self.complex_globs.iter().map(|(_, entry)| entry);
+        self.extension_entries
+            .iter()
+            .chain(complex)
+            .map(|entry| self.hit(entry).mime)
+    }
+
+    fn hit<'a>(&'a self, entry: &'a GlobEntry) -> GlobHit<'a> {
+        GlobHit {
+            weight: entry.weight,
+            mime: MimeTypeRef::unchecked(self.names.get(entry.mime)),
+            case_sensitive: entry.case_sensitive,
+            source: entry.source.as_deref(),
+        }
+    }
+
+    /// This globber followed by its parents, most important first.
+    fn layers(&self) -> impl Iterator<Item = &Globber> {
+        std::iter::successors(Some(self), |layer| {
+            layer.parent.as_deref().map(LazyGlobber::get)
+        })
+    }
+
+    /// The best glob matching `name`, along with its weight, and the pattern that matched.
+    ///
+    /// Names don't have to be UTF-8, as legacy-encoded ones aren't. On Unix their bytes are
+    /// matched as they are, so bytes that aren't UTF-8 only ever match wildcards and the rest of
+    /// the name still matches as usual.
+    fn lookup_filename_entry(&self, name: &std::path::Path) -> Option<(GlobHit<'_>, Matched<'_>)> {
+        if let Some((ext, entry)) = self.extension_match(name) {
+            let matched = Matched {
+                text: Cow::Owned(ext),
+                by_extension: true,
+            };
+            return Some((entry, matched));
         }
+        // The glob crate matches text, so bytes that aren't UTF-8 become U+FFFD and patterns
+        // holding a U+FFFD of their own are skipped
+        #[cfg(feature = "complex_globs")]
+        let bytes = name_bytes(name.as_os_str());
+        #[cfg(feature = "complex_globs")]
+        let name = String::from_utf8_lossy(&bytes);
         #[cfg(feature = "complex_globs")]
-        for (k, v) in &self.complex_globs {
-            let pattern = glob::Pattern::from_str(k).ok()?;
-            if pattern.matches_path(name) {
-                return Some(v.mime.clone());
+        let replaced = matches!(name, Cow::Owned(_));
+        #[cfg(feature = "complex_globs")]
+        for (i, layer) in self.layers().enumerate() {
+            for (k, v) in &layer.complex_globs {
+                let k = layer.names.get(*k);
+                let hit = layer.hit(v);
+                let hidden = self.layers().take(i).any(|above| {
+                    above.deleted.contains_key(hit.mime.essence_str())
+                        || above
+                            .complex_globs
+                            .iter()
+                            .any(|(other, _)| above.names.get(*other) == k)
+                });
+                if hidden || (replaced && k.contains(char::REPLACEMENT_CHARACTER)) {
+                    continue;
+                }
+                let pattern: glob::Pattern = k.parse().ok()?;
+                if pattern.matches(&name) {
+                    let matched = Matched {
+                        text: Cow::Borrowed(k),
+                        by_extension: false,
+                    };
+                    return Some((hit, matched));
+                }
             }
         }
         None
     }
-    // GlobList:
-    // 4			CARD32		N_GLOBS
-    // 12*N_GLOBS	GlobEntry
-    //
-    // GlobEntry:
-    //
-    // 4			CARD32		GLOB_OFFSET
-    // 4			CARD32		MIME_TYPE_OFFSET
-    // 4			CARD32		WEIGHT in lower 8 bits
-    //                              FLAGS in rest:
-    //                              0x100 = case-sensitive
-    fn get_globs_from_cache(cache: &MimeCache) -> Result<Vec<(String, GlobEntry)>, Error> {
-        const STRIDE: usize = 12;
 
-        let start = cache.cache_header.glob_list_offset as usize;
+    /// The longest extension of `name` that some `*.ext` glob claims, lowercased unless that
+    /// glob is case sensitive, so that
+    /// `a.tar.gz` is looked up as `tar.gz` before `gz`. Dots leading the name don't start an
+    /// extension, as in [`Path::extension`].
+    fn matched_extension(&self, name: &Path) -> Option<String> {
+        self.extension_match(name).map(|(ext, _)| ext)
+    }
+
+    /// The [`matched_extension`](Self::matched_extension) of `name` along with the best entry
+    /// for it.
+    fn extension_match(&self, name: &Path) -> Option<(String, GlobHit<'_>)> {
+        let name = name_bytes(name.file_name()?);
+        let leading = name.iter().take_while(|&&b| b == b'.').count();
+        (leading..name.len())
+            .filter(|&i| name[i] == b'.')
+            // Patterns are UTF-8, so an extension that isn't can't match any
+            .filter_map(|i| std::str::from_utf8(&name[i + 1..]).ok())
+            .filter(|ext| !ext.is_empty())
+            .find_map(|ext| {
+                let best = self.lookup_extension(ext).into_iter().next()?;
+                let mut ext = Cow::Borrowed(ext);
+                if !best.case_sensitive {
+                    ext = ascii_lowercase(ext);
+                }
+                Some((ext.into_owned(), best))
+            })
+    }
 
-        let num_globs = get_u32_panics(cache.cache_data.as_slice(), start);
+    /// The entries of the most important layer defining `*.ext`, minus any types a layer above
+    /// it deleted.
+    fn lookup_extension(&self, ext: &str) -> Vec<GlobHit<'_>> {
+        let mut deleted: Vec<&HashMap<MimeType, usize>> = Vec::new();
+        for layer in self.layers() {
+            let hits = layer.own_extension(ext);
+            if !hits.is_empty() {
+                return hits
+                    .into_iter()
+                    .filter(|hit| {
+                        !deleted
+                            .iter()
+                            .any(|d| d.contains_key(hit.mime.essence_str()))
+                    })
+                    .collect();
+            }
+            deleted.push(&layer.deleted);
+        }
+        Vec::new()
+    }
 
-        let list_start = start + 4;
+    /// The entries of the most important directory of this layer alone defining `*.ext`,
+    /// whether in a cache's suffix tree, the index or both. Case-sensitive globs match `ext` as
+    /// written, the others match it lowercased.
+    fn own_extension(&self, ext: &str) -> Vec<GlobHit<'_>> {
+        let lower = ascii_lowercase(Cow::Borrowed(ext));
+        let mut hits =
+            self.own_extension_key(&lower, |case_sensitive| !case_sensitive || lower == ext);
+        if lower != ext {
+            for hit in self.own_extension_key(ext, |case_sensitive| case_sensitive) {
+                add_hit(&mut hits, hit);
+            }
+        }
+        hits
+    }
 
-        let mut output = Vec::new();
+    /// [`own_extension`](Self::own_extension) for the globs stored under `key` that `applies`
+    /// to, given whether they are case sensitive.
+    fn own_extension_key(&self, key: &str, applies: impl Fn(bool) -> bool) -> Vec<GlobHit<'_>> {
+        let (indexed_rank, indexed) = match self.indexed_extension(key) {
+            Some((rank, entries)) => {
+                let hits: Vec<_> = entries
+                    .iter()
+                    .filter(|entry| applies(entry.case_sensitive))
+                    .map(|entry| self.hit(entry))
+                    .collect();
+                (if hits.is_empty() { usize::MAX } else { rank }, hits)
+            }
+            None => (usize::MAX, Vec::new()),
+        };
+        for layer in self
+            .caches
+            .iter()
+            .take_while(|layer| layer.rank <= indexed_rank)
+        {
+            // A corrupt tree can't fail the lookup, so it just matches nothing
+            let found = layer.cache.extension_globs(key).unwrap_or_default();
+            let mut hits: Vec<GlobHit<'_>> = Vec::new();
+            for (mime, weight, case_sensitive) in found {
+                if !applies(case_sensitive)
+                    || self
+                        .deleted
+                        .get(mime.essence_str())
+                        .is_some_and(|&r| r < layer.rank)
+                {
+                    continue;
+                }
+                let hit = GlobHit {
+                    weight,
+                    mime,
+                    case_sensitive,
+                    source: layer.source.as_deref(),
+                };
+                add_hit(&mut hits, hit);
+            }
+            if hits.is_empty() {
+                continue;
+            }
+            if layer.rank == indexed_rank {
+                for hit in indexed {
+                    add_hit(&mut hits, hit);
+                }
+            }
+            return hits;
+        }
+        indexed
+    }
 
-        for i in (list_start..list_start + num_globs as usize * STRIDE).step_by(STRIDE) {
-            let glob_offset = get_u32_panics(cache.cache_data.as_slice(), i) as usize;
+    /// The globs in the globs2 text `globs`, case sensitive where the optional flags field after
+    /// the pattern says `cs`.
+    fn globs2_records(globs: &str) -> Result<Vec<GlobRecord>, Error> {
+        let mut records = Vec::new();
+        for line in globs.lines().filter(|line| !line.starts_with('#')) {
+            let mut fields = line.splitn(4, ':');
+            let (Some(weight), Some(mime), Some(pattern)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(Error::Globs2BadLine(line.to_string()));
+            };
+            let flags = fields.next().unwrap_or_default();
+            records.push(GlobRecord {
+                pattern: pattern.to_string(),
+                mime: MimeType::unchecked(mime),
+                weight: weight.parse().map_err(|_| Error::NotANumber)?,
+                case_sensitive: flags.split(',').any(|flag| flag == "cs"),
+            });
+        }
+        Ok(records)
+    }
+}
 
-            let glob = CStr::from_bytes_until_nul(cache.cache_data.get(glob_offset..).unwrap())
-                .map_err(|_| Error::CstrUnterminated)?
-                .to_str()
-                .map_err(|_| Error::InvalidUTF8)?;
+/// How a file name matched a glob, from which the pattern is only spelled out for callers that
+/// want it.
+#[cfg(feature = "globs")]
+struct Matched<'a> {
+    /// The extension of a `*.ext` glob, lowercased unless the glob is case sensitive, or else the
+    /// literal or wildcard pattern.
+    text: Cow<'a, str>,
+    by_extension: bool,
+}
 
-            let mime_offset = get_u32_panics(cache.cache_data.as_slice(), i + 4) as usize;
+#[cfg(feature = "globs")]
+impl Matched<'_> {
+    fn pattern(&self) -> Cow<'_, str> {
+        if self.by_extension {
+            Cow::Owned(format!("*.{}", self.text))
+        } else {
+            Cow::Borrowed(&self.text)
+        }
+    }
+}
 
-            let mime = CStr::from_bytes_until_nul(cache.cache_data.get(mime_offset..).unwrap())
-                .map_err(|_| Error::CstrUnterminated)?
-                .to_str()
-                .map_err(|_| Error::InvalidUTF8)?;
+/// `s` in ASCII lowercase, copied only if it has uppercase letters and isn't already owned.
+#[cfg(feature = "globs")]
+fn ascii_lowercase(mut s: Cow<'_, str>) -> Cow<'_, str> {
+    if s.bytes().any(|b| b.is_ascii_uppercase()) {
+        s.to_mut().make_ascii_lowercase();
+    }
+    s
+}
 
-            let meta = get_u32_panics(cache.cache_data.as_slice(), i + 8) as usize;
+/// The bytes of the file name `name`, as the OS has them on Unix so that names in legacy
+/// encodings are matched unchanged. Elsewhere what isn't Unicode becomes U+FFFD.
+#[cfg(feature = "globs")]
+fn name_bytes(name: &OsStr) -> Cow<'_, [u8]> {
+    #[cfg(unix)]
+    {
+        Cow::Borrowed(std::os::unix::ffi::OsStrExt::as_bytes(name))
+    }
+    #[cfg(not(unix))]
+    match name.to_string_lossy() {
+        Cow::Borrowed(name) => Cow::Borrowed(name.as_bytes()),
+        Cow::Owned(name) => Cow::Owned(name.into_bytes()),
+    }
+}
 
-            let weight = (meta & 0xFF) as u8;
+/// Adds `entry` to the entries of one extension, keeping the highest weight of a type that is
+/// already there, and the entries sorted by weight and then name.
+#[cfg(feature = "globs")]
+fn add_entry(entries: &mut Vec<GlobEntry<MimeType>>, entry: &GlobEntry<MimeType>) {
+    match entries.iter_mut().find(|e| e.mime == entry.mime) {
+        Some(existing) => {
+            existing.weight = existing.weight.max(entry.weight);
+            existing.case_sensitive &= entry.case_sensitive;
+        }
+        None => entries.push(GlobEntry {
+            weight: entry.weight,
+            mime: entry.mime.clone(),
+            case_sensitive: entry.case_sensitive,
+            source: entry.source.clone(),
+        }),
+    }
+    entries.sort_by(|a, b| b.weight.cmp(&a.weight).then_with(|| a.mime.cmp(&b.mime)));
+}
 
-            output.push((
-                glob.to_string(),
-                GlobEntry {
-                    mime: mime.to_string().into(),
-                    weight,
-                },
-            ));
+/// [`add_entry`] for hits found by a lookup.
+#[cfg(feature = "globs")]
+fn add_hit<'a>(hits: &mut Vec<GlobHit<'a>>, hit: GlobHit<'a>) {
+    match hits.iter_mut().find(|h| h.mime == hit.mime) {
+        Some(existing) => {
+            existing.weight = existing.weight.max(hit.weight);
+            existing.case_sensitive &= hit.case_sensitive;
         }
-        Ok(output)
+        None => hits.push(hit),
     }
+    hits.sort_by(|a, b| b.weight.cmp(&a.weight).then_with(|| a.mime.cmp(&b.mime)));
+}
 
-    fn get_globs2_data(globs: &str) -> Result<Vec<(String, GlobEntry)>, Error> {
-        let mut output = Vec::new();
-        for line in globs.lines() {
-            if line.starts_with('#') {
-                continue;
-            }
-            let line_conents: Vec<&str> = line.splitn(3, ':').collect();
-            if line_conents.len() != 3 {
-                return Err(Error::Globs2BadLine(line.to_string()));
-            }
+#[cfg(feature = "globs")]
+impl LazyGlobber {
+    fn new(layers: Vec<Layer>, parent: Option<Arc<LazyGlobber>>) -> Self {
+        LazyGlobber {
+            layers: Mutex::new(layers),
+            parent,
+            index: OnceLock::new(),
+        }
+    }
 
-            let (weight_raw, mime_string, glob_string) = (
-                line_conents[0].to_string(),
-                line_conents[1].to_string(),
-                line_conents[2].to_string(),
-            );
+    /// The index, built now if this is the first time it is needed.
+    ///
+    /// globs2 files were parsed when they were loaded, so only a mime.cache whose globs turn
+    /// out to be corrupt can stop it being built. That is logged, and file names then match
+    /// only the globs of any parent.
+    fn get(&self) -> &Globber {
+        self.index.get_or_init(|| {
+            let layers =
+                std::mem::take(&mut *self.layers.lock().unwrap_or_else(|e| e.into_inner()));
+            let mut globber = Globber::new(&layers).unwrap_or_else(|e| {
+                log::warn!("file name lookups are off, as the globs can't be indexed: {e}");
+                Globber::default()
+            });
+            globber.parent = self.parent.clone();
+            globber
+        })
+    }
+}
 
-            output.push((
-                glob_string,
-                GlobEntry {
-                    weight: weight_raw.parse().map_err(|_| Error::NotANumber)?,
-                    mime: mime_string.into(),
-                },
-            ));
+#[cfg(feature = "globs")]
+impl std::fmt::Debug for LazyGlobber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.index.get() {
+            Some(globber) => globber.fmt(f),
+            None => f.write_str("LazyGlobber(not built yet)"),
         }
-        Ok(output)
     }
 }
 
 impl MimeSearcher {
     pub fn new() -> Result<Self, Error> {
-        let mime_cache = MimeCache::new()?;
+        Self::with_dirs(&dirs::mime_dirs())
+    }
+
+    /// Loads the databases in `dirs` instead of the XDG data directories.
+    ///
+    /// Each entry is a database root such as `/usr/share/mime`, containing `mime.cache` and
+    /// `globs2`. Earlier entries take precedence.
+    ///
+    /// Fails with [`Error::MimeCacheNotFound`] if none of them has a database, unless the
+    /// `bundled` feature provides one to use instead. It also stands in when none can be read,
+    /// leaving the errors as [warnings](Self::warnings).
+    pub fn with_dirs(dirs: &[PathBuf]) -> Result<Self, Error> {
+        Self::load(Vec::new(), dirs, builder::Options::default())
+    }
+
+    /// Loads the databases in `dirs` behind `added`, which are consulted first.
+    fn load(
+        added: Vec<Arc<dyn MimeDatabase>>,
+        dirs: &[PathBuf],
+        options: builder::Options,
+    ) -> Result<Self, Error> {
+        let origin = Origin {
+            added,
+            dirs: dirs.to_vec(),
+            inherited: None,
+        };
+        Self::load_from(origin, options)
+    }
+
+    /// Loads the databases `origin` names, with the same rules whether for the first time or
+    /// on [`reload`](Self::reload).
+    fn load_from(origin: Origin, options: builder::Options) -> Result<Self, Error> {
+        let mut errors = Vec::new();
+        let (loaded, mut warnings) = Self::load_databases(&origin.dirs, options, &mut errors);
+        let databases: Vec<_> = origin
+            .added
+            .iter()
+            .cloned()
+            .map(|database| Layer {
+                source: None,
+                database: Some(database),
+                #[cfg(feature = "globs")]
+                cache: None,
+                #[cfg(feature = "globs")]
+                globs2: None,
+            })
+            .chain(loaded)
+            .collect();
+        // With nothing installed or readable the bundled database stands in, and what couldn't
+        // be read is only warned about
+        #[cfg(feature = "bundled")]
+        let databases = if origin.inherited.is_none() && databases.is_empty() {
+            match bundled::layer() {
+                Ok(layer) => {
+                    log::warn!("no readable mime database found, using the bundled one instead");
+                    for error in errors.drain(..) {
+                        log::warn!("{error}");
+                        warnings.push(Warning::DatabaseUnreadable(error.to_string()));
+                    }
+                    vec![layer]
+                }
+                Err(e) => {
+                    errors.push(e);
+                    databases
+                }
+            }
+        } else {
+            databases
+        };
+        match &origin.inherited {
+            Some(inherited) => warnings.extend(inherited.warnings.iter().cloned()),
+            // Either source is enough on its own: mime.cache holds every glob, and globs2 alone
+            // still answers file name lookups
+            None if databases.is_empty() && errors.is_empty() => {
+                if options.mime_guess_fallback() {
+                    log::warn!("no mime database found, using mime_guess's table instead");
+                } else {
+                    return Err(Error::MimeCacheNotFound);
+                }
+            }
+            None => {}
+        }
+        Self::from_databases(databases, warnings, errors, origin, options)
+    }
+
+    /// A searcher with the databases in `dirs` layered over this one's, as if they came first
+    /// in the search path.
+    ///
+    /// This one's loaded databases, glob index and probes are shared rather than copied, so a
+    /// host can give each plugin its own customisations cheaply. Changes to the derived
+    /// searcher, including registering probes, don't affect this one. `dirs` may be empty or
+    /// hold no databases, in which case the derived searcher behaves like this one.
+    pub fn derive(&self, dirs: &[PathBuf]) -> Result<MimeSearcher, Error> {
+        let inherited = Inherited {
+            databases: self.databases.clone(),
+            sources: self.sources.clone(),
+            #[cfg(feature = "globs")]
+            glob_index: self.glob_index.clone(),
+            warnings: self.warnings.clone(),
+        };
+        let origin = Origin {
+            added: Vec::new(),
+            dirs: dirs.to_vec(),
+            inherited: Some(Arc::new(inherited)),
+        };
+        let derived = Self::load_from(origin, self.options)?;
         Ok(MimeSearcher {
-            globber: Globber::new(&mime_cache)?,
-            mime_cache,
+            #[cfg(feature = "magic")]
+            probes: self.probes.clone(),
+            names: self.names.clone(),
+            ..derived
         })
     }
 
+    /// Loads the databases again from the same directories, so that a long-running program
+    /// picks up types installed since it started, and swaps them in.
+    ///
+    /// Everything else about the searcher is kept: databases
+    /// [added](MimeSearcherBuilder::add_database) when it was built, types
+    /// [registered temporarily](Self::register_temporary_type), probes, settings and interned
+    /// names. Cached lookups are forgotten, as the answers may have changed. A
+    /// [derived](Self::derive) searcher reloads its own directories, and keeps the databases
+    /// of the searcher it was derived from as they were when it was derived.
+    ///
+    /// ```no_run
+    /// # use shared_mime_info as smi;
+    /// let mut searcher = smi::MimeSearcher::new().unwrap();
+    /// // After a package installs new types
+    /// searcher.reload().unwrap();
+    /// ```
+    ///
+    /// Fails as [`MimeSearcher::with_dirs`] does, in which case the searcher is left as it was.
+    pub fn reload(&mut self) -> Result<(), Error> {
+        let fresh = Self::load_from(Origin::clone(&self.origin), self.options)?;
+        let old = std::mem::replace(self, fresh);
+        #[cfg(feature = "magic")]
+        {
+            self.probes = old.probes;
+            self.sniff_memo = old.sniff_memo;
+        }
+        #[cfg(feature = "globs")]
+        {
+            self.unknown_extensions = old.unknown_extensions;
+        }
+        #[cfg(any(feature = "globs", feature = "icons"))]
+        {
+            self.lookup_cache = old.lookup_cache;
+        }
+        self.names = old.names;
+        // Puts the temporary types back in front, and clears the caches
+        self.update_temporary(|temporary| *temporary = Arc::unwrap_or_clone(old.temporary));
+        Ok(())
+    }
+
+    /// The mime.cache and globs2 of each directory that has either. globs2 is skipped when
+    /// globs are turned off.
+    ///
+    /// A file that exists but can't be loaded is left out and its error added to `errors`, so
+    /// that every broken file is reported at once.
+    fn load_databases(
+        dirs: &[PathBuf],
+        options: builder::Options,
+        errors: &mut Vec<Error>,
+    ) -> (Vec<Layer>, Vec<Warning>) {
+        let mut databases = Vec::new();
+        let mut warnings = Vec::new();
+        for dir in dirs {
+            let mut source = DatabaseSource {
+                dir: dir.clone(),
+                cache: None,
+                cache_version: None,
+                cache_modified: None,
+                globs2: None,
+            };
+            let cache = match MimeCache::load(dir) {
+                Ok(cache) => {
+                    let path = dir.join("mime.cache");
+                    let header = &cache.cache_header;
+                    source.cache_version = Some((header.major_version, header.minor_version));
+                    source.cache_modified = path.metadata().and_then(|m| m.modified()).ok();
+                    source.cache = Some(path);
+                    if MimeCache::is_stale(dir) {
+                        log::warn!(
+                            "{} is older than its packages, run update-mime-database",
+                            dir.join("mime.cache").display()
+                        );
+                        warnings.push(Warning::DatabaseStale(dir.clone()));
+                    }
+                    Some(Arc::new(cache))
+                }
+                Err(Error::MimeCacheNotFound) => None,
+                Err(e) => {
+                    errors.push(e);
+                    None
+                }
+            };
+            let globs2 = Self::load_globs2(dir, options, errors);
+            if globs2.is_some() {
+                source.globs2 = Some(dir.join("globs2"));
+            }
+            if cache.is_some() || globs2.is_some() {
+                databases.push(Layer {
+                    source: Some(source),
+                    database: cache.clone().map(|cache| cache as Arc<dyn MimeDatabase>),
+                    #[cfg(feature = "globs")]
+                    cache,
+                    #[cfg(feature = "globs")]
+                    globs2,
+                });
+            }
+        }
+        (databases, warnings)
+    }
+
+    // The globs2 of `dir`, if it has one and globs are wanted
+    #[cfg(feature = "globs")]
+    fn load_globs2(
+        dir: &Path,
+        options: builder::Options,
+        errors: &mut Vec<Error>,
+    ) -> Option<Vec<GlobRecord>> {
+        let path = dir.join("globs2");
+        match options.globs.then(|| std::fs::read_to_string(&path)) {
+            None => None,
+            Some(Ok(globs2)) => match Globber::globs2_records(&globs2) {
+                Ok(globs) => Some(globs),
+                Err(e) => {
+                    errors.push(e);
+                    None
+                }
+            },
+            Some(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Some(Err(e)) => {
+                errors.push(Error::io(&path, e));
+                None
+            }
+        }
+    }
+
+    #[cfg(not(feature = "globs"))]
+    fn load_globs2(
+        _dir: &Path,
+        _options: builder::Options,
+        _errors: &mut Vec<Error>,
+    ) -> Option<Vec<GlobRecord>> {
+        None
+    }
+
+    /// Builds a searcher from loaded databases, failing with `errors` from loading them. Their
+    /// globs are indexed when first needed.
+    fn from_databases(
+        databases: Vec<Layer>,
+        warnings: Vec<Warning>,
+        errors: Vec<Error>,
+        origin: Origin,
+        options: builder::Options,
+    ) -> Result<Self, Error> {
+        if let Some(error) = Error::combine(errors) {
+            return Err(error);
+        }
+        let searcher = Self::assemble(databases, warnings, origin, options);
+        #[cfg(any(feature = "hierarchy", feature = "icons"))]
+        if searcher.options.index_profile == IndexProfile::Fast {
+            let type_index = index::TypeIndex::new(&searcher.databases)?;
+            return Ok(MimeSearcher {
+                type_index: Some(Arc::new(type_index)),
+                ..searcher
+            });
+        }
+        Ok(searcher)
+    }
+
+    /// A searcher without any database, for which every lookup finds nothing.
+    pub(crate) fn empty() -> Self {
+        let options = builder::Options::default();
+        Self::assemble(Vec::new(), Vec::new(), Origin::default(), options)
+    }
+
+    /// Builds a searcher from loaded databases, all but the type index of the fast
+    /// [`IndexProfile`], which is the only part that can fail.
+    fn assemble(
+        databases: Vec<Layer>,
+        warnings: Vec<Warning>,
+        origin: Origin,
+        options: builder::Options,
+    ) -> Self {
+        let sources = databases
+            .iter()
+            .filter_map(|layer| layer.source.clone())
+            .chain(
+                origin
+                    .inherited
+                    .iter()
+                    .flat_map(|p| p.sources.iter().cloned()),
+            )
+            .collect();
+        let loaded: Vec<_> = databases
+            .iter()
+            .filter_map(|layer| layer.database.clone())
+            .chain(
+                origin
+                    .inherited
+                    .iter()
+                    .flat_map(|p| p.databases.iter().cloned()),
+            )
+            .collect();
+        #[cfg(feature = "globs")]
+        let glob_index = {
+            let fast = options.index_profile == IndexProfile::Fast;
+            let mut indexed = if options.globs { databases } else { Vec::new() };
+            if fast {
+                // Index the extensions of caches too, rather than searching them on each lookup
+                for layer in &mut indexed {
+                    layer.cache = None;
+                }
+            }
+            let parent = origin.inherited.as_ref().map(|p| p.glob_index.clone());
+            let glob_index = Arc::new(LazyGlobber::new(indexed, parent));
+            if fast {
+                glob_index.get();
+            }
+            glob_index
+        };
+        MimeSearcher {
+            #[cfg(feature = "globs")]
+            glob_index,
+            databases: Arc::new(loaded),
+            sources,
+            temporary: Default::default(),
+            #[cfg(feature = "magic")]
+            probes: Default::default(),
+            #[cfg(feature = "magic")]
+            sniff_memo: None,
+            #[cfg(feature = "globs")]
+            unknown_extensions: None,
+            #[cfg(any(feature = "globs", feature = "icons"))]
+            lookup_cache: None,
+            #[cfg(any(feature = "hierarchy", feature = "icons"))]
+            type_index: None,
+            warnings: warnings.into(),
+            options,
+            names: Default::default(),
+            origin: Arc::new(origin),
+        }
+    }
+
+    /// The glob index, built now if no lookup has needed it yet.
+    #[cfg(feature = "globs")]
+    fn globber(&self) -> &Globber {
+        self.glob_index.get()
+    }
+
+    /// Problems found while loading the databases, such as a [stale](Warning::DatabaseStale)
+    /// mime.cache. These are also logged.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Registers a [`ContentProbe`] that refines the results of
+    /// [`find_mimetype_from_data`](Self::find_mimetype_from_data).
+    ///
+    /// Probes run in registration order; the first one that returns a type wins.
+    #[cfg(feature = "magic")]
+    pub fn register_probe<P: ContentProbe + 'static>(&mut self, probe: P) {
+        Arc::make_mut(&mut self.probes).push(Arc::new(probe));
+    }
+
+    /// Parses the most important database into an editable [`MimeDatabaseModel`].
+    ///
+    /// Fails with [`Error::MimeCacheNotFound`] if the searcher was built from globs2 alone.
+    pub fn to_model(&self) -> Result<MimeDatabaseModel, Error> {
+        let database = self.databases.first().ok_or(Error::MimeCacheNotFound)?;
+        database.to_model()
+    }
+
     /// Finds the icon name for a mimetype. To get the actual image you would need to use a crate like
     /// [`icon`](https://crates.io/crates/icon)
+    #[cfg(feature = "icons")]
     pub fn find_icon_for_mimetype(&self, mime_type: MimeType) -> Result<String, Error> {
-        self.mime_cache.find_icon_for_mimetype(mime_type)
+        let Some(cache) = &self.lookup_cache else {
+            return self.icon_lookup(&mime_type);
+        };
+        if let Some(icon) = cache.icons.get(&mime_type) {
+            return icon;
+        }
+        let icon = self.icon_lookup(&mime_type);
+        cache.icons.insert(mime_type, icon.clone());
+        icon
+    }
+
+    #[cfg(feature = "icons")]
+    fn icon_lookup(&self, mime_type: &MimeType) -> Result<String, Error> {
+        if !self.options.icons {
+            return Err(Error::NoIconFound);
+        }
+        if let Some(index) = &self.type_index {
+            return index.generic_icon(mime_type).ok_or(Error::NoIconFound);
+        }
+        for database in self.databases.iter() {
+            if let Some(icon) = database.generic_icon(mime_type)? {
+                return Ok(icon);
+            }
+        }
+        Err(Error::NoIconFound)
     }
 
     /// Finds the mimetype from a filepath.
     ///
     /// Looks at the content in MIME/globs2 and mime.cache.
-    /// It starts with a map of just *.xxx file extensions so that `path.extension()` can be used in
-    /// an internal hashmap.
+    /// It starts with just the *.xxx file extensions, found in the suffix tree of mime.cache and a
+    /// map of those in globs2, looking up the longest extension of the name first so that
+    /// `*.tar.gz` beats `*.gz`.
     /// If that fails, it uses a smaller `Vec` with full globbing logic.
+    /// With the `mime_guess` feature, a searcher built without any database can be told to ask
+    /// that crate's extension table instead.
     ///
+    #[cfg(feature = "globs")]
     pub fn find_mimetype_from_filepath(&self, path: &Path) -> Option<MimeType> {
-        self.globber.lookup_filename(path)
+        let cached = self
+            .lookup_cache
+            .as_ref()
+            .zip(lru::extension_key(path))
+            .map(|(cache, key)| (&cache.names, key));
+        if let Some(mime) = cached
+            .as_ref()
+            .and_then(|(names, key)| names.get(key.as_str()))
+        {
+            return Some(mime);
+        }
+        let Some((entry, matched)) = self.glob_match(path) else {
+            return self.extension_table_match(path);
+        };
+        let mime = self.intern(entry.mime);
+        // Only a match by extension holds for every name ending the same way
+        if let Some((names, key)) = cached {
+            if matched.by_extension {
+                names.insert(key, mime.clone());
+            }
+        }
+        Some(mime)
     }
-}
 
-// Header:
-// 2			CARD16		MAJOR_VERSION	1
-// 2			CARD16		MINOR_VERSION	2
-// 4			CARD32		ALIAS_LIST_OFFSET
-// 4			CARD32		PARENT_LIST_OFFSET
-// 4			CARD32		LITERAL_LIST_OFFSET
-// 4			CARD32		REVERSE_SUFFIX_TREE_OFFSET
-// 4			CARD32		GLOB_LIST_OFFSET
-// 4			CARD32		MAGIC_LIST_OFFSET
-// 4			CARD32		NAMESPACE_LIST_OFFSET
-// 4			CARD32		ICONS_LIST_OFFSET
-// 4			CARD32		GENERIC_ICONS_LIST_OFFSET
-// sum = 4*9 + 4 = 40
-impl MimeCacheHeader {
-    fn read_header(input: &[u8; 40]) -> MimeCacheHeader {
-        MimeCacheHeader {
-            major_version: u16::from_be_bytes(input[0..2].try_into().unwrap()),
-            minor_version: u16::from_be_bytes(input[2..4].try_into().unwrap()),
-            alias_list_offset: u32::from_be_bytes(input[4..8].try_into().unwrap()),
-            parent_list_offset: u32::from_be_bytes(input[8..12].try_into().unwrap()),
-            literal_list_offset: u32::from_be_bytes(input[12..16].try_into().unwrap()),
-            reverse_suffix_tree_offset: u32::from_be_bytes(input[16..20].try_into().unwrap()),
-            glob_list_offset: u32::from_be_bytes(input[20..24].try_into().unwrap()),
-            magic_list_offset: u32::from_be_bytes(input[24..28].try_into().unwrap()),
-            namespace_list_offset: u32::from_be_bytes(input[28..32].try_into().unwrap()),
-            icons_list_offset: u32::from_be_bytes(input[32..36].try_into().unwrap()),
-            generic_icons_list_offset: u32::from_be_bytes(input[36..40].try_into().unwrap()),
-        }
+    /// Like [`find_mimetype_from_filepath`](Self::find_mimetype_from_filepath), but borrows the
+    /// type from the glob index rather than returning a copy of it.
+    #[cfg(feature = "globs")]
+    pub fn find_mimetype_ref_from_filepath(&self, path: &Path) -> Option<MimeTypeRef<'_>> {
+        self.glob_match(path).map(|(entry, _)| entry.mime)
     }
-}
 
-/// Panics all the time
-fn get_u32_panics(data: &[u8], index: usize) -> u32 {
-    u32::from_be_bytes(data[index..index + 4].try_into().unwrap())
-}
+    /// The glob that [`find_mimetype_from_filepath`](Self::find_mimetype_from_filepath) goes
+    /// by for `path`, naming the pattern or literal that matched.
+    ///
+    /// Of the `*.ext` globs the one for the longest extension wins, so `a.tar.gz` matches
+    /// `*.tar.gz` rather than `*.gz`.
+    ///
+    /// ```no_run
+    /// # use shared_mime_info as smi;
+    /// let searcher = smi::MimeSearcher::new().unwrap();
+    /// let matched = searcher.find_glob_match("backup.tar.gz".as_ref()).unwrap();
+    /// assert_eq!(matched.pattern, "*.tar.gz");
+    /// assert_eq!(matched.extension(), Some("tar.gz"));
+    /// ```
+    #[cfg(feature = "globs")]
+    pub fn find_glob_match(&self, path: &Path) -> Option<GlobMatch> {
+        self.glob_match(path).map(|(entry, matched)| GlobMatch {
+            pattern: matched.pattern().into_owned(),
+            mime: self.intern(entry.mime),
+            weight: entry.weight,
+        })
+    }
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    /// Finds the mimetype from a file name that needn't exist on disk, such as the name of an
+    /// upload.
+    ///
+    /// Only the final component of `name` is matched, so a client sending `../photos/a.png`
+    /// gets the same answer as for `a.png`. Names without one, like `..`, match nothing.
+    #[cfg(feature = "globs")]
+    pub fn find_mimetype_from_filename(&self, name: &OsStr) -> Option<MimeType> {
+        let name = Path::new(name).file_name()?;
+        self.find_mimetype_from_filepath(Path::new(name))
+    }
 
-    #[test]
-    fn get_icon_for_mimetype() {
-        let cache = MimeCache::new().unwrap();
-        let start = std::time::Instant::now();
-        assert_eq!(
-            cache.find_icon_for_mimetype(MimeType("font/otf".to_string())),
-            Ok("font-x-generic".to_string().into())
-        );
-        assert_eq!(
-            cache.find_icon_for_mimetype(MimeType("text/javascript".to_string())),
-            Ok("text-x-script".to_string().into())
-        );
-        assert_eq!(
-            cache.find_icon_for_mimetype(MimeType("application/pdf".to_string())),
-            Ok("x-office-document".to_string().into())
-        );
-        assert_eq!(
-            cache.find_icon_for_mimetype(MimeType("not_a_real_mimetype1234".to_string())),
-            Err(Error::NoIconFound)
-        );
-        println!("Time to find icon: {:#?}", start.elapsed());
+    // The best glob for `path`, recording its extension if there is none
+    #[cfg(feature = "globs")]
+    fn glob_match(&self, path: &Path) -> Option<(GlobHit<'_>, Matched<'_>)> {
+        let found = self.globber().lookup_filename_entry(path);
+        let ext = path.extension().map(|ext| ext.to_string_lossy());
+        if let (None, Some(unknown), Some(ext)) = (&found, &self.unknown_extensions, ext) {
+            unknown.record(&ext);
+        }
+        found
     }
 
-    #[test]
-    fn get_mimetype_for_filename() {
-        let cache = Globber::new(&MimeCache::new().unwrap()).unwrap();
-        let start = std::time::Instant::now();
-        assert_eq!(
-            cache.lookup_filename(&std::path::PathBuf::from("foo.pdf")),
-            Some("application/pdf".to_string().into())
+    /// Starts or stops collecting the extensions of file names that no glob matches.
+    ///
+    /// Meant for telemetry: collected over a process lifetime, the list shows which types users
+    /// actually meet that the installed database lacks. Stopping discards what was collected.
+    #[cfg(feature = "globs")]
+    pub fn set_record_unknown_extensions(&mut self, record: bool) {
+        self.unknown_extensions = record.then(Default::default);
+    }
+
+    /// The extensions collected since
+    /// [`set_record_unknown_extensions`](Self::set_record_unknown_extensions) was turned on,
+    /// lowercased and sorted.
+    #[cfg(feature = "globs")]
+    pub fn unknown_extensions(&self) -> Vec<String> {
+        self.unknown_extensions
+            .as_ref()
+            .map_or_else(Vec::new, |unknown| unknown.list())
+    }
+
+    /// The number of leading bytes needed to evaluate every magic rule.
+    ///
+    /// Buffering this much of a file or stream before calling
+    /// [`find_mimetype_from_data`](Self::find_mimetype_from_data) gives the same answer as passing
+    /// the whole thing. A database whose magic can't be read doesn't count, sniffing fails
+    /// with its error instead. Always 0 without the `magic` feature.
+    pub fn max_magic_extent(&self) -> usize {
+        #[cfg(feature = "magic")]
+        if self.options.magic {
+            let extent = self
+                .databases
+                .iter()
+                .filter_map(|database| database.magic_extent().ok())
+                .max()
+                .unwrap_or(0);
+            return self
+                .options
+                .sniff_limit
+                .map_or(extent, |limit| extent.min(limit));
+        }
+        0
+    }
+
+    // Without magic no bytes are looked at, though the file still has to be readable
+    #[cfg(not(feature = "magic"))]
+    pub(crate) fn read_head(&self, path: &Path, data: &mut Vec<u8>) -> Result<(), Error> {
+        std::fs::File::open(path).map_err(|e| Error::io(path, e))?;
+        data.clear();
+        Ok(())
+    }
+
+    /// Lists every type claiming the file extension `ext` (without the leading dot), highest
+    /// weight first. Types with the same weight are sorted by name.
+    #[cfg(feature = "globs")]
+    pub fn types_for_extension(&self, ext: &str) -> Vec<WeightedMatch> {
+        self.globber()
+            .lookup_extension(ext)
+            .into_iter()
+            .map(|entry| WeightedMatch {
+                mime: self.intern(entry.mime),
+                weight: entry.weight,
+            })
+            .collect()
+    }
+
+    /// Whether several types share the highest weight for the extension `ext`.
+    ///
+    /// When this is true a file name alone cannot tell the types apart and the contents should
+    /// be sniffed with [`find_mimetype_from_data`](Self::find_mimetype_from_data).
+    #[cfg(feature = "globs")]
+    pub fn is_ambiguous_extension(&self, ext: &str) -> bool {
+        match self.globber().lookup_extension(ext)[..] {
+            [first, second, ..] => first.weight == second.weight,
+            _ => false,
+        }
+    }
+
+    /// Finds the mimetype from the contents of a file.
+    ///
+    /// Runs the magic rules in mime.cache against `data`, then gives any registered
+    /// [`ContentProbe`] whose trigger matched a chance to refine the answer, followed by the
+    /// built in [`OleProbe`].
+    #[cfg(feature = "magic")]
+    pub fn find_mimetype_from_data(&self, data: &[u8]) -> Result<Option<MimeType>, Error> {
+        Ok(self.sniff(data)?.map(|(mime, _priority)| mime))
+    }
+
+    /// The type the magic rules give `data`, borrowed from the database whose rule matched
+    /// rather than copied out of it.
+    ///
+    /// This is the match [`find_mimetype_from_data`](Self::find_mimetype_from_data) starts
+    /// from. No [`ContentProbe`] gets to refine it, not even the built in [`OleProbe`], as a
+    /// probe's answer isn't in any database, and it isn't memoised. A batch classifier that can
+    /// do without refinement gets its answers without allocating for each one.
+    #[cfg(feature = "magic")]
+    pub fn find_magic_match_ref(&self, data: &[u8]) -> Result<Option<MimeTypeRef<'_>>, Error> {
+        let mut source = &data[..data.len().min(self.max_magic_extent())];
+        Ok(self
+            .magic_lookup_ref(&mut source)?
+            .map(|(mime, _priority)| mime))
+    }
+
+    // `find_mimetype_from_data`, along with the priority of the magic rule that matched
+    #[cfg(feature = "magic")]
+    fn sniff(&self, data: &[u8]) -> Result<Option<(MimeType, u32)>, Error> {
+        let Some((matched, priority)) = self.magic_lookup(data)? else {
+            return Ok(None);
+        };
+
+        let refined = self
+            .probes_for(&matched)
+            .find_map(|probe| probe.probe(&matched, data));
+
+        Ok(Some((refined.unwrap_or(matched), priority)))
+    }
+
+    // The registered probes triggered by `matched`, then the built in ones
+    #[cfg(feature = "magic")]
+    fn probes_for<'a>(
+        &'a self,
+        matched: &'a MimeType,
+    ) -> impl Iterator<Item = &'a dyn ContentProbe> {
+        const BUILT_IN: &[&dyn ContentProbe] = &[&OleProbe];
+        let registered = self.probes.iter().map(|probe| &**probe);
+        registered
+            .chain(BUILT_IN.iter().copied())
+            .filter(move |probe| probe.triggers().contains(matched))
+    }
+
+    /// Finds the mimetype of a seekable source by fetching only the byte ranges magic rules look
+    /// at.
+    ///
+    /// Gives the same answer as [`find_mimetype_from_data`](Self::find_mimetype_from_data) on the
+    /// whole source. The first [`max_magic_extent`](Self::max_magic_extent) bytes are only
+    /// fetched in one go when a [`ContentProbe`] wants to refine the match. Results
+    /// are not memoised, as that would need the whole prefix too.
+    #[cfg(feature = "magic")]
+    pub fn find_mimetype_from_reader(
+        &self,
+        reader: &mut dyn RangeReader,
+    ) -> Result<Option<MimeType>, Error> {
+        let limit = self.max_magic_extent();
+        let Some((matched, _priority)) =
+            self.magic_lookup_in(&mut range::Fetcher::new(reader, limit))?
+        else {
+            return Ok(None);
+        };
+
+        let probes: Vec<_> = self.probes_for(&matched).collect();
+        if probes.is_empty() {
+            return Ok(Some(matched));
+        }
+        let data = reader.read_at(0, limit)?;
+        let refined = probes
+            .into_iter()
+            .find_map(|probe| probe.probe(&matched, &data));
+
+        Ok(Some(refined.unwrap_or(matched)))
+    }
+
+    /// Remembers the magic results for up to `capacity` distinct sniff buffers.
+    ///
+    /// Results are keyed by a hash of the first [`max_magic_extent`](Self::max_magic_extent)
+    /// bytes, so tools that see many identical files skip repeated matching. Registered probes
+    /// still run on every call. A capacity of 0 turns memoisation off.
+    #[cfg(feature = "magic")]
+    pub fn set_sniff_memo_capacity(&mut self, capacity: usize) {
+        self.sniff_memo = (capacity > 0).then(|| Arc::new(memo::SniffMemo::new(capacity)));
+    }
+
+    /// Remembers up to `capacity` of the most recently used file name results, by extension,
+    /// and as many icon results, by type.
+    ///
+    /// Thumbnailers and file managers ask about the same handful of types for every file in a
+    /// directory, and this answers repeats without searching the databases again. Names are
+    /// remembered by their lowercased extension, everything from the first dot on, and only
+    /// when a `*.ext` glob matched them, so `a.tar.gz` answers `B.TAR.GZ` but names matching a
+    /// literal or wildcard glob are looked up every time. Registering or revoking a
+    /// [temporary type](Self::register_temporary_type) forgets everything remembered. A
+    /// capacity of 0 turns the cache off.
+    #[cfg(any(feature = "globs", feature = "icons"))]
+    pub fn set_lookup_cache_capacity(&mut self, capacity: usize) {
+        self.lookup_cache = (capacity > 0).then(|| Arc::new(lru::LookupCache::new(capacity)));
+    }
+
+    // The highest priority match over all databases, earlier ones winning ties. Rules for a type
+    // that a more important database also has rules for are ignored, so overriding a type's magic
+    // replaces it, and a __NOMAGIC__ rule deletes it.
+    #[cfg(feature = "magic")]
+    fn magic_lookup(&self, data: &[u8]) -> Result<Option<(MimeType, u32)>, Error> {
+        let prefix = &data[..data.len().min(self.max_magic_extent())];
+        let memoised = self
+            .sniff_memo
+            .as_ref()
+            .map(|memo| (memo, memo::SniffMemo::key(prefix)));
+        if let Some(result) = memoised.and_then(|(memo, key)| memo.get(key)) {
+            return Ok(result);
+        }
+
+        let mut source = prefix;
+        let best = self.magic_lookup_in(&mut source)?;
+        if let Some((memo, key)) = memoised {
+            memo.insert(key, best.clone());
+        }
+        Ok(best)
+    }
+
+    #[cfg(feature = "magic")]
+    fn magic_lookup_in(
+        &self,
+        data: &mut dyn MagicSource,
+    ) -> Result<Option<(MimeType, u32)>, Error> {
+        let best = self.magic_lookup_ref(data)?;
+        Ok(best.map(|(mime, priority)| (self.intern(mime), priority)))
+    }
+
+    // `magic_lookup_in`, borrowing the type from the database that matched
+    #[cfg(feature = "magic")]
+    fn magic_lookup_ref(
+        &self,
+        data: &mut dyn MagicSource,
+    ) -> Result<Option<(MimeTypeRef<'_>, u32)>, Error> {
+        if !self.options.magic {
+            return Ok(None);
+        }
+        let mut best: Option<(MimeTypeRef<'_>, u32)> = None;
+        for (i, database) in self.databases.iter().enumerate() {
+            let overridden = |mime: &str| {
+                for earlier in &self.databases[..i] {
+                    if earlier.has_magic_for(mime)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            };
+            let found = database.magic_lookup(data, &overridden)?;
+            if let Some((mime, priority)) = found {
+                if best.as_ref().is_none_or(|(_, best)| priority > *best) {
+                    best = Some((mime, priority));
+                }
+            }
+        }
+        Ok(best)
+    }
+}
+
+// The tests exercise every lookup, so they need the default features
+#[cfg(all(
+    test,
+    feature = "globs",
+    feature = "magic",
+    feature = "icons",
+    feature = "hierarchy"
+))]
+mod test {
+    use std::hash::{Hash, Hasher};
+
+    use super::*;
+
+    fn mime(name: &str) -> MimeType {
+        name.parse().unwrap()
+    }
+
+    fn system_dir() -> PathBuf {
+        dirs::mime_dirs()
+            .into_iter()
+            .find(|dir| dir.join("mime.cache").exists())
+            .unwrap()
+    }
+
+    fn system_cache() -> MimeCache {
+        MimeCache::load(&system_dir()).unwrap()
+    }
+
+    /// A fresh directory for one test, removed along with its contents when dropped.
+    #[derive(Debug)]
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> TempDir {
+            // Tests run in parallel, and other runs may be going on at the same time
+            static NEXT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+            let n = NEXT.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            let dir = std::env::temp_dir().join(format!("smi-test-{}-{n}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+    }
+
+    impl std::ops::Deref for TempDir {
+        type Target = PathBuf;
+
+        fn deref(&self) -> &PathBuf {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for TempDir {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn get_icon_for_mimetype() {
+        let cache = system_cache();
+        let start = std::time::Instant::now();
+        assert_eq!(
+            cache.find_icon_for_mimetype(mime("font/otf")),
+            Ok("font-x-generic".to_string())
+        );
+        assert_eq!(
+            cache.find_icon_for_mimetype(mime("text/javascript")),
+            Ok("text-x-script".to_string())
+        );
+        assert_eq!(
+            cache.find_icon_for_mimetype(mime("application/pdf")),
+            Ok("x-office-document".to_string())
+        );
+        assert_eq!(
+            cache.find_icon_for_mimetype(mime("application/x-not-a-real-type")),
+            Err(Error::NoIconFound)
+        );
+        println!("Time to find icon: {:#?}", start.elapsed());
+    }
+
+    #[test]
+    fn mime_type_parts() {
+        let pdf = mime("application/pdf");
+        assert_eq!(pdf, MimeType::APPLICATION_PDF);
+        assert_eq!(MimeType::IMAGE_SVG_XML.subtype(), "svg+xml");
+        assert!(MimeType::new("application/vnd.ms-excel.sheet.macroEnabled.12").is_ok());
+        assert_eq!(pdf.media(), "application");
+        assert_eq!(pdf.subtype(), "pdf");
+        assert_eq!(pdf.essence_str(), "application/pdf");
+        assert_eq!(pdf.to_string(), "application/pdf");
+
+        let icons = HashMap::from([(pdf, "x-office-document")]);
+        assert_eq!(icons.get("application/pdf"), Some(&"x-office-document"));
+
+        for invalid in [
+            "",
+            "pdf",
+            "/pdf",
+            "application/",
+            "a/b/c",
+            "text/html; charset=utf-8",
+            "text/-dash",
+            "text/é",
+            &format!("text/{}", "x".repeat(128)),
+        ] {
+            assert_eq!(
+                MimeType::new(invalid),
+                Err(InvalidMimeType(invalid.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn wildcard_matching() {
+        let svg = MimeType::IMAGE_SVG_XML;
+        assert!(svg.matches("image/svg+xml"));
+        assert!(svg.matches("Image/*"));
+        assert!(svg.matches("*/*"));
+        assert!(!svg.matches("text/*"));
+        assert!(!svg.matches("image/png"));
+
+        let searcher = MimeSearcher::new().unwrap();
+        let images = searcher.list_types_matching("image/*").unwrap();
+        assert!(images.contains(&MimeType::IMAGE_PNG));
+        assert!(images.iter().all(|mime| mime.media() == "image"));
+        assert!(images.is_sorted());
+    }
+
+    #[test]
+    fn stable_ids() {
+        let searcher = MimeSearcher::new().unwrap();
+        // FNV-1a of "application/pdf"
+        assert_eq!(
+            searcher.stable_id(&MimeType::APPLICATION_PDF),
+            0x4664_2326_38e1_a384
+        );
+        assert_eq!(
+            searcher.type_for_stable_id(searcher.stable_id(&MimeType::IMAGE_PNG)),
+            Ok(Some(MimeType::IMAGE_PNG))
+        );
+        assert_eq!(searcher.type_for_stable_id(0), Ok(None));
+    }
+
+    #[test]
+    fn content_type_headers() {
+        let html = MimeType::parse_content_type("Text/HTML; Charset=utf-8").unwrap();
+        assert_eq!(html.mime(), &mime("text/html"));
+        assert_eq!(html.param("charset"), Some("utf-8"));
+        assert_eq!(html.to_header_value(), "text/html; charset=utf-8");
+        assert_eq!(
+            html,
+            ContentType::new(mime("text/html")).with_param("CHARSET", "utf-8")
+        );
+
+        let multipart =
+            MimeType::parse_content_type(r#"multipart/form-data; boundary="a b\"c"; x=1"#).unwrap();
+        assert_eq!(multipart.param("boundary"), Some(r#"a b"c"#));
+        assert_eq!(
+            MimeType::parse_content_type(&multipart.to_header_value()),
+            Ok(multipart)
+        );
+
+        for invalid in [
+            "text/html; charset",
+            "text/html; a=\"b",
+            "text/html; a=b c=d",
+        ] {
+            assert!(MimeType::parse_content_type(invalid).is_err());
+        }
+    }
+
+    impl Globber {
+        fn lookup_filename(&self, name: &std::path::Path) -> Option<&str> {
+            self.lookup_filename_entry(name)
+                .map(|(entry, _)| entry.mime.essence_str())
+        }
+    }
+
+    #[test]
+    fn get_mimetype_for_filename() {
+        let searcher = MimeSearcher::new().unwrap();
+        let cache = searcher.globber();
+        let start = std::time::Instant::now();
+        assert_eq!(
+            cache.lookup_filename(&std::path::PathBuf::from("foo.pdf")),
+            Some("application/pdf")
         );
         assert_eq!(
             cache.lookup_filename(&std::path::PathBuf::from("bar.srt")),
-            Some("application/x-subrip".to_string().into())
+            Some("application/x-subrip")
         );
         assert_eq!(
             cache.lookup_filename(&std::path::PathBuf::from("baz.md")),
-            Some("text/markdown".to_string().into())
+            Some("text/markdown")
         );
         assert_eq!(
             cache.lookup_filename(&std::path::PathBuf::from("321.vdr")),
-            Some("video/mpeg".to_string().into())
+            Some("video/mpeg")
         );
         println!("Time to find mimetype: {:#?}", start.elapsed());
     }
+
+    #[test]
+    fn filename_lookup_ignores_directories() {
+        let searcher = MimeSearcher::with_dirs(&[system_dir()]).unwrap();
+        for name in ["report.pdf", "../uploads/report.pdf", "C.d/report.PDF"] {
+            assert_eq!(
+                searcher.find_mimetype_from_filename(OsStr::new(name)),
+                Some(mime("application/pdf")),
+                "{name}"
+            );
+        }
+        assert_eq!(
+            searcher.find_mimetype_from_filename(OsStr::new("a.pdf/..")),
+            None
+        );
+        assert_eq!(searcher.find_mimetype_from_filename(OsStr::new("")), None);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn non_utf8_names_still_match() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let searcher = MimeSearcher::with_dirs(&[system_dir()]).unwrap();
+        let latin1 = OsStr::from_bytes(b"r\xe9sum\xe9.pdf");
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new(latin1)),
+            Some(mime("application/pdf"))
+        );
+        assert_eq!(
+            searcher.find_mimetype_from_filename(latin1),
+            Some(mime("application/pdf"))
+        );
+        // A non-UTF-8 extension matches nothing rather than stopping the lookup
+        let ext = OsStr::from_bytes(b"notes.\xe9");
+        assert_eq!(searcher.find_mimetype_from_filename(ext), None);
+
+        // Not even a pattern holding the character that stands in for invalid bytes
+        let replaced = MimeSearcher::builder()
+            .data_dirs(std::iter::empty::<PathBuf>())
+            .add_database(MimeDatabaseModel {
+                suffixes: vec![GlobRecord {
+                    pattern: "*.\u{FFFD}".to_string(),
+                    mime: mime("text/x-replaced"),
+                    weight: 50,
+                    case_sensitive: false,
+                }],
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+        assert_eq!(replaced.find_mimetype_from_filename(ext), None);
+        assert_eq!(
+            replaced.find_mimetype_from_filename(OsStr::new("notes.\u{FFFD}")),
+            Some(mime("text/x-replaced"))
+        );
+    }
+
+    #[test]
+    fn ambiguous_extensions() {
+        let searcher = MimeSearcher::new().unwrap();
+
+        let ts = searcher.types_for_extension("ts");
+        assert!(ts.len() > 1);
+        assert!(
+            ts.windows(2)
+                .all(|w| (w[1].weight, &w[0].mime) < (w[0].weight, &w[1].mime))
+        );
+        assert!(ts.iter().any(|m| m.mime.essence_str() == "video/mp2t"));
+        assert!(searcher.is_ambiguous_extension("ts"));
+
+        assert_eq!(
+            searcher.types_for_extension("PDF"),
+            vec![WeightedMatch {
+                mime: mime("application/pdf"),
+                weight: 50
+            }]
+        );
+        assert!(!searcher.is_ambiguous_extension("pdf"));
+        assert!(searcher.types_for_extension("not-an-extension").is_empty());
+    }
+
+    #[test]
+    fn language_hints() {
+        let searcher = MimeSearcher::new().unwrap();
+        let hint = |name: &str| searcher.language_hint_for(&mime(name));
+
+        assert_eq!(hint("text/x-python"), Some("python"));
+        // Subclass of text/x-python
+        assert_eq!(hint("text/x-python3"), Some("python"));
+        // Alias of application/x-shellscript
+        assert_eq!(hint("text/x-sh"), Some("shellscript"));
+        assert_eq!(hint("application/json"), Some("json"));
+        assert_eq!(hint("text/plain"), None);
+        assert_eq!(hint("image/png"), None);
+    }
+
+    #[cfg(feature = "syntax_names")]
+    #[test]
+    fn syntax_names() {
+        let searcher = MimeSearcher::new().unwrap();
+        let python = mime("text/x-python3");
+
+        assert_eq!(searcher.syntect_syntax_for(&python), Some("Python"));
+        assert_eq!(searcher.tree_sitter_language_for(&python), Some("python"));
+        assert_eq!(searcher.syntect_syntax_for(&mime("image/png")), None);
+    }
+
+    #[test]
+    fn get_mimetype_for_data() {
+        let searcher = MimeSearcher::new().unwrap();
+        assert_eq!(
+            searcher.find_mimetype_from_data(b"%PDF-1.7\n"),
+            Ok(Some(mime("application/pdf")))
+        );
+        assert_eq!(
+            searcher.find_mimetype_from_data(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Ok(Some(mime("image/png")))
+        );
+        assert_eq!(searcher.find_mimetype_from_data(b""), Ok(None));
+    }
+
+    #[test]
+    fn max_magic_extent_covers_rules() {
+        let searcher = MimeSearcher::new().unwrap();
+        let model = searcher.to_model().unwrap();
+
+        fn extent(matchlets: &[Matchlet]) -> usize {
+            matchlets
+                .iter()
+                .map(|m| {
+                    let own = (m.range_start + m.range_length) as usize + m.value.len();
+                    own.max(extent(&m.children))
+                })
+                .max()
+                .unwrap_or(0)
+        }
+        let computed = model.magic.iter().map(|m| extent(&m.matchlets)).max();
+
+        assert!(searcher.max_magic_extent() >= computed.unwrap());
+    }
+
+    #[test]
+    fn magic_extent_saturates_at_the_end_of_the_range() {
+        let model = MimeDatabaseModel {
+            magic: vec![MagicMatch {
+                priority: 50,
+                mime: mime("application/x-far"),
+                matchlets: vec![Matchlet {
+                    range_start: u32::MAX - 2,
+                    range_length: 1,
+                    word_size: 1,
+                    value: b"AB".to_vec(),
+                    mask: None,
+                    children: Vec::new(),
+                }],
+            }],
+            ..Default::default()
+        };
+        assert_eq!(model.magic_extent(), Ok(u32::MAX as usize));
+        let cache = MimeCache::from_storage(model.to_cache_bytes()).unwrap();
+        assert_eq!(cache.magic_extent(), Ok(u32::MAX as usize));
+    }
+
+    #[test]
+    fn records_unknown_extensions() {
+        let mut searcher = MimeSearcher::new().unwrap();
+        searcher.find_mimetype_from_filepath(Path::new("a.smi-unknown"));
+        assert_eq!(searcher.unknown_extensions(), Vec::<String>::new());
+
+        searcher.set_record_unknown_extensions(true);
+        for name in ["b.SMI-UNKNOWN", "c.smi-unknown", "d.pdf", "Makefile-smi"] {
+            searcher.find_mimetype_from_filepath(Path::new(name));
+        }
+        assert_eq!(searcher.unknown_extensions(), ["smi-unknown"]);
+    }
+
+    #[test]
+    fn sniff_memo_gives_same_results() {
+        let mut searcher = MimeSearcher::new().unwrap();
+        searcher.set_sniff_memo_capacity(1);
+
+        for _ in 0..2 {
+            assert_eq!(
+                searcher.find_mimetype_from_data(b"%PDF-1.7\n"),
+                Ok(Some(mime("application/pdf")))
+            );
+            assert_eq!(
+                searcher.find_mimetype_from_data(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+                Ok(Some(mime("image/png")))
+            );
+        }
+        assert_eq!(searcher.find_mimetype_from_data(b""), Ok(None));
+    }
+
+    #[test]
+    fn guess_combines_name_and_content() {
+        let searcher = MimeSearcher::new().unwrap();
+        let guess = |name: &str, data: &[u8]| {
+            let guess = searcher.guess(Path::new(name), data).unwrap();
+            (guess.mime.to_string(), guess.method)
+        };
+
+        use DetectionMethod::*;
+        assert_eq!(guess("report.pdf", b""), ("application/pdf".into(), Glob));
+        assert_eq!(
+            guess("report", b"%PDF-1.7\n"),
+            ("application/pdf".into(), Magic)
+        );
+        assert_eq!(
+            guess("notes", b"just some words"),
+            ("text/plain".into(), Fallback)
+        );
+        assert_eq!(
+            guess("blob", b"\0\x01\x02"),
+            ("application/octet-stream".into(), Fallback)
+        );
+    }
+
+    #[test]
+    fn guess_certainty() {
+        let mut searcher = MimeSearcher::new().unwrap();
+        let certain = |searcher: &MimeSearcher, name: &str, data: &[u8]| {
+            searcher.guess(Path::new(name), data).unwrap().certain
+        };
+
+        assert!(certain(&searcher, "report.pdf", b""));
+        assert!(certain(&searcher, "report", b"%PDF-1.7\n"));
+        assert!(!certain(&searcher, "notes", b"just some words"));
+        // Several types share the weight of *.ts, and the contents don't pick one
+        assert!(!certain(&searcher, "clip.ts", b"just some words"));
+
+        searcher.set_conflict_policy(ConflictPolicy::MagicFirst);
+        assert!(certain(&searcher, "report.pdf", b"%PDF-1.7\n"));
+        assert!(!certain(&searcher, "photo.png", b"%PDF-1.7\n"));
+    }
+
+    #[test]
+    fn explain_traces_guess() {
+        let mut searcher = MimeSearcher::with_dirs(&[system_dir()]).unwrap();
+        let pdf_glob = Step::GlobMatched {
+            pattern: "*.pdf".to_string(),
+            mime: mime("application/pdf"),
+            weight: 50,
+            source: Some(system_dir().join("mime.cache")),
+        };
+
+        let explained = searcher
+            .explain_data(Path::new("report.PDF"), b"%PDF-1.7\n")
+            .unwrap();
+        let pdf_magic = Step::MagicMatched {
+            mime: mime("application/pdf"),
+            priority: 50,
+        };
+        assert_eq!(explained.steps, [pdf_glob, pdf_magic]);
+        assert_eq!(explained.guess.mime, mime("application/pdf"));
+
+        let explained = searcher.explain_data(Path::new("notes"), b"words").unwrap();
+        assert_eq!(
+            explained.steps,
+            [
+                Step::NoGlobMatched,
+                Step::NoMagicMatched,
+                Step::Fallback { text: true }
+            ]
+        );
+        assert_eq!(
+            explained.to_string(),
+            "1. no glob matched the name\n2. no magic rule matched the contents\n\
+             3. nothing matched, contents look like text\n=> text/plain (by Fallback, uncertain)"
+        );
+
+        searcher.set_conflict_policy(ConflictPolicy::MagicFirst);
+        let explained = searcher
+            .explain_data(Path::new("photo.png"), b"%PDF-1.7\n")
+            .unwrap();
+        assert!(matches!(
+            &explained.steps[..],
+            [
+                Step::GlobMatched { .. },
+                Step::MagicMatched { .. },
+                Step::ConflictResolved {
+                    policy: ConflictPolicy::MagicFirst,
+                    winner: DetectionMethod::Magic
+                }
+            ]
+        ));
+
+        let missing = searcher.explain(Path::new("/nonexistent/report.pdf"));
+        assert!(missing.unwrap_err().is_not_found());
+    }
+
+    #[test]
+    fn text_heuristics_refine_plain_text() {
+        let builder = || MimeSearcher::builder().data_dirs([system_dir()]);
+        let searcher = builder().with_text_heuristics(true).build().unwrap();
+        let guess = |name: &str, data: &[u8]| searcher.guess(Path::new(name), data).unwrap();
+
+        let script = guess("build", b"#!/usr/bin/env -S python3 -u\nprint('hi')\n");
+        assert_eq!(script.mime, mime("text/x-python3"));
+        assert_eq!(script.method, DetectionMethod::Heuristic);
+        assert!(!script.certain);
+        assert_eq!(
+            guess("configure", b"echo hi\n# vim: set ft=sh:\n").mime,
+            mime("application/x-shellscript")
+        );
+        assert_eq!(
+            guess("Rakefile2", b"# -*- mode: ruby -*-\ntask :default\n").mime,
+            mime("application/x-ruby")
+        );
+        assert_eq!(guess("notes", b"words").method, DetectionMethod::Fallback);
+        assert_eq!(
+            guess("notes.txt", b"# vim: set ft=sh:\n").mime,
+            mime("text/plain")
+        );
+
+        let explained = searcher
+            .explain_data(Path::new("configure"), b"# vim: set ft=sh:\n")
+            .unwrap();
+        assert_eq!(
+            explained.steps.last().unwrap().to_string(),
+            "heuristically application/x-shellscript, as the mode line names `sh`"
+        );
+
+        let plain = builder().build().unwrap();
+        let script = plain
+            .guess(Path::new("configure"), b"# vim: set ft=sh:\n")
+            .unwrap();
+        assert_eq!(script.method, DetectionMethod::Fallback);
+    }
+
+    #[test]
+    fn default_policy_keeps_overruled_magic() {
+        let searcher = MimeSearcher::new().unwrap();
+        let guess = searcher
+            .guess(Path::new("photo.png"), b"%PDF-1.7\n")
+            .unwrap();
+        assert_eq!(guess.mime, mime("image/png"));
+        assert_eq!(guess.method, DetectionMethod::Glob);
+        assert_eq!(
+            guess
+                .overruled
+                .map(|evidence| (evidence.mime, evidence.method)),
+            Some((mime("application/pdf"), DetectionMethod::Magic))
+        );
+
+        let agreed = searcher
+            .guess(Path::new("report.pdf"), b"%PDF-1.7\n")
+            .unwrap();
+        assert_eq!(agreed.overruled, None);
+        assert!(agreed.certain);
+
+        // A .docx is a zip, so its contents confirm its name rather than contradict it
+        let docx = mime("application/vnd.openxmlformats-officedocument.wordprocessingml.document");
+        let refined = searcher
+            .guess(Path::new("letter.docx"), b"PK\x03\x04\x14\0\0\0")
+            .unwrap();
+        assert_eq!(
+            (refined.mime, refined.method),
+            (docx, DetectionMethod::Glob)
+        );
+        assert_eq!(refined.overruled, None);
+        assert!(refined.certain);
+    }
+
+    #[test]
+    fn conflict_policies() {
+        let mut searcher = MimeSearcher::new().unwrap();
+        let mut guess = |policy| {
+            searcher.set_conflict_policy(policy);
+            searcher
+                .guess(Path::new("photo.png"), b"%PDF-1.7\n")
+                .unwrap()
+        };
+
+        let glob_first = guess(ConflictPolicy::GlobFirst);
+        assert_eq!(glob_first.mime, mime("image/png"));
+        let overruled = glob_first.overruled.unwrap();
+        assert_eq!(
+            (overruled.mime, overruled.method),
+            (mime("application/pdf"), DetectionMethod::Magic)
+        );
+        assert!(!glob_first.certain);
+
+        let magic_first = guess(ConflictPolicy::MagicFirst);
+        assert_eq!(magic_first.mime, mime("application/pdf"));
+        assert_eq!(
+            magic_first.overruled,
+            Some(Evidence::new(mime("image/png"), DetectionMethod::Glob, 50))
+        );
+
+        let weak_magic = guess(ConflictPolicy::MagicAbovePriority(100));
+        assert_eq!(weak_magic.method, DetectionMethod::Glob);
+        let overruled = weak_magic.overruled.unwrap();
+        assert_eq!(
+            (overruled.mime, overruled.method),
+            (mime("application/pdf"), DetectionMethod::Magic)
+        );
+
+        let weak_glob = guess(ConflictPolicy::GlobAboveWeight(50));
+        assert_eq!(weak_glob.mime, mime("application/pdf"));
+
+        // No policy turns a .docx into the zip it is
+        let docx = mime("application/vnd.openxmlformats-officedocument.wordprocessingml.document");
+        for policy in [
+            ConflictPolicy::MagicFirst,
+            ConflictPolicy::MagicAbovePriority(0),
+            ConflictPolicy::GlobAboveWeight(100),
+        ] {
+            searcher.set_conflict_policy(policy);
+            let guess = searcher
+                .guess(Path::new("letter.docx"), b"PK\x03\x04\x14\0\0\0")
+                .unwrap();
+            assert_eq!(guess.mime, docx, "{policy:?}");
+            assert!(guess.certain, "{policy:?}");
+        }
+    }
+
+    #[test]
+    fn classifier_consumes_chunks() {
+        let searcher = MimeSearcher::new().unwrap();
+
+        let mut classifier = Classifier::new(&searcher);
+        assert!(matches!(
+            classifier.feed(b"%PDF-"),
+            Ok(ClassifierEvent::NeedMore(_))
+        ));
+        assert!(matches!(
+            classifier.feed(b"1.7\n"),
+            Ok(ClassifierEvent::NeedMore(_))
+        ));
+        assert_eq!(classifier.buffered(), b"%PDF-1.7\n");
+        assert_eq!(classifier.finish().unwrap().mime, mime("application/pdf"));
+
+        let mut classifier = Classifier::new(&searcher).with_name("report.pdf");
+        assert_eq!(
+            classifier.feed(b""),
+            Ok(ClassifierEvent::Classified(Guess::new(
+                mime("application/pdf"),
+                DetectionMethod::Glob
+            )))
+        );
+    }
+
+    #[test]
+    fn sniffs_readers() {
+        let searcher = MimeSearcher::new().unwrap();
+        let mut body = b"%PDF-1.7\n".to_vec();
+        body.resize(searcher.max_magic_extent() + 100, b' ');
+
+        // Only what the magic rules look at is taken from the stream
+        let mut rest = &body[..];
+        let guess = searcher.sniff_reader(&mut rest).unwrap();
+        assert_eq!(guess.mime, mime("application/pdf"));
+        assert_eq!(rest.len(), 100);
+        assert_eq!(
+            searcher.sniff_reader(&b"%PDF-1.7\n"[..]).unwrap().mime,
+            mime("application/pdf")
+        );
+
+        #[cfg(feature = "futures")]
+        {
+            use std::{
+                future::Future,
+                pin::pin,
+                task::{Context, Poll, Waker},
+            };
+
+            let mut rest = &body[..];
+            let guess = {
+                let mut sniffing = pin!(searcher.sniff_futures_reader(&mut rest));
+                // Reading a slice never waits
+                match sniffing
+                    .as_mut()
+                    .poll(&mut Context::from_waker(Waker::noop()))
+                {
+                    Poll::Ready(guess) => guess,
+                    Poll::Pending => panic!("reading a slice waited"),
+                }
+            };
+            assert_eq!(guess.unwrap().mime, mime("application/pdf"));
+            assert_eq!(rest.len(), 100);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "tokio")]
+    fn sniffs_asynchronously() {
+        let searcher = MimeSearcher::new().unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let dir = TempDir::new();
+        std::fs::write(dir.join("report"), b"%PDF-1.7\n").unwrap();
+
+        runtime.block_on(async {
+            let report = searcher.sniff_file_async(dir.join("report")).await;
+            assert_eq!(report.unwrap().mime, mime("application/pdf"));
+            // A name that settles the type is enough, the file is never opened
+            let by_name = searcher.sniff_file_async(dir.join("missing.pdf")).await;
+            assert_eq!(by_name.unwrap().mime, mime("application/pdf"));
+            let missing = searcher.sniff_file_async(dir.join("missing")).await;
+            assert!(matches!(missing, Err(Error::Io(_))));
+
+            // Only what the magic rules look at is taken from a stream
+            let mut body = b"%PDF-1.7\n".to_vec();
+            body.resize(searcher.max_magic_extent() + 100, b' ');
+            let mut rest = &body[..];
+            let guess = searcher.sniff_async_reader(&mut rest).await.unwrap();
+            assert_eq!(guess.mime, mime("application/pdf"));
+            assert_eq!(rest.len(), 100);
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "scanner")]
+    fn scanner_calls_content_hooks() {
+        let dir = TempDir::new();
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::write(dir.join("a.pdf"), b"%PDF-1.7\n").unwrap();
+        std::fs::write(dir.join("nested/b.txt"), b"hello").unwrap();
+
+        let searcher = MimeSearcher::new().unwrap();
+        let seen = std::sync::Mutex::new(Vec::new());
+        let entries = Scanner::new(&searcher)
+            .on_content(&["application/*"], |path, guess, data| {
+                seen.lock()
+                    .unwrap()
+                    .push((path.to_path_buf(), guess.mime.clone(), data.to_vec()));
+            })
+            .scan(&dir);
+
+        let found: Vec<_> = entries
+            .iter()
+            .map(|e| (e.path.clone(), e.result.clone().unwrap().mime.to_string()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (dir.join("a.pdf"), "application/pdf".to_string()),
+                (dir.join("nested/b.txt"), "text/plain".to_string()),
+            ]
+        );
+        assert_eq!(
+            seen.into_inner().unwrap(),
+            vec![(
+                dir.join("a.pdf"),
+                mime("application/pdf"),
+                b"%PDF-1.7\n".to_vec()
+            )]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "scanner")]
+    fn scanner_filters_types() {
+        let dir = TempDir::new();
+        std::fs::write(dir.join("a.png"), b"").unwrap();
+        std::fs::write(dir.join("b.svg"), b"").unwrap();
+        std::fs::write(dir.join("c.pdf"), b"").unwrap();
+
+        let searcher = MimeSearcher::new().unwrap();
+        let scan = |patterns: &[&str]| {
+            Scanner::new(&searcher)
+                .filter(patterns)
+                .scan(&dir)
+                .into_iter()
+                .map(|e| e.path.file_name().unwrap().to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+        let images = scan(&["image/*", "!image/svg+xml"]);
+        let not_pdf = scan(&["!application/pdf"]);
+
+        assert_eq!(images, ["a.png"]);
+        assert_eq!(not_pdf, ["a.png", "b.svg"]);
+    }
+
+    #[test]
+    fn magic_from_range_reader() {
+        struct Counting<'a>(&'a [u8], Vec<(u64, usize)>);
+        impl RangeReader for Counting<'_> {
+            fn read_at(&mut self, offset: u64, len: usize) -> std::io::Result<Vec<u8>> {
+                self.1.push((offset, len));
+                let rest = self.0.get(offset as usize..).unwrap_or_default();
+                Ok(rest[..len.min(rest.len())].to_vec())
+            }
+        }
+
+        let searcher = MimeSearcher::new().unwrap();
+        let mut pdf = b"%PDF-1.7\n".to_vec();
+        pdf.resize(1 << 20, b' ');
+        let mut reader = Counting(&pdf, Vec::new());
+        assert_eq!(
+            searcher.find_mimetype_from_reader(&mut reader),
+            searcher.find_mimetype_from_data(&pdf)
+        );
+        let fetched: usize = reader.1.iter().map(|(_, len)| len).sum();
+        assert!(
+            reader
+                .1
+                .iter()
+                .all(|(offset, len)| { *offset as usize + len <= searcher.max_magic_extent() })
+        );
+        assert!(fetched < pdf.len());
+
+        let mut png = std::io::Cursor::new(b"\x89PNG\r\n\x1a\n".to_vec());
+        assert_eq!(
+            searcher.find_mimetype_from_reader(&mut png),
+            Ok(Some(mime("image/png")))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[cfg(feature = "scanner")]
+    fn scanner_survives_symlink_cycles() {
+        let dir = TempDir::new();
+        std::fs::create_dir_all(dir.join("a/b")).unwrap();
+        std::fs::write(dir.join("top.txt"), b"").unwrap();
+        std::fs::write(dir.join("a/b/deep.txt"), b"").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("a/b/loop")).unwrap();
+
+        let searcher = MimeSearcher::new().unwrap();
+        let scan = |scanner: Scanner| {
+            scanner
+                .scan(&dir)
+                .into_iter()
+                .map(|e| e.path.strip_prefix(&dir).unwrap().to_path_buf())
+                .collect::<Vec<_>>()
+        };
+        let followed = scan(Scanner::new(&searcher).follow_symlinks(true));
+        let shallow = scan(Scanner::new(&searcher).follow_symlinks(true).max_depth(1));
+        let same_fs = scan(Scanner::new(&searcher).same_file_system(true));
+
+        let all = [PathBuf::from("a/b/deep.txt"), PathBuf::from("top.txt")];
+        assert_eq!(followed, all);
+        assert_eq!(shallow, [PathBuf::from("top.txt")]);
+        assert_eq!(same_fs, all);
+    }
+
+    #[test]
+    fn probe_refines_magic_match() {
+        struct PdfA(Vec<MimeType>);
+
+        impl ContentProbe for PdfA {
+            fn triggers(&self) -> &[MimeType] {
+                &self.0
+            }
+
+            fn probe(&self, _matched: &MimeType, data: &[u8]) -> Option<MimeType> {
+                data.windows(4)
+                    .any(|w| w == b"pdfa")
+                    .then(|| mime("application/x-pdfa"))
+            }
+        }
+
+        let mut searcher = MimeSearcher::new().unwrap();
+        searcher.register_probe(PdfA(vec![mime("application/pdf")]));
+
+        assert_eq!(
+            searcher.find_mimetype_from_data(b"%PDF-1.7\n% pdfa"),
+            Ok(Some(mime("application/x-pdfa")))
+        );
+        assert_eq!(
+            searcher.find_mimetype_from_data(b"%PDF-1.7\n"),
+            Ok(Some(mime("application/pdf")))
+        );
+    }
+
+    #[test]
+    fn ole_containers_are_told_apart() {
+        // A header and one directory sector holding the root and a stream per name
+        let compound_file = |clsid: [u8; 16], streams: &[&str]| {
+            let mut data = vec![0; 1024];
+            data[..8].copy_from_slice(&[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1]);
+            data[0x1E..0x20].copy_from_slice(&9u16.to_le_bytes());
+            data[0x4C..512].fill(0xFF);
+            for (i, name) in ["Root Entry"].iter().chain(streams).enumerate() {
+                let entry = &mut data[512 + i * 128..][..128];
+                let name: Vec<u8> = name.encode_utf16().flat_map(u16::to_le_bytes).collect();
+                entry[..name.len()].copy_from_slice(&name);
+                entry[0x40..0x42].copy_from_slice(&(name.len() as u16 + 2).to_le_bytes());
+                entry[0x42] = if i == 0 { 5 } else { 2 };
+            }
+            data[512 + 0x50..][..16].copy_from_slice(&clsid);
+            data
+        };
+        let searcher = MimeSearcher::with_dirs(&[system_dir()]).unwrap();
+        let word = [
+            0x06, 0x09, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x46,
+        ];
+
+        assert_eq!(
+            searcher.find_mimetype_from_data(&compound_file(word, &["1Table"])),
+            Ok(Some(mime("application/msword")))
+        );
+        assert_eq!(
+            searcher.find_mimetype_from_data(&compound_file([0; 16], &["Workbook"])),
+            Ok(Some(mime("application/vnd.ms-excel")))
+        );
+        let ole = mime("application/x-ole-storage");
+        let unknown = compound_file([0; 16], &["Contents"]);
+        assert_eq!(OleProbe.probe(&ole, &unknown), None);
+        assert_eq!(OleProbe.probe(&ole, &unknown[..600]), None);
+    }
+
+    #[test]
+    fn newer_minor_version_is_read() {
+        let mut data = system_cache().bytes().to_vec();
+        data[2..4].copy_from_slice(&3u16.to_be_bytes());
+
+        let cache = MimeCache::from_storage(data).unwrap();
+        assert_eq!(cache.cache_header.minor_version, 3);
+        assert_eq!(
+            cache.find_icon_for_mimetype(mime("application/pdf")),
+            Ok("x-office-document".to_string())
+        );
+    }
+
+    #[test]
+    fn cache_versions() {
+        let with_version = |major: u16, minor: u16| {
+            let mut data = system_cache().bytes().to_vec();
+            data[0..2].copy_from_slice(&major.to_be_bytes());
+            data[2..4].copy_from_slice(&minor.to_be_bytes());
+            MimeCache::from_storage(data).map(|cache| cache.cache_header.minor_version)
+        };
+        assert_eq!(with_version(1, 1), Ok(1));
+        assert_eq!(
+            with_version(1, 0),
+            Err(Error::UnsupportedCacheVersion { major: 1, minor: 0 })
+        );
+        assert_eq!(
+            with_version(2, 0),
+            Err(Error::UnsupportedCacheVersion { major: 2, minor: 0 })
+        );
+    }
+
+    #[test]
+    fn corrupt_cache_errors_instead_of_panicking() {
+        let exercise = |data: Vec<u8>| {
+            let model = MimeDatabaseModel::from_cache_bytes(&data);
+            let Ok(cache) = MimeCache::from_storage(data) else {
+                return model.map(|_| ());
+            };
+            let _ = cache.resolve_alias(&mime("application/x-pdf"));
+            let _ = cache.parents(&mime("image/svg+xml"));
+            let _ = cache.find_icon_for_mimetype(mime("application/pdf"));
+            let searcher = MimeSearcher::from_databases(
+                vec![Layer {
+                    source: None,
+                    database: Some(Arc::new(cache)),
+                    cache: None,
+                    globs2: None,
+                }],
+                Vec::new(),
+                Vec::new(),
+                Origin::default(),
+                builder::Options::default(),
+            );
+            if let Ok(searcher) = searcher {
+                let _ = searcher.find_mimetype_from_data(b"%PDF-1.7\n");
+                let _ = searcher.find_mimetype_from_filepath(Path::new("a.tar.gz"));
+            }
+            model.map(|_| ())
+        };
+
+        let original = system_cache().bytes().to_vec();
+        for len in (0..original.len()).step_by(original.len() / 61 + 1) {
+            let result = exercise(original[..len].to_vec());
+            if len < original.len() / 2 {
+                assert!(result.is_err(), "length {len}");
+            }
+        }
+        for offset in (40..original.len()).step_by(original.len() / 97 + 1) {
+            let mut data = original.clone();
+            data[offset..(offset + 4).min(original.len())].fill(0xFF);
+            let _ = exercise(data);
+        }
+        let glob = |pattern: &str| GlobRecord {
+            pattern: pattern.to_string(),
+            mime: mime("text/plain"),
+            weight: 50,
+            case_sensitive: false,
+        };
+        let model = MimeDatabaseModel {
+            globs: vec![glob("a*"), glob("b*")],
+            ..Default::default()
+        };
+        let mut data = model.to_cache_bytes();
+        let second = MimeCacheHeader::parse(&data).unwrap().glob_list_offset as usize + 4 + 12;
+        data[second..second + 4].copy_from_slice(&0xFF_FFFFu32.to_be_bytes());
+        assert_eq!(
+            MimeDatabaseModel::from_cache_bytes(&data),
+            Err(Error::CorruptCache {
+                section: CacheSection::GlobList,
+                entry: Some(1),
+                offset: 0xFF_FFFF,
+                problem: Corruption::OutOfRange,
+            })
+        );
+        assert_eq!(
+            Error::CorruptCache {
+                section: CacheSection::GlobList,
+                entry: Some(42),
+                offset: 0x1f00,
+                problem: Corruption::OutOfRange,
+            }
+            .to_string(),
+            "mime.cache is corrupt: offset past the end of the file at offset 0x1f00 in the glob \
+             list entry 42"
+        );
+    }
+
+    #[test]
+    fn database_dirs_from_environment() {
+        let env = |vars: &'static [(&str, &str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.into())
+            }
+        };
+        let home = Some(Path::new("/home/user"));
+        let paths = |paths: &[&str]| paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+
+        assert_eq!(
+            dirs::mime_dirs_from(env(&[]), home),
+            paths(&[
+                "/home/user/.local/share/mime",
+                "/usr/local/share/mime",
+                "/usr/share/mime"
+            ])
+        );
+        assert_eq!(
+            dirs::mime_dirs_from(
+                env(&[
+                    ("XDG_DATA_HOME", "/data"),
+                    ("XDG_DATA_DIRS", "/a:relative:/b/")
+                ]),
+                home
+            ),
+            paths(&["/data/mime", "/a/mime", "/b/mime"])
+        );
+        assert_eq!(
+            dirs::mime_dirs_from(
+                env(&[
+                    ("SMI_MIME_DIR", "/fixture:/other"),
+                    ("XDG_DATA_HOME", "/data")
+                ]),
+                home
+            ),
+            paths(&["/fixture", "/other"])
+        );
+    }
+
+    #[test]
+    fn searcher_with_dirs() {
+        let dir = TempDir::new();
+
+        let model = MimeDatabaseModel {
+            generic_icons: vec![(mime("application/x-test"), "test-icon".into())],
+            ..Default::default()
+        };
+        std::fs::write(dir.join("mime.cache"), model.to_cache_bytes()).unwrap();
+        std::fs::write(dir.join("globs2"), "50:application/x-test:*.smitest\n").unwrap();
+
+        let searcher = MimeSearcher::with_dirs(std::slice::from_ref(&dir)).unwrap();
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("a.smitest")),
+            Some(mime("application/x-test"))
+        );
+        assert_eq!(
+            searcher.find_icon_for_mimetype(mime("application/x-test")),
+            Ok("test-icon".to_string())
+        );
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("a.pdf")),
+            None
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        #[cfg(not(feature = "bundled"))]
+        assert_eq!(
+            MimeSearcher::with_dirs(&[dir.to_path_buf()]).unwrap_err(),
+            Error::MimeCacheNotFound
+        );
+    }
+
+    #[test]
+    fn builder_toggles() {
+        let builder = || MimeSearcher::builder().data_dirs([system_dir()]);
+        let pdf = b"%PDF-1.7\n";
+
+        let full = builder().build().unwrap();
+        assert_eq!(
+            full.find_mimetype_from_data(pdf),
+            Ok(Some(mime("application/pdf")))
+        );
+
+        let slim = builder()
+            .with_magic(false)
+            .with_globs(false)
+            .with_icons(false)
+            .build()
+            .unwrap();
+        assert_eq!(slim.find_mimetype_from_data(pdf), Ok(None));
+        assert_eq!(slim.max_magic_extent(), 0);
+        assert_eq!(slim.find_mimetype_from_filepath(Path::new("a.pdf")), None);
+        assert_eq!(
+            slim.find_icon_for_mimetype(mime("application/pdf")),
+            Err(Error::NoIconFound)
+        );
+
+        let limited = builder().sniff_limit(4).build().unwrap();
+        assert_eq!(limited.max_magic_extent(), 4);
+    }
+
+    #[test]
+    fn cache_from_shared_storage() {
+        let bytes: Arc<[u8]> = system_cache().bytes().into();
+        let searcher = MimeSearcher::builder()
+            .data_dirs(Vec::<PathBuf>::new())
+            .add_database(MimeCache::from_storage(bytes.clone()).unwrap())
+            .add_database(MimeCache::from_storage(bytes.clone()).unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(
+            searcher.find_mimetype_from_data(b"%PDF-1.7\n"),
+            Ok(Some(mime("application/pdf")))
+        );
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("a.pdf")),
+            Some(mime("application/pdf"))
+        );
+        assert_eq!(Arc::strong_count(&bytes), 3);
+
+        let err = MimeCache::from_storage(&b"not a cache"[..]).unwrap_err();
+        assert!(err.is_parse_failure(), "{err}");
+    }
+
+    #[test]
+    fn glob_match_names_the_pattern() {
+        let searcher = MimeSearcher::with_dirs(&[system_dir()]).unwrap();
+        let matched = |name: &str| searcher.find_glob_match(Path::new(name)).unwrap();
+
+        let tarball = matched("backup.TAR.gz");
+        assert_eq!(tarball.pattern, "*.tar.gz");
+        assert_eq!(tarball.extension(), Some("tar.gz"));
+        assert_eq!(tarball.mime, mime("application/x-compressed-tar"));
+        assert_eq!(matched("notes.gz").pattern, "*.gz");
+        assert_eq!(matched("release.1.0.gz").pattern, "*.gz");
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("backup.tar.gz")),
+            Some(tarball.mime)
+        );
+        assert_eq!(searcher.find_glob_match(Path::new("README")), None);
+    }
+
+    #[test]
+    #[cfg(feature = "xml")]
+    fn packages_compile_to_a_cache() {
+        let dir = TempDir::new();
+        std::fs::create_dir_all(dir.join("packages")).unwrap();
+        std::fs::write(
+            dir.join("packages/a.xml"),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- Installed by an application -->
+<mime-info xmlns="http://www.freedesktop.org/standards/shared-mime-info">
+  <mime-type type="application/x-plug">
+    <comment>Plug &amp; play</comment>
+    <sub-class-of type="application/zip"/>
+    <alias type="application/x-plugin"/>
+    <generic-icon name="package-x-generic"/>
+    <glob pattern="*.PLUG"/>
+    <glob pattern="*.tar.plug" weight="60"/>
+    <magic priority="70">
+      <match type="string" value="PLUG\x00" offset="0:4">
+        <match type="big16" value="0x0102" offset="8"/>
+      </match>
+    </magic>
+  </mime-type>
+</mime-info>
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("packages/Override.xml"),
+            r#"<mime-info><mime-type type="application/x-plug">
+                <glob-deleteall/><glob pattern="*.plg"/></mime-type></mime-info>"#,
+        )
+        .unwrap();
+
+        let model = MimeDatabaseModel::from_packages(&dir).unwrap();
+        assert_eq!(
+            model.aliases,
+            [(mime("application/x-plugin"), mime("application/x-plug"))]
+        );
+        assert_eq!(
+            model.parents,
+            [(mime("application/x-plug"), vec![mime("application/zip")])]
+        );
+        assert_eq!(
+            model
+                .literals
+                .iter()
+                .map(|g| &g.pattern[..])
+                .collect::<Vec<_>>(),
+            ["__NOGLOBS__"]
+        );
+        assert_eq!(
+            model
+                .suffixes
+                .iter()
+                .map(|g| &g.pattern[..])
+                .collect::<Vec<_>>(),
+            ["*.plg"]
+        );
+        let matchlet = &model.magic[0].matchlets[0];
+        assert_eq!((matchlet.range_start, matchlet.range_length), (0, 5));
+        assert_eq!(matchlet.value, b"PLUG\0");
+        assert_eq!(matchlet.children[0].value, [1, 2]);
+
+        let searcher = MimeSearcher::builder()
+            .data_dirs(Vec::<PathBuf>::new())
+            .add_database(MimeCache::from_storage(model.to_cache_bytes()).unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(
+            searcher.find_mimetype_from_data(b"..PLUG\0.\x01\x02"),
+            Ok(Some(mime("application/x-plug")))
+        );
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("a.plg")),
+            Some(mime("application/x-plug"))
+        );
+
+        std::fs::write(dir.join("packages/b.xml"), "<mime-info>\n<mime-type>").unwrap();
+        let broken = MimeDatabaseModel::from_packages(&dir).unwrap_err();
+        assert!(broken.is_parse_failure());
+        assert!(
+            broken
+                .to_string()
+                .ends_with("b.xml:2: <mime-type> needs a `type` attribute"),
+            "{broken}"
+        );
+
+        let whole_range = r#"<mime-info><mime-type type="application/x-far"><magic>
+<match type="string" offset="0:4294967295" value="AB"/></magic></mime-type></mime-info>"#;
+        std::fs::write(dir.join("packages/b.xml"), whole_range).unwrap();
+        let broken = MimeDatabaseModel::from_packages(&dir).unwrap_err();
+        assert!(
+            broken.to_string().ends_with("match offset out of range"),
+            "{broken}"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "xml")]
+    fn doctor_finds_and_regenerates() {
+        let dir = TempDir::new();
+        std::fs::create_dir_all(dir.join("packages")).unwrap();
+        assert!(doctor_dir(&dir, false).is_healthy());
+
+        let package = r#"<mime-info><mime-type type="application/x-doc">
+            <glob pattern="*.xdoc"/><glob pattern="*.XDoc" case-sensitive="true"/>
+            </mime-type></mime-info>"#;
+        std::fs::write(dir.join("packages/doc.xml"), package).unwrap();
+        let report = doctor_dir(&dir, false);
+        assert_eq!(report.problems, [Problem::MissingCache]);
+        assert!(!report.is_healthy());
+
+        let report = doctor_dir(&dir, true);
+        assert!(report.regenerated);
+        assert!(doctor_dir(&dir, false).problems.is_empty());
+        let globs2 = std::fs::read_to_string(dir.join("globs2")).unwrap();
+        assert!(
+            globs2.contains("\n50:application/x-doc:*.xdoc\n"),
+            "{globs2}"
+        );
+        assert!(
+            globs2.contains("\n50:application/x-doc:*.XDoc:cs\n"),
+            "{globs2}"
+        );
+        let searcher = MimeSearcher::with_dirs(std::slice::from_ref(&dir)).unwrap();
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("a.xdoc")),
+            Some(mime("application/x-doc"))
+        );
+
+        let package = r#"<mime-info><mime-type type="application/x-new"/></mime-info>"#;
+        std::fs::write(dir.join("packages/new.xml"), package).unwrap();
+        let report = doctor_dir(&dir, false);
+        assert!(report.problems.contains(&Problem::OrphanedPackage {
+            path: dir.join("packages/new.xml"),
+            missing: vec![mime("application/x-new")],
+        }));
+
+        std::fs::write(dir.join("packages/bad.xml"), "<mime-info>").unwrap();
+        let report = doctor_dir(&dir, true);
+        assert!(!report.regenerated);
+        assert!(
+            report
+                .problems
+                .iter()
+                .any(|problem| matches!(problem, Problem::InvalidPackage(_)))
+        );
+        assert!(report.to_string().contains("bad.xml:1: unclosed element"));
+
+        // Regenerating replaces an unreadable globs2, but not an unreadable package
+        let unreadable = |path: &str| Problem::Unreadable {
+            path: dir.join(path),
+            error: Error::io(&dir.join(path), std::io::ErrorKind::PermissionDenied.into()),
+        };
+        let report = DoctorReport {
+            dir: dir.clone(),
+            problems: vec![Problem::MissingCache, unreadable("globs2")],
+            regenerated: true,
+        };
+        assert!(report.is_healthy());
+        let report = DoctorReport {
+            problems: vec![Problem::MissingCache, unreadable("packages/a.xml")],
+            ..report
+        };
+        assert!(!report.is_healthy());
+    }
+
+    #[test]
+    fn batch_lookup_of_paths() {
+        let dir = TempDir::new();
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("report.pdf"), b"not really").unwrap();
+        std::fs::write(dir.join("untitled"), b"%PDF-1.7\n").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(dir.join("gone"), dir.join("dangling")).unwrap();
+
+        let searcher = MimeSearcher::with_dirs(&[system_dir()]).unwrap();
+        let paths = ["sub", "report.pdf", "untitled", "missing", "dangling"].map(|p| dir.join(p));
+        let found = searcher.find_mimetypes_for_paths(&paths);
+        #[cfg(feature = "rayon")]
+        assert_eq!(searcher.par_classify(&paths), found);
+        assert_eq!(
+            found[..4],
+            [
+                Some(MimeType::INODE_DIRECTORY),
+                Some(MimeType::APPLICATION_PDF),
+                Some(MimeType::APPLICATION_PDF),
+                None,
+            ]
+        );
+        #[cfg(unix)]
+        assert_eq!(found[4], Some(MimeType::INODE_SYMLINK));
+    }
+
+    #[test]
+    fn temporary_types_can_be_revoked() {
+        let mut searcher = MimeSearcher::with_dirs(&[system_dir()]).unwrap();
+        searcher.set_sniff_memo_capacity(8);
+        let project = mime("application/x-test-project");
+        let definition = |weight| MimeDatabaseModel {
+            suffixes: vec![GlobRecord {
+                pattern: "*.pdf".to_string(),
+                mime: project.clone(),
+                weight,
+                case_sensitive: false,
+            }],
+            magic: vec![MagicMatch {
+                priority: 90,
+                mime: project.clone(),
+                matchlets: vec![Matchlet {
+                    range_start: 0,
+                    range_length: 1,
+                    word_size: 1,
+                    value: b"%PDF".to_vec(),
+                    mask: None,
+                    children: Vec::new(),
+                }],
+            }],
+            ..Default::default()
+        };
+        let pdf = b"%PDF-1.7\n";
+        assert_eq!(
+            searcher.find_mimetype_from_data(pdf),
+            Ok(Some(mime("application/pdf")))
+        );
+
+        let first = searcher.register_temporary_type(definition(50));
+        let second = searcher.register_temporary_type(definition(60));
+        assert_eq!(
+            searcher.find_mimetype_from_data(pdf),
+            Ok(Some(project.clone()))
+        );
+        assert_eq!(
+            searcher
+                .find_glob_match(Path::new("a.pdf"))
+                .map(|m| m.weight),
+            Some(60)
+        );
+
+        assert!(searcher.revoke_temporary_type(second));
+        assert_eq!(
+            searcher
+                .find_glob_match(Path::new("a.pdf"))
+                .map(|m| m.weight),
+            Some(50)
+        );
+        let mut other = MimeSearcher::with_dirs(&[system_dir()]).unwrap();
+        let foreign = other.register_temporary_type(MimeDatabaseModel::default());
+        assert!(!searcher.revoke_temporary_type(foreign));
+
+        assert!(searcher.revoke_temporary_type(first));
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("a.pdf")),
+            Some(mime("application/pdf"))
+        );
+        assert_eq!(
+            searcher.find_mimetype_from_data(pdf),
+            Ok(Some(mime("application/pdf")))
+        );
+    }
+
+    #[test]
+    fn display_order_groups_by_media() {
+        let sorted = |order: &DisplayOrder, mut types: Vec<(MimeType, &'static str)>| {
+            types.sort_by(|a, b| order.compare((&a.0, a.1), (&b.0, b.1)));
+            types.into_iter().map(|(_, d)| d).collect::<Vec<_>>()
+        };
+        let types = vec![
+            (mime("text/plain"), "plain text"),
+            (mime("image/png"), "png image"),
+            (mime("image/gif"), "GIF image"),
+            (mime("application/zip"), "Zip archive"),
+            (mime("application/x-zip"), "Zip archive"),
+        ];
+        assert_eq!(
+            sorted(&DisplayOrder::new(), types),
+            [
+                "Zip archive",
+                "Zip archive",
+                "GIF image",
+                "png image",
+                "plain text"
+            ]
+        );
+
+        #[cfg(feature = "collation")]
+        {
+            let words = || {
+                ["zebra", "äpple", "apple"]
+                    .map(|word| (mime("text/plain"), word))
+                    .to_vec()
+            };
+            let swedish = DisplayOrder::for_locale("sv").unwrap();
+            assert_eq!(sorted(&swedish, words()), ["apple", "zebra", "äpple"]);
+            let german = DisplayOrder::for_locale("de").unwrap();
+            assert_eq!(sorted(&german, words()), ["apple", "äpple", "zebra"]);
+            assert!(DisplayOrder::for_locale("not a locale!").is_none());
+        }
+    }
+
+    #[test]
+    fn all_globs_lists_every_source() {
+        let dir = TempDir::new();
+        let globs2 = "# comment\n50:application/x-test:*.smitest\n60:text/x-test:*.Test:cs\n";
+        std::fs::write(dir.join("globs2"), globs2).unwrap();
+        let added = MimeDatabaseModel {
+            suffixes: vec![GlobRecord {
+                pattern: "*.smitest".to_string(),
+                mime: mime("text/plain"),
+                weight: 80,
+                case_sensitive: false,
+            }],
+            ..Default::default()
+        };
+        let searcher = MimeSearcher::builder()
+            .data_dirs([&*dir])
+            .add_database(added)
+            .build()
+            .unwrap();
+
+        let record = |pattern: &str, mime_type: &str, weight, case_sensitive| GlobRecord {
+            pattern: pattern.to_string(),
+            mime: mime(mime_type),
+            weight,
+            case_sensitive,
+        };
+        // The overridden glob is still listed
+        assert_eq!(
+            searcher.all_globs().unwrap(),
+            [
+                record("*.Test", "text/x-test", 60, true),
+                record("*.smitest", "application/x-test", 50, false),
+                record("*.smitest", "text/plain", 80, false),
+            ]
+        );
+        // globs2 is read again rather than kept
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(searcher.all_globs(), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn case_sensitive_globs_match_as_written() {
+        let glob = |pattern: &str, mime_type: &str, case_sensitive| GlobRecord {
+            pattern: pattern.to_string(),
+            mime: mime(mime_type),
+            weight: 50,
+            case_sensitive,
+        };
+        let globs2 = "50:text/x-c++src:*.C:cs\n50:text/x-csrc:*.c:cs\n\
+                      50:application/x-core:core:cs\n50:text/x-smi:*.SMI\n";
+        let from_globs2 = TempDir::new();
+        std::fs::write(from_globs2.join("globs2"), globs2).unwrap();
+        let from_cache = TempDir::new();
+        let model = MimeDatabaseModel {
+            suffixes: vec![
+                glob("*.C", "text/x-c++src", true),
+                glob("*.c", "text/x-csrc", true),
+                glob("*.smi", "text/x-smi", false),
+            ],
+            literals: vec![glob("core", "application/x-core", true)],
+            ..Default::default()
+        };
+        std::fs::write(from_cache.join("mime.cache"), model.to_cache_bytes()).unwrap();
+
+        for dir in [from_globs2, from_cache] {
+            let mut searcher = MimeSearcher::with_dirs(&[dir.to_path_buf()]).unwrap();
+            // The lookup cache tells the cases apart too
+            searcher.set_lookup_cache_capacity(8);
+            assert_eq!(
+                searcher.find_mimetype_from_filepath(Path::new("b.C")),
+                Some(mime("text/x-c++src"))
+            );
+            assert_eq!(
+                searcher.find_mimetype_from_filepath(Path::new("b.c")),
+                Some(mime("text/x-csrc"))
+            );
+            let find = |name: &str| {
+                searcher
+                    .find_mimetype_ref_from_filepath(Path::new(name))
+                    .map(|m| m.to_string())
+            };
+            assert_eq!(find("a.C").as_deref(), Some("text/x-c++src"));
+            assert_eq!(find("a.c").as_deref(), Some("text/x-csrc"));
+            assert_eq!(find("a.Smi").as_deref(), Some("text/x-smi"));
+            #[cfg(feature = "complex_globs")]
+            {
+                assert_eq!(find("core").as_deref(), Some("application/x-core"));
+                assert_eq!(find("CORE"), None);
+            }
+        }
+    }
+
+    #[test]
+    fn extensions_for_a_type() {
+        let test_type = mime("application/x-test");
+        let glob = |pattern: &str, weight| GlobRecord {
+            pattern: pattern.to_string(),
+            mime: test_type.clone(),
+            weight,
+            case_sensitive: false,
+        };
+        let added = MimeDatabaseModel {
+            aliases: vec![(mime("application/x-test-alias"), test_type.clone())],
+            suffixes: vec![glob("*.smib", 50), glob("*.smia", 50), glob("*.smic", 60)],
+            globs: vec![glob("smi-*", 90)],
+            ..Default::default()
+        };
+        let searcher = MimeSearcher::builder()
+            .data_dirs([system_dir()])
+            .add_database(added)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            searcher.extensions_for(&mime("application/x-test-alias")),
+            ["smic", "smia", "smib"]
+        );
+        let jpeg = searcher.extensions_for(&mime("image/jpeg"));
+        assert!(
+            ["jpg", "jpeg", "jpe"]
+                .iter()
+                .all(|ext| jpeg.contains(&ext.to_string()))
+        );
+        assert!(
+            searcher
+                .extensions_for(&mime("application/x-unknown"))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn preferred_extensions() {
+        let test_type = mime("application/x-test");
+        let glob = |pattern: &str, weight| GlobRecord {
+            pattern: pattern.to_string(),
+            mime: test_type.clone(),
+            weight,
+            case_sensitive: false,
+        };
+        let added = MimeDatabaseModel {
+            suffixes: vec![
+                glob("*.smi.gz", 60),
+                glob("*.smi+", 60),
+                glob("*.smiz", 60),
+                glob("*.smix", 60),
+                glob("*.sm", 40),
+            ],
+            ..Default::default()
+        };
+        let searcher = MimeSearcher::builder()
+            .data_dirs([system_dir()])
+            .add_database(added)
+            .build()
+            .unwrap();
+
+        let preferred = |name: &str| searcher.preferred_extension(&mime(name));
+        assert_eq!(preferred("application/x-test").as_deref(), Some("smix"));
+        assert_eq!(preferred("image/jpeg").as_deref(), Some("jpg"));
+        assert_eq!(preferred("text/plain").as_deref(), Some("txt"));
+        assert_eq!(preferred("application/x-unknown"), None);
+    }
+
+    #[test]
+    fn icon_listings() {
+        let test_type = mime("application/x-test");
+        let added = MimeDatabaseModel {
+            icons: vec![(test_type.clone(), "application-x-test".to_string())],
+            generic_icons: vec![
+                (test_type.clone(), "x-test".to_string()),
+                (mime("application/pdf"), "x-test-document".to_string()),
+            ],
+            ..Default::default()
+        };
+        let searcher = MimeSearcher::builder()
+            .data_dirs([system_dir()])
+            .add_database(added)
+            .build()
+            .unwrap();
+
+        let icons = searcher.all_icons().unwrap();
+        assert!(icons.contains(&(test_type.clone(), "application-x-test".to_string())));
+        let generic = searcher.all_generic_icons().unwrap();
+        assert!(generic.contains(&(test_type, "x-test".to_string())));
+        // The added database wins over the system one, which has its own icon for PDFs
+        assert!(generic.contains(&(mime("application/pdf"), "x-test-document".to_string())));
+        assert!(!generic.contains(&(mime("application/pdf"), "x-office-document".to_string())));
+        assert!(generic.len() > 2);
+        assert!(generic.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn info_gathers_everything_about_a_type() {
+        let searcher = MimeSearcher::with_dirs(&[system_dir()]).unwrap();
+        let info = searcher.info(&mime("application/x-pdf"));
+        assert_eq!(info.mime, mime("application/pdf"));
+        // In the user's language, so only compare with the lookup of its own
+        assert!(info.comment.is_some());
+        assert_eq!(info.comment, searcher.comment(&info.mime));
+        assert_eq!(info.generic_icon.as_deref(), Some("x-office-document"));
+        assert!(info.globs.iter().any(|glob| glob.pattern == "*.pdf"));
+        assert!(info.aliases.contains(&mime("application/x-pdf")));
+        assert!(info.aliases.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let svg = searcher.info(&mime("image/svg+xml"));
+        assert!(svg.parents.contains(&mime("application/xml")));
+    }
+
+    #[test]
+    fn localized_comments() {
+        let searcher = MimeSearcher::with_dirs(&[system_dir()]).unwrap();
+        let pdf = mime("application/x-pdf");
+        let comment = |locale| searcher.comment_for_locale(&pdf, locale);
+        assert_eq!(comment("C").as_deref(), Some("PDF document"));
+        assert_eq!(comment("de").as_deref(), Some("PDF-Dokument"));
+        // Falls back from the country to the language, then to the untranslated comment
+        assert_eq!(comment("de_DE.UTF-8@euro").as_deref(), Some("PDF-Dokument"));
+        assert_eq!(comment("pt-BR"), comment("pt_BR"));
+        assert_ne!(comment("pt_BR"), comment("pt"));
+        assert_eq!(comment("xx_YY").as_deref(), Some("PDF document"));
+        assert_eq!(
+            searcher.comment_for_locale(&mime("application/x-unknown-test"), "C"),
+            None
+        );
+    }
+
+    #[test]
+    fn acronyms() {
+        let searcher = MimeSearcher::with_dirs(&[system_dir()]).unwrap();
+        let pdf = mime("application/x-pdf");
+        assert_eq!(searcher.acronym(&pdf).as_deref(), Some("PDF"));
+        let expanded = searcher.expanded_acronym(&pdf);
+        assert_eq!(expanded.as_deref(), Some("Portable Document Format"));
+        assert_eq!(searcher.info(&pdf).expanded_acronym, expanded);
+        assert_eq!(searcher.acronym(&MimeType::TEXT_PLAIN), None);
+    }
+
+    #[test]
+    fn database_sources() {
+        let dir = TempDir::new();
+        std::fs::write(dir.join("globs2"), "50:application/x-test:*.smitest\n").unwrap();
+        let dirs = [system_dir(), dir.clone(), dir.join("missing")];
+        let searcher = MimeSearcher::builder()
+            .data_dirs(dirs.clone())
+            .build()
+            .unwrap();
+        let without_globs = MimeSearcher::builder()
+            .data_dirs(dirs)
+            .with_globs(false)
+            .build()
+            .unwrap();
+        let derived = without_globs.derive(std::slice::from_ref(&dir)).unwrap();
+
+        let sources = searcher.sources();
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[0].dir, system_dir());
+        assert_eq!(sources[0].cache, Some(system_dir().join("mime.cache")));
+        assert_eq!(sources[0].cache_version.map(|(major, _)| major), Some(1));
+        assert!(sources[0].cache_modified.is_some());
+        assert_eq!(sources[1].dir, *dir);
+        assert_eq!(sources[1].cache, None);
+        assert_eq!(sources[1].globs2, Some(dir.join("globs2")));
+
+        // Without globs the second directory has nothing to load
+        let dirs: Vec<_> = without_globs.sources().iter().map(|s| &s.dir).collect();
+        assert_eq!(dirs, [&system_dir()]);
+        assert_eq!(without_globs.sources()[0].globs2, None);
+        assert_eq!(derived.sources(), without_globs.sources());
+    }
+
+    #[test]
+    fn database_stats() {
+        let searcher = MimeSearcher::with_dirs(&[system_dir()]).unwrap();
+        let stats = searcher.stats().unwrap();
+        assert_eq!(
+            stats.types,
+            searcher.list_types_matching("*/*").unwrap().len()
+        );
+        assert!(stats.globs > stats.types / 2);
+        assert!(stats.magic_rules > 0 && stats.aliases > 0 && stats.generic_icons > 0);
+        assert!(stats.index_bytes > 0);
+
+        let added = MimeSearcher::builder()
+            .data_dirs([system_dir()])
+            .add_database(MimeDatabaseModel {
+                aliases: vec![(mime("application/x-test-alias"), mime("application/x-test"))],
+                ..Default::default()
+            })
+            .with_globs(false)
+            .build()
+            .unwrap()
+            .stats()
+            .unwrap();
+        assert_eq!(added.aliases, stats.aliases + 1);
+        assert_eq!(added.types, stats.types + 1);
+        // The caches still define globs, but no index is built for them
+        assert!(added.globs > 0);
+        assert!(added.index_bytes < stats.index_bytes);
+    }
+
+    #[test]
+    fn types_by_media_class() {
+        let searcher = MimeSearcher::with_dirs(&[system_dir()]).unwrap();
+        let images = searcher.types_in_media("image").unwrap();
+        assert!(images.contains(&MimeType::IMAGE_PNG));
+        assert!(images.iter().all(|mime| mime.media() == "image"));
+        assert_eq!(images, searcher.list_types_matching("image/*").unwrap());
+        assert_eq!(searcher.types_in_media("Image").unwrap(), images);
+        assert!(searcher.types_in_media("no-such-media").unwrap().is_empty());
+    }
+
+    #[test]
+    fn type_search() {
+        let searcher = MimeSearcher::with_dirs(&[system_dir()]).unwrap();
+        let found = searcher.search_types("png").unwrap();
+        assert_eq!(found[0], MimeType::IMAGE_PNG);
+        let spreadsheets = searcher.search_types("SPREA").unwrap();
+        assert!(spreadsheets.contains(&mime("application/vnd.oasis.opendocument.spreadsheet")));
+        // Scattered characters match too, after anything matching outright
+        let scattered = searcher.search_types("odsprsht").unwrap();
+        assert!(scattered.contains(&mime("application/vnd.oasis.opendocument.spreadsheet")));
+        // Aliases are searched, and give their canonical type
+        let pdf = mime("application/pdf");
+        assert!(searcher.search_types("x-pdf").unwrap().contains(&pdf));
+        assert!(searcher.search_types("zzzzzzzzzz").unwrap().is_empty());
+        assert_eq!(
+            searcher.search_types("").unwrap().len(),
+            searcher.stats().unwrap().types
+        );
+
+        // Descriptions are in the user's language, so only compare with the lookup of its own
+        assert!(!searcher.search_types("document").unwrap().contains(&pdf));
+        let described = searcher.search_types_with_comments("document").unwrap();
+        let comment = searcher.comment(&pdf).unwrap().to_lowercase();
+        assert_eq!(described.contains(&pdf), comment.contains("document"));
+    }
+
+    #[test]
+    fn borrowed_lookups() {
+        let searcher = MimeSearcher::with_dirs(&[system_dir()]).unwrap();
+        let png = searcher
+            .find_mimetype_ref_from_filepath(Path::new("a.png"))
+            .unwrap();
+        assert_eq!(png, MimeType::IMAGE_PNG);
+        assert_eq!((png.media(), png.subtype()), ("image", "png"));
+        assert_eq!(png.to_mime_type(), MimeType::IMAGE_PNG);
+        assert_eq!(png.to_string(), "image/png");
+        assert_eq!(
+            searcher.find_mimetype_ref_from_filepath(Path::new("a.smitest")),
+            None
+        );
+
+        let pdf = searcher
+            .find_magic_match_ref(b"%PDF-1.7\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(pdf.essence_str(), "application/pdf");
+        assert_eq!(searcher.find_magic_match_ref(b"").unwrap(), None);
+        // Hashes like the owned type, as both hash just the string
+        let hash = |value: &dyn Fn(&mut std::hash::DefaultHasher)| {
+            let mut hasher = std::hash::DefaultHasher::new();
+            value(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(
+            hash(&|h| pdf.hash(h)),
+            hash(&|h| mime("application/pdf").hash(h))
+        );
+    }
+
+    #[test]
+    fn glob_index_is_built_on_first_use() {
+        let searcher = MimeSearcher::with_dirs(&[system_dir()]).unwrap();
+        let derived = searcher.derive(&[]).unwrap();
+        assert!(
+            searcher
+                .find_icon_for_mimetype(mime("application/pdf"))
+                .is_ok()
+        );
+        assert!(searcher.glob_index.index.get().is_none());
+
+        // Looking up a name in a derived searcher builds its parent's index too
+        assert_eq!(
+            derived.find_mimetype_from_filepath(Path::new("a.png")),
+            Some(MimeType::IMAGE_PNG)
+        );
+        assert!(searcher.glob_index.index.get().is_some());
+        assert!(searcher.glob_index.layers.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn cache_extensions_are_looked_up_in_place() {
+        let dir = TempDir::new();
+        let glob = |pattern: &str, mime_type: &str, weight| GlobRecord {
+            pattern: pattern.to_string(),
+            mime: mime(mime_type),
+            weight,
+            case_sensitive: false,
+        };
+        let model = MimeDatabaseModel {
+            suffixes: vec![
+                glob("*.smicache", "application/x-cached", 60),
+                glob("*.smicache", "application/x-other", 40),
+                glob("*.pdf", "application/x-not-pdf", 50),
+                glob("*.smigone", "application/x-gone", 50),
+            ],
+            ..Default::default()
+        };
+        std::fs::write(dir.join("mime.cache"), model.to_cache_bytes()).unwrap();
+        std::fs::write(
+            dir.join("globs2"),
+            "70:application/x-overlay:*.smicache\n0:image/png:__NOGLOBS__\n",
+        )
+        .unwrap();
+        let searcher = MimeSearcher::with_dirs(&[dir.clone(), system_dir()]).unwrap();
+
+        // The cache and the globs2 of the same directory both count
+        let types: Vec<_> = searcher
+            .types_for_extension("SMICACHE")
+            .into_iter()
+            .map(|m| (m.mime.to_string(), m.weight))
+            .collect();
+        assert_eq!(
+            types,
+            [
+                ("application/x-overlay".to_string(), 70),
+                ("application/x-cached".to_string(), 60),
+                ("application/x-other".to_string(), 40),
+            ]
+        );
+        assert_eq!(
+            searcher.find_mimetype_ref_from_filepath(Path::new("a.smigone")),
+            Some(MimeTypeRef::from(&mime("application/x-gone")))
+        );
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("a.pdf")),
+            Some(mime("application/x-not-pdf"))
+        );
+        assert_eq!(
+            searcher.extensions_for(&mime("application/x-cached")),
+            ["smicache"]
+        );
+        // Deleting a type hides its globs in the system cache
+        assert!(searcher.types_for_extension("png").is_empty());
+        assert!(
+            searcher
+                .find_glob_match(Path::new("a.smicache.pdf"))
+                .is_some_and(|matched| matched.pattern == "*.pdf")
+        );
+    }
+
+    #[test]
+    fn lookups_share_interned_types() {
+        let searcher = MimeSearcher::with_dirs(&[system_dir()]).unwrap();
+        let shared = |a: &MimeType, b: &MimeType| std::ptr::eq(a.essence_str(), b.essence_str());
+        let pdf = searcher
+            .find_mimetype_from_filepath(Path::new("a.pdf"))
+            .unwrap();
+        let again = searcher
+            .find_mimetype_from_filepath(Path::new("b.PDF"))
+            .unwrap();
+        assert!(shared(&pdf, &again));
+        let sniffed = searcher
+            .find_mimetype_from_data(b"%PDF-1.4\n")
+            .unwrap()
+            .unwrap();
+        assert!(shared(&pdf, &sniffed));
+
+        let mine = mime("application/pdf");
+        assert!(!shared(&pdf, &mine));
+        assert!(shared(&pdf, &searcher.intern(&mine)));
+        let derived = searcher.derive(&[]).unwrap();
+        assert!(shared(&pdf, &derived.intern(&mine)));
+        assert_eq!(pdf, mine);
+    }
+
+    #[test]
+    fn lookup_cache_remembers_recent_results() {
+        let plain = MimeSearcher::with_dirs(&[system_dir()]).unwrap();
+        let mut searcher = MimeSearcher::with_dirs(&[system_dir()]).unwrap();
+        searcher.set_lookup_cache_capacity(2);
+        for name in [
+            "a.pdf", "B.PDF", "c.tar.gz", "Makefile", "d.png", "a.pdf", ".e.PNG",
+        ] {
+            assert_eq!(
+                searcher.find_mimetype_from_filepath(Path::new(name)),
+                plain.find_mimetype_from_filepath(Path::new(name)),
+                "{name}"
+            );
+        }
+        for _ in 0..2 {
+            assert_eq!(
+                searcher.find_icon_for_mimetype(mime("application/pdf")),
+                plain.find_icon_for_mimetype(mime("application/pdf"))
+            );
+        }
+
+        let handle = searcher.register_temporary_type(MimeDatabaseModel {
+            suffixes: vec![GlobRecord {
+                pattern: "*.pdf".to_string(),
+                mime: mime("application/x-test"),
+                weight: 50,
+                case_sensitive: false,
+            }],
+            ..Default::default()
+        });
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("a.pdf")),
+            Some(mime("application/x-test"))
+        );
+        searcher.revoke_temporary_type(handle);
+
+        let lru = lru::Lru::new(2);
+        lru.insert("a", 1);
+        lru.insert("b", 2);
+        assert_eq!(lru.get("a"), Some(1));
+        lru.insert("c", 3);
+        assert_eq!(
+            (lru.get("a"), lru.get("b"), lru.get("c")),
+            (Some(1), None, Some(3))
+        );
+    }
+
+    #[cfg(feature = "codegen")]
+    #[test]
+    fn embedded_database() {
+        let dir = TempDir::new();
+        let out = dir.join("mime.cache");
+        embed_database(&system_dir(), Some(&[mime("application/pdf")]), &out).unwrap();
+        let cache: &'static [u8] = Vec::leak(std::fs::read(&out).unwrap());
+        std::fs::remove_file(&out).unwrap();
+
+        let searcher = MimeSearcher::embedded(cache).unwrap();
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("a.pdf")),
+            Some(mime("application/pdf"))
+        );
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("a.png")),
+            None
+        );
+        assert_eq!(searcher.sources(), []);
+        assert_eq!(
+            embed_database(&out, None, &out),
+            Err(Error::MimeCacheNotFound)
+        );
+    }
+
+    #[test]
+    fn binary_search_finds_list_edges() {
+        for len in 0..6 {
+            let sorted: Vec<usize> = (0..len).map(|i| 2 * i + 1).collect();
+            for key in 0..=2 * len + 1 {
+                let found = reader::bsearch(len, |i| Ok(sorted[i].cmp(&key))).unwrap();
+                assert_eq!(
+                    found,
+                    sorted.binary_search(&key).ok(),
+                    "{key} in {sorted:?}"
+                );
+            }
+        }
+
+        // The same through a cache's alias list, whose entries are read as they are compared
+        for len in 0..4 {
+            let aliases: Vec<_> = (0..len)
+                .map(|i| {
+                    (
+                        mime(&format!("text/x-alias{i}")),
+                        mime(&format!("text/x-type{i}")),
+                    )
+                })
+                .collect();
+            let model = MimeDatabaseModel {
+                aliases: aliases.clone(),
+                ..Default::default()
+            };
+            let cache = MimeCache::from_storage(model.to_cache_bytes()).unwrap();
+            for (alias, mime) in &aliases {
+                assert_eq!(cache.resolve_alias(alias), Ok(Some(mime.clone())));
+            }
+            for absent in [
+                "text/x-alias",
+                "text/x-alias00",
+                "text/x-alias9",
+                "a/b",
+                "z/z",
+            ] {
+                assert_eq!(cache.resolve_alias(&mime(absent)), Ok(None), "{absent}");
+            }
+        }
+    }
+
+    #[test]
+    fn index_profiles_agree() {
+        let build = |profile| {
+            MimeSearcher::builder()
+                .data_dirs([system_dir()])
+                .index_profile(profile)
+                .build()
+                .unwrap()
+        };
+        let minimal = build(IndexProfile::Minimal);
+        let fast = build(IndexProfile::Fast);
+        assert!(minimal.type_index.is_none());
+        assert!(minimal.glob_index.index.get().is_none());
+        assert!(fast.glob_index.index.get().is_some());
+        assert!(fast.globber().caches.is_empty());
+
+        for mime in minimal.known_types().unwrap() {
+            assert_eq!(
+                minimal.direct_parents(&mime),
+                fast.direct_parents(&mime),
+                "{mime}"
+            );
+            assert_eq!(
+                minimal.icon_lookup(&mime),
+                fast.icon_lookup(&mime),
+                "{mime}"
+            );
+            for ext in minimal.extensions_for(&mime) {
+                assert_eq!(
+                    minimal.types_for_extension(&ext),
+                    fast.types_for_extension(&ext),
+                    "{ext}"
+                );
+            }
+        }
+        for name in ["a.PNG", "a.tar.gz", "Makefile", "README", "a.unknown"] {
+            assert_eq!(
+                minimal.find_mimetype_from_filepath(Path::new(name)),
+                fast.find_mimetype_from_filepath(Path::new(name)),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn magic_ranges_and_masks() {
+        let mut data: Vec<u8> = (0..96u32).map(|i| (i * 37 % 251) as u8).collect();
+        data[40..63].copy_from_slice(b"needle-in-a-haystack-42");
+        // Offset by offset, as the rules are defined
+        let naive = |start: usize, length: usize, value: &[u8], mask: Option<&[u8]>| {
+            (start..start + length).any(|offset| {
+                let Some(window) = data.get(offset..offset + value.len()) else {
+                    return false;
+                };
+                match mask {
+                    None => window == value,
+                    Some(mask) => {
+                        (0..value.len()).all(|i| window[i] & mask[i] == value[i] & mask[i])
+                    }
+                }
+            })
+        };
+        let upper = [0xdf; 23];
+        let no_anchor = [0x7f; 23];
+        let mut sparse = [0; 23];
+        sparse[3] = 0xff;
+        // Where the range starts, its length, the value and the mask
+        type Rule<'a> = (usize, usize, &'a [u8], Option<&'a [u8]>);
+        let rules: [Rule; 12] = [
+            (40, 1, b"needle", None),
+            (0, 96, b"needle", None),
+            (0, 40, b"needle", None),
+            (0, 41, b"needle-in-a-haystack-42", None),
+            (41, 50, b"needle", None),
+            (30, 20, b"NEEDLE-IN-A-HAYSTACK-42", Some(&upper)),
+            (30, 20, b"NEEDLE-IN-A-HAYSTACK-43", Some(&upper)),
+            (0, 96, b"needle-in-a-haystack-42", Some(&no_anchor)),
+            (0, 96, b"xxxdxxxxxxxxxxxxxxxxxxx", Some(&sparse)),
+            (0, 96, b"xxxqxxxxxxxxxxxxxxxxxxx", Some(&sparse)),
+            (80, 40, b"needle", None),
+            (90, 10, b"haystack-42", Some(&upper[..11])),
+        ];
+        for (start, length, value, mask) in rules {
+            let rule = MagicMatch {
+                priority: 50,
+                mime: mime("application/x-test"),
+                matchlets: vec![Matchlet {
+                    range_start: start as u32,
+                    range_length: length as u32,
+                    word_size: 1,
+                    value: value.to_vec(),
+                    mask: mask.map(<[u8]>::to_vec),
+                    children: Vec::new(),
+                }],
+            };
+            let expected = naive(start, length, value, mask);
+            assert_eq!(
+                rule.matches(&mut &data[..]),
+                Ok(expected),
+                "{start} {length}"
+            );
+            let model = MimeDatabaseModel {
+                magic: vec![rule],
+                ..Default::default()
+            };
+            let cache = MimeCache::from_storage(model.to_cache_bytes()).unwrap();
+            let found = cache.magic_lookup(&mut &data[..], &|_| Ok(false)).unwrap();
+            assert_eq!(found.is_some(), expected, "{start} {length}");
+        }
+    }
+
+    #[test]
+    fn cache_sections_are_parsed_on_demand() {
+        let model = MimeDatabaseModel {
+            aliases: vec![(mime("text/x-alias"), MimeType::TEXT_PLAIN)],
+            generic_icons: vec![(MimeType::TEXT_PLAIN, "text-x-generic".to_string())],
+            ..Default::default()
+        };
+        let mut data = model.to_cache_bytes();
+        // Point the magic list past the end of the file
+        data[24..28].copy_from_slice(&0xFF_FFFFu32.to_be_bytes());
+        let cache = MimeCache::from_storage(data).unwrap();
+        let aliases = cache.to_model_of(&[CacheSection::AliasList]).unwrap();
+        assert_eq!(aliases.aliases, model.aliases);
+        assert!(aliases.generic_icons.is_empty());
+        assert!(
+            cache.sections[CacheSection::AliasList as usize]
+                .get()
+                .is_some()
+        );
+        assert!(
+            cache.sections[CacheSection::MagicList as usize]
+                .get()
+                .is_none()
+        );
+
+        // The broken section fails every time, the others keep working
+        let magic_error = cache.to_model();
+        assert!(matches!(
+            magic_error,
+            Err(Error::CorruptCache {
+                section: CacheSection::MagicList,
+                problem: Corruption::OutOfRange,
+                ..
+            })
+        ));
+        assert_eq!(
+            cache.to_model_of(&[CacheSection::MagicList, CacheSection::AliasList]),
+            magic_error
+        );
+        assert_eq!(
+            cache.to_model_of(&[CacheSection::GenericIconsList]),
+            Ok(MimeDatabaseModel {
+                generic_icons: model.generic_icons.clone(),
+                ..Default::default()
+            })
+        );
+        let searcher = MimeSearcher::builder()
+            .data_dirs(std::iter::empty::<PathBuf>())
+            .add_database(cache)
+            .build()
+            .unwrap();
+        assert_eq!(
+            searcher.info(&mime("text/x-alias")).aliases,
+            [mime("text/x-alias")]
+        );
+        assert_eq!(
+            searcher.all_generic_icons(),
+            Ok(vec![(MimeType::TEXT_PLAIN, "text-x-generic".to_string())])
+        );
+    }
+
+    #[test]
+    fn glob_index_packs_names() {
+        let dir = TempDir::new();
+        std::fs::write(
+            dir.join("globs2"),
+            "50:text/x-a:*.aa\n50:text/x-a:*.ab\n40:text/x-b:*.aa\n30:text/x-a:a*z\n",
+        )
+        .unwrap();
+        let searcher = MimeSearcher::with_dirs(std::slice::from_ref(&dir)).unwrap();
+        assert_eq!(
+            searcher.types_for_extension("AA"),
+            [
+                WeightedMatch::new(mime("text/x-a"), 50),
+                WeightedMatch::new(mime("text/x-b"), 40),
+            ]
+        );
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("b.ab")),
+            Some(mime("text/x-a"))
+        );
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("b.ac")),
+            None
+        );
+
+        // Each type is stored once, however many globs claim it
+        let globber = searcher.globber();
+        let spans: std::collections::HashSet<_> = globber
+            .extension_entries
+            .iter()
+            .chain(globber.complex_globs.iter().map(|(_, entry)| entry))
+            .filter(|entry| globber.names.get(entry.mime) == "text/x-a")
+            .map(|entry| entry.mime)
+            .collect();
+        assert_eq!(spans.len(), 1);
+        assert_eq!(
+            globber.indexed_extensions().collect::<Vec<_>>(),
+            ["aa", "ab"]
+        );
+    }
+
+    #[test]
+    fn reload_picks_up_new_types() {
+        let dir = TempDir::new();
+        let user = dir.join("user");
+        std::fs::write(dir.join("globs2"), "50:text/x-old:*.smiold\n").unwrap();
+        let mut searcher = MimeSearcher::with_dirs(&[user.clone(), dir.clone()]).unwrap();
+        searcher.set_lookup_cache_capacity(8);
+        let project = mime("application/x-test-project");
+        let _project = searcher.register_temporary_type(MimeDatabaseModel {
+            suffixes: vec![GlobRecord {
+                pattern: "*.smiproject".to_string(),
+                mime: project.clone(),
+                weight: 50,
+                case_sensitive: false,
+            }],
+            ..Default::default()
+        });
+        let lookup = |searcher: &MimeSearcher, name: &str| {
+            searcher.find_mimetype_from_filepath(Path::new(name))
+        };
+        assert_eq!(lookup(&searcher, "a.smiold"), Some(mime("text/x-old")));
+        assert_eq!(lookup(&searcher, "a.sminew"), None);
+        let derived = searcher.derive(&[]).unwrap();
+
+        // A directory that was empty counts once it has a database
+        std::fs::create_dir_all(&user).unwrap();
+        std::fs::write(user.join("globs2"), "50:text/x-new:*.sminew\n").unwrap();
+        std::fs::write(dir.join("globs2"), "50:text/x-changed:*.smiold\n").unwrap();
+        searcher.reload().unwrap();
+        assert_eq!(searcher.sources().len(), 2);
+        assert_eq!(lookup(&searcher, "a.sminew"), Some(mime("text/x-new")));
+        assert_eq!(lookup(&searcher, "a.smiold"), Some(mime("text/x-changed")));
+        assert_eq!(lookup(&searcher, "a.smiproject"), Some(project.clone()));
+
+        // A derived searcher keeps its parent's databases as they were
+        let mut derived = derived;
+        derived.reload().unwrap();
+        assert_eq!(lookup(&derived, "a.smiold"), Some(mime("text/x-old")));
+        assert_eq!(lookup(&derived, "a.smiproject"), Some(project));
+
+        // A failed reload leaves the searcher working
+        std::fs::write(user.join("globs2"), "not a glob\n").unwrap();
+        assert!(searcher.reload().is_err());
+        assert_eq!(lookup(&searcher, "a.sminew"), Some(mime("text/x-new")));
+    }
+
+    #[test]
+    #[cfg(all(feature = "notify", feature = "globs"))]
+    fn watching_reloads_changed_databases() {
+        let dir = TempDir::new();
+        std::fs::write(dir.join("globs2"), "50:text/x-old:*.smiwatch\n").unwrap();
+        let (reloaded, on_reload) = std::sync::mpsc::channel();
+        let watched = MimeSearcher::with_dirs(std::slice::from_ref(&dir))
+            .unwrap()
+            .watch(move |outcome| {
+                let _ = reloaded.send(outcome);
+            })
+            .unwrap();
+        let lookup = |name: &str| {
+            watched
+                .snapshot()
+                .find_mimetype_from_filepath(Path::new(name))
+        };
+        assert_eq!(lookup("a.smiwatch"), Some(mime("text/x-old")));
+
+        std::fs::write(dir.join("globs2"), "50:text/x-new:*.smiwatch\n").unwrap();
+        let outcome = on_reload.recv_timeout(std::time::Duration::from_secs(10));
+        assert_eq!(outcome, Ok(Ok(())));
+        assert_eq!(lookup("a.smiwatch"), Some(mime("text/x-new")));
+        drop(watched);
+    }
+
+    #[test]
+    fn system_searcher_is_shared() {
+        assert!(std::ptr::eq(system(), system()));
+        let handles: Vec<_> = (0..4)
+            .map(|_| std::thread::spawn(|| system() as *const _ as usize))
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), system() as *const _ as usize);
+        }
+    }
+
+    #[test]
+    fn clones_share_until_changed() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+        let dir = TempDir::new();
+        std::fs::write(dir.join("globs2"), "50:text/x-old:*.smiclone\n").unwrap();
+        let mut searcher = MimeSearcher::with_dirs(std::slice::from_ref(&dir)).unwrap();
+        searcher.set_lookup_cache_capacity(8);
+        assert_send_sync(&searcher);
+        let lookup =
+            |searcher: &MimeSearcher| searcher.find_mimetype_from_filepath(Path::new("a.smiclone"));
+
+        let mut clone = searcher.clone();
+        assert!(Arc::ptr_eq(&clone.databases, &searcher.databases));
+        let worker = std::thread::spawn({
+            let searcher = searcher.clone();
+            move || lookup(&searcher)
+        });
+        assert_eq!(worker.join().unwrap(), Some(mime("text/x-old")));
+
+        // The clone's own type doesn't leak into the original through the shared cache
+        let _handle = clone.register_temporary_type(MimeDatabaseModel {
+            suffixes: vec![GlobRecord {
+                pattern: "*.smiclone".to_string(),
+                mime: mime("text/x-new"),
+                weight: 50,
+                case_sensitive: false,
+            }],
+            ..Default::default()
+        });
+        assert_eq!(lookup(&clone), Some(mime("text/x-new")));
+        assert_eq!(lookup(&searcher), Some(mime("text/x-old")));
+    }
+
+    #[test]
+    fn shared_searcher_swaps_under_snapshots() {
+        let dir = TempDir::new();
+        std::fs::write(dir.join("globs2"), "50:text/x-old:*.smishared\n").unwrap();
+        let searcher = MimeSearcher::with_dirs(std::slice::from_ref(&dir)).unwrap();
+        let shared = Arc::new(SharedMimeSearcher::new(searcher));
+        let lookup = |searcher: &MimeSearcher| {
+            searcher.find_mimetype_from_filepath(Path::new("a.smishared"))
+        };
+        let before = shared.snapshot();
+
+        std::fs::write(dir.join("globs2"), "50:text/x-new:*.smishared\n").unwrap();
+        let reloader = std::thread::spawn({
+            let shared = shared.clone();
+            move || shared.reload()
+        });
+        reloader.join().unwrap().unwrap();
+        assert_eq!(lookup(&shared.snapshot()), Some(mime("text/x-new")));
+        assert_eq!(lookup(&before), Some(mime("text/x-old")));
+
+        // A failed reload keeps the current searcher, where the bundled database doesn't stand in
+        #[cfg(not(feature = "bundled"))]
+        {
+            std::fs::write(dir.join("globs2"), "not a glob\n").unwrap();
+            assert!(shared.reload().is_err());
+            assert_eq!(lookup(&shared.snapshot()), Some(mime("text/x-new")));
+        }
+        let replaced = shared.replace(before);
+        assert_eq!(lookup(&replaced), Some(mime("text/x-new")));
+        assert_eq!(lookup(&shared.snapshot()), Some(mime("text/x-old")));
+    }
+
+    #[test]
+    fn slow_sniffing_is_aborted() {
+        // Hands out a byte at a time, slowly
+        struct Trickle<'a>(&'a [u8]);
+        impl std::io::Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                std::thread::sleep(std::time::Duration::from_millis(2));
+                let n = self.0.len().min(buf.len()).min(1);
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+        let searcher = MimeSearcher::builder()
+            .sniff_timeout(std::time::Duration::from_millis(20))
+            .build()
+            .unwrap();
+        let mut body = b"%PDF-1.7\n".to_vec();
+        body.resize(searcher.max_magic_extent(), b' ');
+        match searcher.sniff_reader(Trickle(&body)) {
+            Err(Error::SniffAborted { read }) => assert!(read > 0 && read < body.len()),
+            other => panic!("sniffing wasn't aborted: {other:?}"),
+        }
+        // Fast reads finish in time, and a name that settles the type needs none
+        assert_eq!(
+            searcher.sniff_reader(&body[..]).unwrap().mime,
+            mime("application/pdf")
+        );
+        let guess = searcher.sniff_file("/nonexistent/report.pdf").unwrap();
+        assert_eq!(guess.mime, mime("application/pdf"));
+
+        // Other lookups reading files give up the same way
+        let dir = TempDir::new();
+        let short = dir.join("short");
+        std::fs::write(&short, b"%PDF-1.7\n").unwrap();
+        let impatient = MimeSearcher::builder()
+            .sniff_timeout(std::time::Duration::ZERO)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            impatient.explain(&short),
+            Err(Error::SniffAborted { read: 9 })
+        ));
+        assert_eq!(impatient.find_mimetypes_for_paths(&[short]), [None]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn non_regular_files_are_not_read() {
+        let dir = TempDir::new();
+        let socket = dir.join("socket");
+        let _listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+        let searcher = MimeSearcher::new().unwrap();
+
+        let inode = |name: &str| Guess::new(mime(name), DetectionMethod::Inode);
+        assert_eq!(searcher.sniff_file(&socket).unwrap(), inode("inode/socket"));
+        assert_eq!(searcher.sniff_file(&dir).unwrap(), inode("inode/directory"));
+        let explanation = searcher.explain(&socket).unwrap();
+        assert_eq!(explanation.guess, inode("inode/socket"));
+        assert_eq!(
+            explanation.steps,
+            [Step::NotRegularFile {
+                mime: mime("inode/socket")
+            }]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn results_round_trip_through_serde() {
+        let guess = Guess {
+            overruled: Some(Evidence::new(mime("text/plain"), DetectionMethod::Glob, 50)),
+            certain: false,
+            ..Guess::new(MimeType::APPLICATION_PDF, DetectionMethod::Magic)
+        };
+        let json = serde_json::to_value(&guess).unwrap();
+        assert_eq!(json["mime"], "application/pdf");
+        assert_eq!(json["overruled"]["method"], "Glob");
+        assert_eq!(serde_json::from_value::<Guess>(json).unwrap(), guess);
+
+        let model = MimeDatabaseModel {
+            aliases: vec![(mime("application/x-pdf"), MimeType::APPLICATION_PDF)],
+            suffixes: vec![GlobRecord {
+                pattern: "*.pdf".to_string(),
+                mime: MimeType::APPLICATION_PDF,
+                weight: 50,
+                case_sensitive: false,
+            }],
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&model).unwrap();
+        assert_eq!(
+            serde_json::from_str::<MimeDatabaseModel>(&json).unwrap(),
+            model
+        );
+
+        assert!(serde_json::from_str::<MimeType>("\"not a type\"").is_err());
+    }
+
+    #[test]
+    fn snapshots_answer_as_the_merged_databases() {
+        let test_type = mime("application/x-test");
+        let glob = |pattern: &str, mime: &MimeType| GlobRecord {
+            pattern: pattern.to_string(),
+            mime: mime.clone(),
+            weight: 50,
+            case_sensitive: false,
+        };
+        let overrides = MimeDatabaseModel {
+            aliases: vec![(mime("application/x-test-alias"), test_type.clone())],
+            suffixes: vec![glob("*.pdf", &test_type)],
+            globs: vec![glob(NO_GLOBS_MARKER, &mime("image/png"))],
+            ..Default::default()
+        };
+        let searcher = MimeSearcher::builder()
+            .data_dirs([system_dir()])
+            .add_database(overrides)
+            .build()
+            .unwrap();
+        let snapshot = searcher.export_snapshot().unwrap();
+        let pinned = MimeSearcher::from_snapshot(snapshot).unwrap();
+
+        assert!(pinned.sources().is_empty());
+        for name in ["a.pdf", "a.png", "a.txt", "a.tar.gz"] {
+            assert_eq!(
+                pinned.find_mimetype_from_filepath(Path::new(name)),
+                searcher.find_mimetype_from_filepath(Path::new(name)),
+                "{name}"
+            );
+        }
+        assert_eq!(
+            pinned.find_mimetype_from_filepath(Path::new("a.pdf")),
+            Some(test_type.clone())
+        );
+        assert_eq!(pinned.find_mimetype_from_filepath(Path::new("a.png")), None);
+        assert_eq!(
+            pinned.find_mimetype_from_data(b"\x89PNG\r\n\x1a\n"),
+            Ok(Some(mime("image/png")))
+        );
+        assert!(pinned.types_equal(&mime("application/x-test-alias"), &test_type));
+        assert!(pinned.types_equal(&mime("application/x-pdf"), &MimeType::APPLICATION_PDF));
+    }
+
+    #[test]
+    #[cfg(all(feature = "mime_guess", not(feature = "bundled")))]
+    fn missing_database_falls_back_to_mime_guess() {
+        let dir = TempDir::new();
+        let missing = dir.join("missing");
+        let builder = || MimeSearcher::builder().data_dirs([missing.clone()]);
+        assert_eq!(builder().build().unwrap_err(), Error::MimeCacheNotFound);
+
+        let searcher = builder().with_mime_guess_fallback(true).build().unwrap();
+        let guess = searcher.guess(Path::new("a.pdf"), b"%PDF-1.7\n").unwrap();
+        assert_eq!(guess.mime, MimeType::APPLICATION_PDF);
+        assert_eq!(guess.method, DetectionMethod::ExtensionTable);
+        assert!(!guess.certain);
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("a.png")),
+            Some(mime("image/png"))
+        );
+        let readme = searcher.guess(Path::new("README"), b"hello\n").unwrap();
+        assert_eq!(readme.method, DetectionMethod::Fallback);
+
+        // Any database, even one that doesn't know the name, stands in for the table
+        let searcher = builder()
+            .with_mime_guess_fallback(true)
+            .add_database(MimeDatabaseModel::default())
+            .build()
+            .unwrap();
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("a.png")),
+            None
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "bundled")]
+    fn bundled_database_stands_in_for_a_missing_one() {
+        let dir = TempDir::new();
+        let searcher = MimeSearcher::with_dirs(&[dir.join("missing")]).unwrap();
+        assert!(searcher.sources().is_empty());
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("a.pdf")),
+            Some(MimeType::APPLICATION_PDF)
+        );
+        assert_eq!(
+            searcher.find_mimetype_from_data(b"\x89PNG\r\n\x1a\n"),
+            Ok(Some(mime("image/png")))
+        );
+        let installed = MimeSearcher::with_dirs(&[system_dir()]).unwrap();
+        assert_eq!(installed.sources().len(), 1);
+        assert_eq!(
+            MimeSearcher::bundled()
+                .unwrap()
+                .find_mimetype_from_filepath(Path::new("a.pdf")),
+            Some(MimeType::APPLICATION_PDF)
+        );
+    }
+
+    #[test]
+    fn added_database_overrides_system() {
+        let test_type = mime("application/x-test");
+        let overrides = MimeDatabaseModel {
+            aliases: vec![(mime("application/x-test-alias"), test_type.clone())],
+            parents: vec![(test_type.clone(), vec![mime("application/pdf")])],
+            suffixes: vec![GlobRecord {
+                pattern: "*.pdf".to_string(),
+                mime: test_type.clone(),
+                weight: 50,
+                case_sensitive: false,
+            }],
+            magic: vec![MagicMatch {
+                priority: 90,
+                mime: test_type.clone(),
+                matchlets: vec![Matchlet {
+                    range_start: 0,
+                    range_length: 1,
+                    word_size: 1,
+                    value: b"%PDF".to_vec(),
+                    mask: None,
+                    children: Vec::new(),
+                }],
+            }],
+            generic_icons: vec![(test_type.clone(), "x-test".to_string())],
+            ..Default::default()
+        };
+        let searcher = MimeSearcher::builder()
+            .data_dirs([system_dir()])
+            .add_database(overrides)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("a.pdf")),
+            Some(test_type.clone())
+        );
+        assert_eq!(
+            searcher.find_mimetype_from_data(b"%PDF-1.7\n"),
+            Ok(Some(test_type.clone()))
+        );
+        assert_eq!(
+            searcher.find_icon_for_mimetype(test_type.clone()),
+            Ok("x-test".to_string())
+        );
+        assert!(searcher.types_equal(&mime("application/x-test-alias"), &test_type));
+        assert_eq!(
+            searcher
+                .ancestry(&mime("application/x-test-alias"))
+                .unwrap()[..2],
+            [test_type.clone(), mime("application/pdf")]
+        );
+        // Types the added database doesn't define still come from the system one
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("a.png")),
+            Some(mime("image/png"))
+        );
+    }
+
+    #[test]
+    fn every_broken_database_is_reported() {
+        let root = TempDir::new();
+        let (cache_dir, globs2_dir) = (root.join("cache"), root.join("globs2"));
+        std::fs::create_dir_all(&cache_dir).unwrap();
+        std::fs::create_dir_all(&globs2_dir).unwrap();
+        std::fs::write(cache_dir.join("mime.cache"), b"junk").unwrap();
+        std::fs::write(globs2_dir.join("globs2"), "50:text/plain:*.txt\nbad line\n").unwrap();
+
+        // The bundled database stands in for them, leaving the errors as warnings
+        #[cfg(feature = "bundled")]
+        {
+            let searcher = MimeSearcher::with_dirs(&[cache_dir, globs2_dir]).unwrap();
+            assert_eq!(
+                searcher.warnings(),
+                [
+                    Warning::DatabaseUnreadable(Error::MissingHeader.to_string()),
+                    Warning::DatabaseUnreadable(
+                        Error::Globs2BadLine("bad line".to_string()).to_string()
+                    ),
+                ]
+            );
+        }
+        #[cfg(not(feature = "bundled"))]
+        {
+            let err = MimeSearcher::with_dirs(&[cache_dir.clone(), globs2_dir]).unwrap_err();
+            assert_eq!(
+                err,
+                Error::Multiple(vec![
+                    Error::MissingHeader,
+                    Error::Globs2BadLine("bad line".to_string())
+                ])
+            );
+            assert!(err.is_parse_failure());
+            assert_eq!(
+                err.to_string(),
+                "2 database files failed to load; mime.cache is too short to have a header; \
+                 malformed globs2 line `bad line`"
+            );
+
+            // A single failure is reported as itself
+            let err = MimeSearcher::with_dirs(&[cache_dir]).unwrap_err();
+            assert_eq!(err, Error::MissingHeader);
+            assert_eq!(err.errors(), [Error::MissingHeader]);
+        }
+    }
+
+    #[test]
+    fn stale_cache_is_reported() {
+        let dir = TempDir::new();
+        std::fs::create_dir_all(dir.join("packages")).unwrap();
+        let cache = std::fs::File::create(dir.join("mime.cache")).unwrap();
+        std::io::Write::write_all(&mut &cache, &MimeDatabaseModel::default().to_cache_bytes())
+            .unwrap();
+        cache
+            .set_modified(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap();
+
+        let searcher = MimeSearcher::with_dirs(std::slice::from_ref(&dir)).unwrap();
+        assert_eq!(searcher.warnings(), [Warning::DatabaseStale(dir.clone())]);
+
+        cache.set_modified(std::time::SystemTime::now()).unwrap();
+        let searcher = MimeSearcher::with_dirs(std::slice::from_ref(&dir)).unwrap();
+        assert_eq!(searcher.warnings(), []);
+    }
+
+    #[test]
+    fn errors_describe_their_cause() {
+        use std::error::Error as _;
+
+        let dir = TempDir::new();
+        std::fs::create_dir_all(dir.join("mime.cache")).unwrap();
+        let unreadable = MimeCache::load(&dir).unwrap_err();
+
+        assert!(matches!(unreadable, Error::Io(_)));
+        assert!(!unreadable.is_not_found() && !unreadable.is_parse_failure());
+        assert!(unreadable.source().is_some());
+        assert!(unreadable.to_string().contains("mime.cache"));
+
+        // The bundled database stands in for a missing one
+        #[cfg(not(feature = "bundled"))]
+        {
+            let missing = MimeSearcher::with_dirs(&[dir.join("missing")]).unwrap_err();
+            assert!(missing.is_not_found());
+            assert_eq!(missing.to_string(), "no mime database found");
+        }
+
+        let truncated = MimeCache::from_storage(vec![0; 4]).unwrap_err();
+        assert!(truncated.is_parse_failure());
+    }
+
+    #[test]
+    fn searcher_without_globs2() {
+        let dir = TempDir::new();
+        std::fs::write(dir.join("mime.cache"), system_cache().bytes()).unwrap();
+
+        let searcher = MimeSearcher::with_dirs(std::slice::from_ref(&dir)).unwrap();
+
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("foo.pdf")),
+            Some(mime("application/pdf"))
+        );
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("baz.md")),
+            Some(mime("text/markdown"))
+        );
+    }
+
+    #[test]
+    fn user_database_overrides_system() {
+        let dir = TempDir::new();
+
+        let model = MimeDatabaseModel {
+            magic: vec![MagicMatch {
+                priority: 50,
+                mime: mime("application/pdf"),
+                matchlets: vec![Matchlet {
+                    range_start: 0,
+                    range_length: 1,
+                    word_size: 1,
+                    value: b"XPDF".to_vec(),
+                    mask: None,
+                    children: Vec::new(),
+                }],
+            }],
+            ..Default::default()
+        };
+        std::fs::write(dir.join("mime.cache"), model.to_cache_bytes()).unwrap();
+        std::fs::write(dir.join("globs2"), "10:application/x-user-pdf:*.pdf\n").unwrap();
+
+        let searcher = MimeSearcher::with_dirs(&[dir.clone(), system_dir()]).unwrap();
+
+        // The user glob wins despite its lower weight
+        assert_eq!(
+            searcher.types_for_extension("pdf"),
+            vec![WeightedMatch {
+                mime: mime("application/x-user-pdf"),
+                weight: 10
+            }]
+        );
+        // And the system's magic for application/pdf is replaced by the user's
+        assert_eq!(
+            searcher.find_mimetype_from_data(b"XPDF"),
+            Ok(Some(mime("application/pdf")))
+        );
+        assert_ne!(
+            searcher.find_mimetype_from_data(b"%PDF-1.7\n"),
+            Ok(Some(mime("application/pdf")))
+        );
+    }
+
+    #[test]
+    fn drag_and_drop_targets() {
+        let dir = TempDir::new();
+        let model = MimeDatabaseModel {
+            aliases: vec![(mime("text/x-old-python"), mime("text/x-python"))],
+            parents: vec![(mime("text/x-python"), vec![mime("text/plain")])],
+            ..Default::default()
+        };
+        std::fs::write(dir.join("mime.cache"), model.to_cache_bytes()).unwrap();
+        let searcher = MimeSearcher::with_dirs(std::slice::from_ref(&dir)).unwrap();
+
+        let offered = [
+            mime("image/png"),
+            mime("text/x-python"),
+            mime("text/uri-list"),
+        ];
+        assert_eq!(
+            searcher.common_targets(&offered, &["text/plain"]),
+            [mime("text/x-python")]
+        );
+        assert_eq!(
+            searcher.common_targets(&offered, &["text/x-old-python", "image/*"]),
+            [mime("image/png"), mime("text/x-python")]
+        );
+        assert_eq!(searcher.common_targets(&offered, &["*/*"]), offered);
+        assert_eq!(searcher.common_targets(&offered, &["audio/*"]), []);
+
+        assert!(searcher.types_equal(&mime("text/x-old-python"), &mime("text/x-python")));
+        assert!(searcher.types_equal(&mime("text/x-python"), &mime("Text/X-Python")));
+        assert!(!searcher.types_equal(&mime("text/x-python"), &mime("text/plain")));
+    }
+
+    #[test]
+    fn derived_searcher_layers_over_parent() {
+        let dir = TempDir::new();
+        let model = MimeDatabaseModel {
+            generic_icons: vec![(mime("application/x-plugin"), "plugin-icon".into())],
+            ..Default::default()
+        };
+        std::fs::write(dir.join("mime.cache"), model.to_cache_bytes()).unwrap();
+        std::fs::write(
+            dir.join("globs2"),
+            "50:application/x-plugin:*.plug\n0:application/pdf:__NOGLOBS__\n",
+        )
+        .unwrap();
+
+        let parent = MimeSearcher::with_dirs(&[system_dir()]).unwrap();
+        let child = parent.derive(std::slice::from_ref(&dir)).unwrap();
+
+        let lookup = |searcher: &MimeSearcher, name: &str| {
+            searcher.find_mimetype_from_filepath(Path::new(name))
+        };
+        assert_eq!(lookup(&child, "a.plug"), Some(mime("application/x-plugin")));
+        assert_eq!(lookup(&child, "a.pdf"), None);
+        assert_eq!(lookup(&child, "a.png"), Some(mime("image/png")));
+        assert_eq!(lookup(&parent, "a.plug"), None);
+        assert_eq!(lookup(&parent, "a.pdf"), Some(mime("application/pdf")));
+
+        assert_eq!(
+            child.find_icon_for_mimetype(mime("application/x-plugin")),
+            Ok("plugin-icon".to_string())
+        );
+        assert_eq!(
+            child.find_mimetype_from_data(b"%PDF-1.7\n"),
+            Ok(Some(mime("application/pdf")))
+        );
+    }
+
+    #[test]
+    fn deletion_markers() {
+        let dir = TempDir::new();
+
+        let model = MimeDatabaseModel {
+            magic: vec![MagicMatch {
+                priority: 50,
+                mime: mime("application/pdf"),
+                matchlets: vec![Matchlet {
+                    range_start: 0,
+                    range_length: 1,
+                    word_size: 1,
+                    value: b"__NOMAGIC__".to_vec(),
+                    mask: None,
+                    children: Vec::new(),
+                }],
+            }],
+            ..Default::default()
+        };
+        std::fs::write(dir.join("mime.cache"), model.to_cache_bytes()).unwrap();
+        std::fs::write(
+            dir.join("globs2"),
+            "0:application/pdf:__NOGLOBS__\n50:application/pdf:*.xpdf\n",
+        )
+        .unwrap();
+
+        let searcher = MimeSearcher::with_dirs(&[dir.clone(), system_dir()]).unwrap();
+
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("a.xpdf")),
+            Some(mime("application/pdf"))
+        );
+        assert_ne!(
+            searcher.find_mimetype_from_filepath(Path::new("a.pdf")),
+            Some(mime("application/pdf"))
+        );
+        assert_ne!(
+            searcher.find_mimetype_from_data(b"%PDF-1.7\n"),
+            Ok(Some(mime("application/pdf")))
+        );
+        assert_ne!(
+            searcher.find_mimetype_from_data(b"__NOMAGIC__"),
+            Ok(Some(mime("application/pdf")))
+        );
+    }
+
+    #[test]
+    fn searcher_without_cache() {
+        let dir = TempDir::new();
+        std::fs::write(dir.join("globs2"), "50:application/x-test:*.smitest\n").unwrap();
+
+        let searcher = MimeSearcher::with_dirs(std::slice::from_ref(&dir)).unwrap();
+
+        assert_eq!(
+            searcher.find_mimetype_from_filepath(Path::new("a.smitest")),
+            Some(mime("application/x-test"))
+        );
+        assert_eq!(
+            searcher.find_icon_for_mimetype(mime("application/x-test")),
+            Err(Error::NoIconFound)
+        );
+        assert_eq!(searcher.find_mimetype_from_data(b"%PDF-1.7\n"), Ok(None));
+        assert_eq!(searcher.max_magic_extent(), 0);
+        assert_eq!(searcher.to_model(), Err(Error::MimeCacheNotFound));
+    }
+
+    #[test]
+    fn model_subset() {
+        let glob = |pattern: &str, name: &str| GlobRecord {
+            pattern: pattern.to_string(),
+            mime: mime(name),
+            weight: 50,
+            case_sensitive: false,
+        };
+        let model = MimeDatabaseModel {
+            aliases: vec![
+                (mime("text/x-old-python"), mime("text/x-python")),
+                (mime("image/x-png"), mime("image/png")),
+            ],
+            parents: vec![
+                (mime("text/plain"), vec![mime("application/octet-stream")]),
+                (mime("text/x-python"), vec![mime("text/plain")]),
+            ],
+            suffixes: vec![
+                glob("*.png", "image/png"),
+                glob("*.py", "text/x-python"),
+                glob("*.txt", "text/plain"),
+            ],
+            generic_icons: vec![
+                (mime("image/png"), "image-x-generic".into()),
+                (mime("text/x-python"), "text-x-script".into()),
+            ],
+            ..Default::default()
+        };
+
+        let subset = model.extract_subset(&[mime("text/x-old-python")]);
+        assert_eq!(
+            subset,
+            MimeDatabaseModel {
+                aliases: vec![(mime("text/x-old-python"), mime("text/x-python"))],
+                parents: model.parents.clone(),
+                suffixes: vec![glob("*.py", "text/x-python"), glob("*.txt", "text/plain")],
+                generic_icons: vec![(mime("text/x-python"), "text-x-script".into())],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn model_roundtrip() {
+        let original = system_cache();
+        let model = MimeDatabaseModel::from_cache_bytes(original.bytes()).unwrap();
+
+        let compiled = model.to_cache_bytes();
+        assert_eq!(
+            MimeDatabaseModel::from_cache_bytes(&compiled),
+            Ok(model.clone())
+        );
+        assert_eq!(
+            MimeDatabaseModel::from_cache_bytes(&compiled)
+                .unwrap()
+                .to_cache_bytes(),
+            compiled
+        );
+
+        let recompiled = MimeCache::from_storage(compiled).unwrap();
+        for (mime, _) in &model.generic_icons {
+            assert_eq!(
+                recompiled.find_icon_for_mimetype(mime.clone()),
+                original.find_icon_for_mimetype(mime.clone())
+            );
+        }
+        assert_eq!(
+            recompiled.magic_lookup(&mut &b"%PDF-1.7\n"[..], &|_| Ok(false)),
+            original.magic_lookup(&mut &b"%PDF-1.7\n"[..], &|_| Ok(false))
+        );
+    }
 }
@@ -0,0 +1,132 @@
+//! Enumerating the types the loaded databases know about.
+
+#[cfg(feature = "icons")]
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+#[cfg(feature = "icons")]
+use crate::{CacheSection, MimeDatabaseModel};
+use crate::{Error, MimeSearcher, MimeType};
+#[cfg(feature = "globs")]
+use crate::{GlobRecord, Globber};
+
+impl MimeSearcher {
+    /// Every canonical type mentioned anywhere in the loaded databases, sorted by name.
+    ///
+    /// Neither mime.cache nor globs2 has a list of types, so this collects the types of every
+    /// glob, magic rule, namespace, icon, alias and subclass entry. Aliases themselves are left
+    /// out.
+    pub(crate) fn known_types(&self) -> Result<BTreeSet<MimeType>, Error> {
+        let mut types = BTreeSet::new();
+        let mut aliases = BTreeSet::new();
+        #[cfg(feature = "globs")]
+        for layer in self.globber().layers() {
+            types.extend(layer.indexed_types().map(|mime| mime.to_mime_type()));
+        }
+        for database in self.databases.iter() {
+            let model = database.to_model()?;
+            for (alias, mime) in model.aliases {
+                aliases.insert(alias);
+                types.insert(mime);
+            }
+            for (mime, parents) in model.parents {
+                types.insert(mime);
+                types.extend(parents);
+            }
+            let globs = [model.literals, model.suffixes, model.globs]
+                .into_iter()
+                .flatten();
+            types.extend(globs.map(|glob| glob.mime));
+            types.extend(model.magic.into_iter().map(|m| m.mime));
+            types.extend(model.namespaces.into_iter().map(|ns| ns.mime));
+            let icons = model.icons.into_iter().chain(model.generic_icons);
+            types.extend(icons.map(|(mime, _)| mime));
+        }
+        Ok(&types - &aliases)
+    }
+
+    /// Every glob defined by the loaded databases and globs2 files, as `(pattern, type, weight,
+    /// case sensitivity)` records sorted by pattern, then type, then weight. A glob found in
+    /// both a mime.cache and the globs2 next to it is listed once. Only the index built from
+    /// globs2 files is kept after loading, so they are read again.
+    ///
+    /// These are the definitions as installed rather than what lookups see: globs hidden by the
+    /// same pattern in a more important database are listed too, and so are the `__NOGLOBS__`
+    /// markers hiding them, so that tools auditing or exporting the database see everything.
+    /// Fails with the error of the first database whose globs can't be read.
+    #[cfg(feature = "globs")]
+    pub fn all_globs(&self) -> Result<Vec<GlobRecord>, Error> {
+        let mut globs = BTreeSet::new();
+        for database in self.databases.iter() {
+            globs.extend(database.globs()?);
+        }
+        for path in self
+            .sources
+            .iter()
+            .filter_map(|source| source.globs2.as_ref())
+        {
+            let globs2 = std::fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+            globs.extend(Globber::globs2_records(&globs2)?);
+        }
+        Ok(globs.into_iter().collect())
+    }
+
+    /// Every type given an icon of its own, with the icon's name, sorted by type. Few databases
+    /// have any, as icon themes usually name icons after the type.
+    ///
+    /// Where several databases list a type the most important one wins, as for lookups. Empty
+    /// if the searcher was built without icons.
+    #[cfg(feature = "icons")]
+    pub fn all_icons(&self) -> Result<Vec<(MimeType, String)>, Error> {
+        self.collect_icons(CacheSection::IconsList, |model| model.icons)
+    }
+
+    /// Every type given a generic icon, with the icon's name, sorted by type. These are what
+    /// [`find_icon_for_mimetype`](Self::find_icon_for_mimetype) answers with, and the same
+    /// rules apply as for [`all_icons`](Self::all_icons).
+    #[cfg(feature = "icons")]
+    pub fn all_generic_icons(&self) -> Result<Vec<(MimeType, String)>, Error> {
+        self.collect_icons(CacheSection::GenericIconsList, |model| model.generic_icons)
+    }
+
+    #[cfg(feature = "icons")]
+    fn collect_icons(
+        &self,
+        section: CacheSection,
+        list: impl Fn(MimeDatabaseModel) -> Vec<(MimeType, String)>,
+    ) -> Result<Vec<(MimeType, String)>, Error> {
+        if !self.options.icons {
+            return Ok(Vec::new());
+        }
+        let mut icons = BTreeMap::new();
+        for database in self.databases.iter() {
+            for (mime, icon) in list(database.to_model_of(&[section])?) {
+                icons.entry(mime).or_insert(icon);
+            }
+        }
+        Ok(icons.into_iter().collect())
+    }
+
+    /// Every known type of the media class `media`, such as every `image/*` type for `image`,
+    /// sorted by name. The class is compared ignoring case.
+    ///
+    /// Types are gathered as for [`list_types_matching`](Self::list_types_matching), from the
+    /// globs, magic, namespaces, icons, aliases and subclasses the databases define.
+    pub fn types_in_media(&self, media: &str) -> Result<Vec<MimeType>, Error> {
+        Ok(self
+            .known_types()?
+            .into_iter()
+            .filter(|mime| mime.media().eq_ignore_ascii_case(media))
+            .collect())
+    }
+
+    /// Every known type matching `pattern`, sorted by name. Patterns are as for
+    /// [`MimeType::matches`], e.g. `image/*`.
+    pub fn list_types_matching(&self, pattern: &str) -> Result<Vec<MimeType>, Error> {
+        Ok(self
+            .known_types()?
+            .into_iter()
+            .filter(|mime| mime.matches(pattern))
+            .collect())
+    }
+}
@@ -0,0 +1,112 @@
+//! Small caches of recent lookups, for callers that ask about the same few types over and over.
+
+#[cfg(feature = "globs")]
+use std::path::Path;
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    hash::Hash,
+    sync::{Mutex, PoisonError},
+};
+
+#[cfg(feature = "icons")]
+use crate::Error;
+use crate::MimeType;
+
+/// A map holding at most `capacity` entries, evicting the least recently used one to make room.
+///
+/// Eviction scans every entry, which is cheap at the sizes this is meant for.
+#[derive(Debug)]
+pub(crate) struct Lru<K, V> {
+    capacity: usize,
+    entries: Mutex<LruEntries<K, V>>,
+}
+
+#[derive(Debug)]
+struct LruEntries<K, V> {
+    /// Each value with the time it was last used.
+    values: HashMap<K, (V, u64)>,
+    clock: u64,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Lru<K, V> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Lru {
+            capacity,
+            entries: Mutex::new(LruEntries {
+                values: HashMap::new(),
+                clock: 0,
+            }),
+        }
+    }
+
+    pub(crate) fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.clock += 1;
+        let now = entries.clock;
+        let (value, used) = entries.values.get_mut(key)?;
+        *used = now;
+        Some(value.clone())
+    }
+
+    pub(crate) fn insert(&self, key: K, value: V) {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.clock += 1;
+        let now = entries.clock;
+        if entries.values.len() >= self.capacity && !entries.values.contains_key(&key) {
+            let oldest = entries
+                .values
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.values.remove(&oldest);
+            }
+        }
+        entries.values.insert(key, (value, now));
+    }
+}
+
+/// The recent lookups a searcher remembers, once turned on with
+/// [`set_lookup_cache_capacity`](crate::MimeSearcher::set_lookup_cache_capacity).
+#[derive(Debug)]
+pub(crate) struct LookupCache {
+    capacity: usize,
+    /// Types found by extension, keyed by the lowercased part of the file name from its first
+    /// dot on, such as `tar.gz`.
+    #[cfg(feature = "globs")]
+    pub(crate) names: Lru<String, MimeType>,
+    #[cfg(feature = "icons")]
+    pub(crate) icons: Lru<MimeType, Result<String, Error>>,
+}
+
+impl LookupCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        LookupCache {
+            capacity,
+            #[cfg(feature = "globs")]
+            names: Lru::new(capacity),
+            #[cfg(feature = "icons")]
+            icons: Lru::new(capacity),
+        }
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+/// The part of the file name of `path` that decides which `*.ext` glob it matches: everything
+/// from the first dot on, with dots leading the name skipped as they don't start an extension.
+/// Case is kept, as case-sensitive globs tell `.C` from `.c`. `None` for names without one.
+#[cfg(feature = "globs")]
+pub(crate) fn extension_key(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_string_lossy();
+    let stem = name.trim_start_matches('.');
+    let (_, extensions) = stem.split_once('.')?;
+    Some(extensions.to_string())
+}
@@ -0,0 +1,243 @@
+//! Content sniffing against the magic list stored in mime.cache.
+
+use crate::{
+    CacheSection, Corruption, Error, MagicMatch, Matchlet, MimeCache, MimeTypeRef, NO_MAGIC_MARKER,
+    range::MagicSource,
+    reader::{CacheReader, MAX_NESTING},
+};
+
+// MagicList:
+// 4			CARD32		N_MATCHES
+// 4			CARD32		MAX_EXTENT
+// 4			CARD32		FIRST_MATCH_OFFSET
+//
+// Match:
+// 4			CARD32		PRIORITY
+// 4			CARD32		MIME_TYPE_OFFSET
+// 4			CARD32		N_MATCHLETS
+// 4			CARD32		FIRST_MATCHLET_OFFSET
+//
+// Matchlet:
+// 4			CARD32		RANGE_START
+// 4			CARD32		RANGE_LENGTH
+// 4			CARD32		WORD_SIZE
+// 4			CARD32		VALUE_LENGTH
+// 4			CARD32		VALUE
+// 4			CARD32		MASK (0 if no mask)
+// 4			CARD32		N_CHILDREN
+// 4			CARD32		FIRST_CHILD_OFFSET
+const MATCH_STRIDE: usize = 16;
+const MATCHLET_STRIDE: usize = 32;
+
+impl MimeCache {
+    /// The number of bytes any magic rule can look at, as recorded by update-mime-database.
+    pub(crate) fn magic_max_extent(&self) -> Result<usize, Error> {
+        let start = self.cache_header.magic_list_offset as usize;
+        self.reader(CacheSection::MagicList).usize(start + 4)
+    }
+
+    /// Finds the highest priority magic match for `data`, ignoring matches for types that
+    /// `overridden` returns true for.
+    ///
+    /// Matches are stored sorted by descending priority, so the first one that fires wins.
+    pub(crate) fn magic_lookup(
+        &self,
+        data: &mut dyn MagicSource,
+        overridden: &dyn Fn(&str) -> Result<bool, Error>,
+    ) -> Result<Option<(MimeTypeRef<'_>, u32)>, Error> {
+        let cache = self.reader(CacheSection::MagicList);
+        let start = self.cache_header.magic_list_offset as usize;
+
+        let num_matches = cache.usize(start)?;
+        let first_match = cache.usize(start + 8)?;
+
+        for i in 0..num_matches {
+            let cache = cache.entry(i);
+            let ptr = cache.nth(first_match, i, MATCH_STRIDE)?;
+
+            let priority = cache.u32(ptr)?;
+            let num_matchlets = cache.usize(ptr + 8)?;
+            let first_matchlet = cache.usize(ptr + 12)?;
+
+            if is_no_magic_marker(cache, num_matchlets, first_matchlet)? {
+                continue;
+            }
+            if any_matchlet_matches(cache, num_matchlets, first_matchlet, data, 0)? {
+                let mime = cache.cstr_at(ptr + 4)?;
+                if !overridden(mime)? {
+                    return Ok(Some((MimeTypeRef::unchecked(mime), priority)));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Whether this cache has any magic rules for `mime`.
+    pub(crate) fn has_magic_for(&self, mime: &str) -> Result<bool, Error> {
+        let cache = self.reader(CacheSection::MagicList);
+        let start = self.cache_header.magic_list_offset as usize;
+
+        let num_matches = cache.usize(start)?;
+        let first_match = cache.usize(start + 8)?;
+        for i in 0..num_matches {
+            let ptr = cache.nth(first_match, i, MATCH_STRIDE)?;
+            if cache.entry(i).cstr_at(ptr + 4)? == mime {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+fn is_no_magic_marker(
+    cache: CacheReader<'_>,
+    num_matchlets: usize,
+    first_matchlet: usize,
+) -> Result<bool, Error> {
+    if num_matchlets != 1 {
+        return Ok(false);
+    }
+    let value_length = cache.usize(first_matchlet + 12)?;
+    let value_offset = cache.usize(first_matchlet + 16)?;
+    Ok(cache.bytes(value_offset, value_length)? == NO_MAGIC_MARKER)
+}
+
+fn any_matchlet_matches(
+    cache: CacheReader<'_>,
+    count: usize,
+    first: usize,
+    data: &mut dyn MagicSource,
+    depth: usize,
+) -> Result<bool, Error> {
+    if depth > MAX_NESTING {
+        return Err(cache.corrupt(first, Corruption::TooDeep));
+    }
+    for i in 0..count {
+        let ptr = cache.nth(first, i, MATCHLET_STRIDE)?;
+        if matchlet_matches(cache, ptr, data, depth)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// A matchlet matches if its value is found anywhere in its range and, when it has children,
+// at least one of them matches as well.
+fn matchlet_matches(
+    cache: CacheReader<'_>,
+    ptr: usize,
+    data: &mut dyn MagicSource,
+    depth: usize,
+) -> Result<bool, Error> {
+    let range_start = cache.usize(ptr)?;
+    let range_length = cache.usize(ptr + 4)?;
+    let value_length = cache.usize(ptr + 12)?;
+    let value_offset = cache.usize(ptr + 16)?;
+    let mask_offset = cache.usize(ptr + 20)?;
+    let num_children = cache.usize(ptr + 24)?;
+    let first_child = cache.usize(ptr + 28)?;
+
+    let value = cache.bytes(value_offset, value_length)?;
+    let mask = match mask_offset {
+        0 => None,
+        offset => Some(cache.bytes(offset, value_length)?),
+    };
+
+    let found = found_in_range(data, range_start, range_length, value, mask)?;
+
+    Ok(found
+        && (num_children == 0
+            || any_matchlet_matches(cache, num_children, first_child, data, depth + 1)?))
+}
+
+// Whether `value` is at any of the `range_length` offsets from `range_start`, comparing only
+// the bits set in `mask`. Only the bytes the range covers are asked of `data`.
+//
+// Unmasked values are found with memmem. Masked ones use memchr on an anchor byte the mask
+// keeps in full, then a word-wise masked compare of the window at each hit.
+fn found_in_range(
+    data: &mut dyn MagicSource,
+    range_start: usize,
+    range_length: usize,
+    value: &[u8],
+    mask: Option<&[u8]>,
+) -> Result<bool, Error> {
+    if range_length == 0 {
+        return Ok(false);
+    }
+    let covered = data.range(range_start, range_length.saturating_add(value.len()) - 1)?;
+    if value.is_empty() {
+        return Ok(!covered.is_empty());
+    }
+    // `covered` stops where the last offset's window does, so any window found in it is in range
+    let Some(mask) = mask else {
+        return Ok(match range_length {
+            1 => covered == value,
+            _ => memchr::memmem::find(covered, value).is_some(),
+        });
+    };
+    let Some(last) = covered.len().checked_sub(value.len()) else {
+        return Ok(false);
+    };
+    // A mask shorter than the value, as a hand-built model may have, leaves the rest unchecked
+    let compared = mask.len().min(value.len());
+    let (value, mask) = (&value[..compared], &mask[..compared]);
+    let matches_at = |offset: usize| masked_eq(&covered[offset..offset + compared], value, mask);
+    // Windows can only match where a byte the mask keeps in full does, if there is one
+    match mask.iter().position(|&m| m == 0xff) {
+        Some(anchor) => Ok(
+            memchr::memchr_iter(value[anchor], &covered[anchor..=anchor + last]).any(matches_at),
+        ),
+        None => Ok((0..=last).any(matches_at)),
+    }
+}
+
+// Whether `data` and `value` agree in the bits set in `mask`, all three being the same length,
+// comparing a word at a time.
+fn masked_eq(mut data: &[u8], mut value: &[u8], mut mask: &[u8]) -> bool {
+    const WORD: usize = size_of::<u64>();
+    while let (Some((d, data_rest)), Some((v, value_rest)), Some((m, mask_rest))) = (
+        data.split_first_chunk::<WORD>(),
+        value.split_first_chunk::<WORD>(),
+        mask.split_first_chunk::<WORD>(),
+    ) {
+        let word = |bytes: &[u8; WORD]| u64::from_ne_bytes(*bytes);
+        if (word(d) ^ word(v)) & word(m) != 0 {
+            return false;
+        }
+        (data, value, mask) = (data_rest, value_rest, mask_rest);
+    }
+    data.iter()
+        .zip(value)
+        .zip(mask)
+        .all(|((d, v), m)| (d ^ v) & m == 0)
+}
+
+// The same rules, evaluated from a parsed model rather than the cache bytes.
+impl MagicMatch {
+    /// Whether this is a `__NOMAGIC__` rule, which deletes the type's magic from less important
+    /// databases instead of matching anything.
+    pub(crate) fn is_no_magic_marker(&self) -> bool {
+        matches!(&self.matchlets[..], [only] if only.value == NO_MAGIC_MARKER)
+    }
+
+    pub(crate) fn matches(&self, data: &mut dyn MagicSource) -> Result<bool, Error> {
+        any_matches(&self.matchlets, data)
+    }
+}
+
+fn any_matches(matchlets: &[Matchlet], data: &mut dyn MagicSource) -> Result<bool, Error> {
+    for matchlet in matchlets {
+        let found = found_in_range(
+            data,
+            matchlet.range_start as usize,
+            matchlet.range_length as usize,
+            &matchlet.value,
+            matchlet.mask.as_deref(),
+        )?;
+        if found && (matchlet.children.is_empty() || any_matches(&matchlet.children, data)?) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
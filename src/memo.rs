@@ -0,0 +1,62 @@
+//! Memoisation of magic results for repeated content.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Mutex,
+};
+
+use crate::MimeType;
+
+/// A bounded map from the hash of a sniff buffer to the magic result it produced.
+///
+/// Only the bytes magic rules can reach are hashed, so files that differ past that point share
+/// an entry. The oldest entry is evicted once `capacity` is reached.
+#[derive(Debug)]
+pub(crate) struct SniffMemo {
+    capacity: usize,
+    entries: Mutex<MemoEntries>,
+}
+
+#[derive(Debug, Default)]
+struct MemoEntries {
+    results: HashMap<u64, Option<(MimeType, u32)>>,
+    order: VecDeque<u64>,
+}
+
+impl SniffMemo {
+    pub(crate) fn new(capacity: usize) -> Self {
+        SniffMemo {
+            capacity,
+            entries: Mutex::default(),
+        }
+    }
+
+    pub(crate) fn key(prefix: &[u8]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        prefix.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub(crate) fn get(&self, key: u64) -> Option<Option<(MimeType, u32)>> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.results.get(&key).cloned()
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub(crate) fn insert(&self, key: u64, result: Option<(MimeType, u32)>) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.results.insert(key, result).is_some() {
+            return;
+        }
+        entries.order.push_back(key);
+        while entries.order.len() > self.capacity {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.results.remove(&oldest);
+            }
+        }
+    }
+}
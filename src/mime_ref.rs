@@ -0,0 +1,89 @@
+//! Type names borrowed from where they were found instead of copied out.
+
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+use crate::MimeType;
+
+/// A mime type borrowed from the database or index that answered a lookup, such as the string
+/// pool of a loaded mime.cache, so that the lookup allocates nothing.
+///
+/// Batch classifiers that only compare or print results can use the `_ref` lookups, such as
+/// [`find_mimetype_ref_from_filepath`](crate::MimeSearcher::find_mimetype_ref_from_filepath),
+/// and call [`to_mime_type`](Self::to_mime_type) for the few results they keep. It orders,
+/// hashes and displays like the [`MimeType`] it borrows from, and compares equal to it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub struct MimeTypeRef<'a> {
+    essence: &'a str,
+    slash: usize,
+}
+
+impl<'a> MimeTypeRef<'a> {
+    /// Wraps a type name read from a database without checking it, as
+    /// [`MimeType::unchecked`] does.
+    pub(crate) fn unchecked(essence: &'a str) -> Self {
+        MimeTypeRef {
+            slash: essence.find('/').unwrap_or(essence.len()),
+            essence,
+        }
+    }
+
+    /// The media (top level) type, `text` in `text/plain`.
+    pub fn media(&self) -> &'a str {
+        &self.essence[..self.slash]
+    }
+
+    /// The subtype, `plain` in `text/plain`.
+    pub fn subtype(&self) -> &'a str {
+        self.essence.get(self.slash + 1..).unwrap_or("")
+    }
+
+    /// The whole type as a string, `text/plain`, borrowed for as long as the type is.
+    pub fn essence_str(&self) -> &'a str {
+        self.essence
+    }
+
+    /// Copies the type out, for keeping it past the borrow.
+    pub fn to_mime_type(&self) -> MimeType {
+        MimeType::unchecked(self.essence)
+    }
+}
+
+impl<'a> From<&'a MimeType> for MimeTypeRef<'a> {
+    fn from(mime: &'a MimeType) -> Self {
+        MimeTypeRef::unchecked(mime.essence_str())
+    }
+}
+
+impl From<MimeTypeRef<'_>> for MimeType {
+    fn from(mime: MimeTypeRef<'_>) -> Self {
+        mime.to_mime_type()
+    }
+}
+
+impl PartialEq<MimeType> for MimeTypeRef<'_> {
+    fn eq(&self, other: &MimeType) -> bool {
+        self.essence == other.essence_str()
+    }
+}
+
+impl fmt::Display for MimeTypeRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.essence)
+    }
+}
+
+// Hashes only the string, like `MimeType`
+impl Hash for MimeTypeRef<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.essence.hash(state);
+    }
+}
+
+impl AsRef<str> for MimeTypeRef<'_> {
+    fn as_ref(&self) -> &str {
+        self.essence
+    }
+}
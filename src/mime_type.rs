@@ -0,0 +1,230 @@
+//! The mime type itself, checked once when it is made so that its parts can be read freely.
+
+use std::{
+    borrow::Borrow,
+    hash::{Hash, Hasher},
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::Error;
+
+/// A mime type such as `text/plain`, split into its media type and subtype.
+///
+/// Construct one with [`MimeType::new`], which rejects anything that isn't a valid
+/// `media/subtype` pair, so the accessors never need to re-check the string. Common types are
+/// also available as constants such as [`MimeType::TEXT_PLAIN`], which don't allocate. Clones
+/// share the string rather than copying it, and a searcher hands out one
+/// [interned](crate::MimeSearcher::intern) copy of each type it finds.
+///
+/// It parses with `str::parse`, displays as the plain type string, and hashes like that string,
+/// so a `HashMap<MimeType, _>` can be queried with a `&str`.
+#[derive(Debug, Clone)]
+pub struct MimeType {
+    essence: Essence,
+    slash: usize,
+}
+
+/// The string of a [`MimeType`], either a constant or shared by every clone, so that copying a
+/// type never allocates.
+#[derive(Clone)]
+enum Essence {
+    Static(&'static str),
+    Shared(Arc<str>),
+}
+
+impl std::ops::Deref for Essence {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            Essence::Static(essence) => essence,
+            Essence::Shared(essence) => essence,
+        }
+    }
+}
+
+impl std::fmt::Debug for Essence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl MimeType {
+    pub const APPLICATION_JSON: MimeType = MimeType::constant("application/json");
+    pub const APPLICATION_OCTET_STREAM: MimeType = MimeType::constant("application/octet-stream");
+    pub const APPLICATION_PDF: MimeType = MimeType::constant("application/pdf");
+    pub const APPLICATION_XML: MimeType = MimeType::constant("application/xml");
+    pub const APPLICATION_ZIP: MimeType = MimeType::constant("application/zip");
+    pub const APPLICATION_X_ZERO_SIZE: MimeType = MimeType::constant("application/x-zerosize");
+    pub const AUDIO_MPEG: MimeType = MimeType::constant("audio/mpeg");
+    pub const IMAGE_GIF: MimeType = MimeType::constant("image/gif");
+    pub const IMAGE_JPEG: MimeType = MimeType::constant("image/jpeg");
+    pub const IMAGE_PNG: MimeType = MimeType::constant("image/png");
+    pub const IMAGE_SVG_XML: MimeType = MimeType::constant("image/svg+xml");
+    pub const IMAGE_WEBP: MimeType = MimeType::constant("image/webp");
+    pub const INODE_DIRECTORY: MimeType = MimeType::constant("inode/directory");
+    pub const INODE_SYMLINK: MimeType = MimeType::constant("inode/symlink");
+    pub const TEXT_CSS: MimeType = MimeType::constant("text/css");
+    pub const TEXT_HTML: MimeType = MimeType::constant("text/html");
+    pub const TEXT_MARKDOWN: MimeType = MimeType::constant("text/markdown");
+    pub const TEXT_PLAIN: MimeType = MimeType::constant("text/plain");
+    pub const VIDEO_MP4: MimeType = MimeType::constant("video/mp4");
+
+    // Only for strings known to be valid, such as the constants above
+    pub(crate) const fn constant(essence: &'static str) -> MimeType {
+        let bytes = essence.as_bytes();
+        let mut slash = 0;
+        while bytes[slash] != b'/' {
+            slash += 1;
+        }
+        MimeType {
+            essence: Essence::Static(essence),
+            slash,
+        }
+    }
+
+    /// Checks `essence` against the RFC 6838 grammar: a media type and a subtype, each 1 to 127
+    /// characters from `A-Z a-z 0-9 ! # $ & - ^ _ . +` starting with a letter or digit, joined by
+    /// a single `/`.
+    ///
+    /// `try_from` and `str::parse` check the same way.
+    pub fn new(essence: &str) -> Result<MimeType, InvalidMimeType> {
+        Self::parse(essence)
+    }
+
+    /// Wraps a type name read from a database without checking it, since update-mime-database
+    /// has already done so.
+    pub(crate) fn unchecked(essence: impl AsRef<str>) -> MimeType {
+        let essence = essence.as_ref();
+        MimeType {
+            slash: essence.find('/').unwrap_or(essence.len()),
+            essence: Essence::Shared(essence.into()),
+        }
+    }
+
+    /// The media (top level) type, `text` in `text/plain`.
+    pub fn media(&self) -> &str {
+        &self.essence[..self.slash]
+    }
+
+    /// The subtype, `plain` in `text/plain`.
+    pub fn subtype(&self) -> &str {
+        self.essence.get(self.slash + 1..).unwrap_or("")
+    }
+
+    /// The whole type as a string, `text/plain`.
+    pub fn essence_str(&self) -> &str {
+        &self.essence
+    }
+
+    /// Whether this type matches `pattern`, which is a full type, a `media/*` wildcard or `*/*`.
+    /// Case is ignored, as for all mime types.
+    pub fn matches(&self, pattern: &str) -> bool {
+        match pattern.strip_suffix("/*") {
+            Some("*") => true,
+            Some(media) => self.media().eq_ignore_ascii_case(media),
+            None => self.essence.eq_ignore_ascii_case(pattern),
+        }
+    }
+
+    fn parse(essence: &str) -> Result<Self, InvalidMimeType> {
+        // restricted-name from RFC 6838 section 4.2
+        let valid_part = |part: &str| {
+            (1..=127).contains(&part.len())
+                && part.starts_with(|c: char| c.is_ascii_alphanumeric())
+                && part
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b"!#$&-^_.+".contains(&b))
+        };
+        match essence.split_once('/') {
+            Some((media, subtype)) if valid_part(media) && valid_part(subtype) => Ok(MimeType {
+                slash: media.len(),
+                essence: Essence::Shared(essence.into()),
+            }),
+            _ => Err(InvalidMimeType(essence.to_string())),
+        }
+    }
+}
+
+/// A string that [`MimeType::new`] rejected.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct InvalidMimeType(pub String);
+
+impl From<InvalidMimeType> for Error {
+    fn from(value: InvalidMimeType) -> Self {
+        Error::InvalidMimeType(value.0)
+    }
+}
+
+impl TryFrom<String> for MimeType {
+    type Error = InvalidMimeType;
+
+    fn try_from(value: String) -> Result<Self, InvalidMimeType> {
+        Self::parse(&value)
+    }
+}
+
+impl TryFrom<&str> for MimeType {
+    type Error = InvalidMimeType;
+
+    fn try_from(value: &str) -> Result<Self, InvalidMimeType> {
+        Self::new(value)
+    }
+}
+
+impl FromStr for MimeType {
+    type Err = InvalidMimeType;
+
+    fn from_str(s: &str) -> Result<Self, InvalidMimeType> {
+        Self::new(s)
+    }
+}
+
+impl std::fmt::Display for MimeType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.essence)
+    }
+}
+
+// Types compare as their strings, which `slash` follows from. Interned types share their string,
+// so comparing two of them usually stops at the pointer.
+impl PartialEq for MimeType {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.essence_str(), other.essence_str())
+            || self.essence_str() == other.essence_str()
+    }
+}
+
+impl Eq for MimeType {}
+
+impl PartialOrd for MimeType {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MimeType {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.essence_str().cmp(other.essence_str())
+    }
+}
+
+// Hashes only the string so that it agrees with the `Borrow<str>` impl
+impl Hash for MimeType {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.essence_str().hash(state);
+    }
+}
+
+impl AsRef<str> for MimeType {
+    fn as_ref(&self) -> &str {
+        &self.essence
+    }
+}
+
+impl Borrow<str> for MimeType {
+    fn borrow(&self) -> &str {
+        &self.essence
+    }
+}
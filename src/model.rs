@@ -0,0 +1,837 @@
+//! An editable document model of a mime.cache file.
+//!
+//! [`MimeDatabaseModel::from_cache_bytes`] parses every section of a cache into plain structs,
+//! and [`MimeDatabaseModel::to_cache_bytes`] compiles them back. The writer is deterministic and
+//! only reorders what readers binary search (aliases, parents, literals, namespaces and icons are
+//! sorted by key), keeping the given order of glob and same-priority magic rules since that
+//! decides which one wins. A model parsed from a cache therefore survives a recompile unchanged,
+//! and recompiling it again gives identical bytes.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    CacheSection, Corruption, Error, MimeCacheHeader, MimeType,
+    reader::{CacheReader, MAX_NESTING},
+};
+
+const HEADER_LEN: usize = 40;
+
+/// Flag set in a glob's weight word when the pattern is case sensitive.
+const CASE_SENSITIVE_FLAG: u32 = 0x100;
+
+/// Glob pattern deleting the globs of a type from less important databases.
+pub(crate) const NO_GLOBS_MARKER: &str = "__NOGLOBS__";
+
+/// The value of the single matchlet of a rule that deletes a type's magic from less important
+/// databases. Such rules never match anything themselves.
+#[cfg_attr(not(any(feature = "magic", feature = "xml")), allow(dead_code))]
+pub(crate) const NO_MAGIC_MARKER: &[u8] = b"__NOMAGIC__";
+
+/// Every section of a mime.cache file.
+///
+/// Written caches always use the 1.2 layout.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MimeDatabaseModel {
+    /// `(alias, canonical type)` pairs.
+    pub aliases: Vec<(MimeType, MimeType)>,
+    /// Each type with its direct parents.
+    pub parents: Vec<(MimeType, Vec<MimeType>)>,
+    /// Globs without wildcards, matched against the whole file name.
+    pub literals: Vec<GlobRecord>,
+    /// `*.ext` style globs, stored in the cache as a reverse suffix tree.
+    pub suffixes: Vec<GlobRecord>,
+    /// All remaining globs.
+    pub globs: Vec<GlobRecord>,
+    /// Content sniffing rules.
+    pub magic: Vec<MagicMatch>,
+    /// XML root element namespaces.
+    pub namespaces: Vec<NamespaceRecord>,
+    /// `(type, icon name)` pairs.
+    pub icons: Vec<(MimeType, String)>,
+    /// `(type, generic icon name)` pairs.
+    pub generic_icons: Vec<(MimeType, String)>,
+}
+
+/// A single glob rule.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GlobRecord {
+    pub pattern: String,
+    pub mime: MimeType,
+    pub weight: u8,
+    pub case_sensitive: bool,
+}
+
+/// A prioritised magic rule for one type. It fires if any of its top level matchlets match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MagicMatch {
+    pub priority: u32,
+    pub mime: MimeType,
+    pub matchlets: Vec<Matchlet>,
+}
+
+/// A byte comparison at some offset range. Children are only checked once the parent matched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Matchlet {
+    pub range_start: u32,
+    pub range_length: u32,
+    pub word_size: u32,
+    pub value: Vec<u8>,
+    pub mask: Option<Vec<u8>>,
+    pub children: Vec<Matchlet>,
+}
+
+/// Maps an XML root element to a type.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NamespaceRecord {
+    pub uri: String,
+    pub local_name: String,
+    pub mime: MimeType,
+}
+
+impl MagicMatch {
+    /// How many leading bytes the rule can look at, or `u32::MAX` for a rule reaching further.
+    pub(crate) fn extent(&self) -> u32 {
+        fn extent(matchlets: &[Matchlet]) -> u32 {
+            matchlets
+                .iter()
+                .map(|m| {
+                    let value_length = u32::try_from(m.value.len()).unwrap_or(u32::MAX);
+                    let own = m
+                        .range_start
+                        .saturating_add(m.range_length)
+                        .saturating_add(value_length);
+                    own.max(extent(&m.children))
+                })
+                .max()
+                .unwrap_or(0)
+        }
+        extent(&self.matchlets)
+    }
+}
+
+impl MimeDatabaseModel {
+    /// Parses a complete mime.cache file.
+    pub fn from_cache_bytes(data: &[u8]) -> Result<Self, Error> {
+        let header = MimeCacheHeader::parse(data)?;
+        let mut model = MimeDatabaseModel::default();
+        for section in CacheSection::ALL {
+            model.merge(Self::read_section(data, &header, section)?);
+        }
+        Ok(model)
+    }
+
+    /// Parses the one section `section` of a cache, leaving every other list empty.
+    pub(crate) fn read_section(
+        data: &[u8],
+        header: &MimeCacheHeader,
+        section: CacheSection,
+    ) -> Result<Self, Error> {
+        let data = CacheReader::new(data, section);
+        let mut model = MimeDatabaseModel::default();
+        match section {
+            CacheSection::AliasList => {
+                model.aliases = read_pairs(data, header.alias_list_offset)?
+                    .into_iter()
+                    .map(|(alias, mime)| (MimeType::unchecked(alias), MimeType::unchecked(mime)))
+                    .collect()
+            }
+            CacheSection::ParentList => {
+                model.parents = read_parents(data, header.parent_list_offset)?
+            }
+            CacheSection::LiteralList => {
+                model.literals = read_glob_list(data, header.literal_list_offset)?
+            }
+            CacheSection::ReverseSuffixTree => {
+                model.suffixes = read_suffix_tree(data, header.reverse_suffix_tree_offset)?
+            }
+            CacheSection::GlobList => model.globs = read_glob_list(data, header.glob_list_offset)?,
+            CacheSection::MagicList => model.magic = read_magic(data, header.magic_list_offset)?,
+            CacheSection::NamespaceList => {
+                model.namespaces = read_namespaces(data, header.namespace_list_offset)?
+            }
+            CacheSection::IconsList => {
+                model.icons = read_pairs(data, header.icons_list_offset)?
+                    .into_iter()
+                    .map(|(mime, icon)| (MimeType::unchecked(mime), icon))
+                    .collect()
+            }
+            CacheSection::GenericIconsList => {
+                model.generic_icons = read_pairs(data, header.generic_icons_list_offset)?
+                    .into_iter()
+                    .map(|(mime, icon)| (MimeType::unchecked(mime), icon))
+                    .collect()
+            }
+        }
+        Ok(model)
+    }
+
+    /// Appends every list of `other` to the same list of this model.
+    pub(crate) fn merge(&mut self, other: MimeDatabaseModel) {
+        self.aliases.extend(other.aliases);
+        self.parents.extend(other.parents);
+        self.literals.extend(other.literals);
+        self.suffixes.extend(other.suffixes);
+        self.globs.extend(other.globs);
+        self.magic.extend(other.magic);
+        self.namespaces.extend(other.namespaces);
+        self.icons.extend(other.icons);
+        self.generic_icons.extend(other.generic_icons);
+    }
+
+    /// A minimal database holding only `types`, along with everything needed to resolve them.
+    ///
+    /// Requested aliases are resolved to their canonical type, and all ancestors of the requested
+    /// types are kept too, so subclass lookups keep working. Every alias, glob, magic rule,
+    /// namespace and icon of a kept type is copied, nothing else is.
+    pub fn extract_subset(&self, types: &[MimeType]) -> MimeDatabaseModel {
+        let canonical = |mime: &MimeType| {
+            self.aliases
+                .iter()
+                .find(|(alias, _)| alias == mime)
+                .map_or(mime, |(_, canonical)| canonical)
+                .clone()
+        };
+
+        let mut keep = BTreeSet::new();
+        let mut pending: Vec<MimeType> = types.iter().map(canonical).collect();
+        while let Some(mime) = pending.pop() {
+            if keep.contains(&mime) {
+                continue;
+            }
+            if let Some((_, parents)) = self.parents.iter().find(|(m, _)| *m == mime) {
+                pending.extend(parents.iter().map(canonical));
+            }
+            keep.insert(mime);
+        }
+
+        let kept_pairs = |pairs: &[(MimeType, String)]| {
+            pairs
+                .iter()
+                .filter(|(mime, _)| keep.contains(mime))
+                .cloned()
+                .collect()
+        };
+        let kept_globs = |globs: &[GlobRecord]| {
+            globs
+                .iter()
+                .filter(|glob| keep.contains(&glob.mime))
+                .cloned()
+                .collect()
+        };
+        MimeDatabaseModel {
+            aliases: self
+                .aliases
+                .iter()
+                .filter(|(_, mime)| keep.contains(mime))
+                .cloned()
+                .collect(),
+            parents: self
+                .parents
+                .iter()
+                .filter(|(mime, _)| keep.contains(mime))
+                .cloned()
+                .collect(),
+            literals: kept_globs(&self.literals),
+            suffixes: kept_globs(&self.suffixes),
+            globs: kept_globs(&self.globs),
+            magic: self
+                .magic
+                .iter()
+                .filter(|m| keep.contains(&m.mime))
+                .cloned()
+                .collect(),
+            namespaces: self
+                .namespaces
+                .iter()
+                .filter(|ns| keep.contains(&ns.mime))
+                .cloned()
+                .collect(),
+            icons: kept_pairs(&self.icons),
+            generic_icons: kept_pairs(&self.generic_icons),
+        }
+    }
+
+    /// Compiles the model into a version 1.2 mime.cache file.
+    pub fn to_cache_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::new(self.strings(), self.blobs());
+
+        let alias_list = writer.pairs(
+            self.aliases
+                .iter()
+                .map(|(a, m)| (a.essence_str(), m.essence_str())),
+        );
+        let parent_list = writer.parents(&self.parents);
+        let literal_list = writer.glob_list(&self.literals, true);
+        let suffix_tree = writer.suffix_tree(&self.suffixes);
+        let glob_list = writer.glob_list(&self.globs, false);
+        let magic_list = writer.magic(&self.magic);
+        let namespace_list = writer.namespaces(&self.namespaces);
+        let icons_list = writer.pairs(self.icons.iter().map(|(m, i)| (m.essence_str(), &i[..])));
+        let generic_icons_list = writer.pairs(
+            self.generic_icons
+                .iter()
+                .map(|(m, i)| (m.essence_str(), &i[..])),
+        );
+
+        let header = [
+            alias_list,
+            parent_list,
+            literal_list,
+            suffix_tree,
+            glob_list,
+            magic_list,
+            namespace_list,
+            icons_list,
+            generic_icons_list,
+        ];
+
+        let mut out = writer.buf;
+        out[0..2].copy_from_slice(&1u16.to_be_bytes());
+        out[2..4].copy_from_slice(&2u16.to_be_bytes());
+        for (i, offset) in header.iter().enumerate() {
+            out[4 + i * 4..8 + i * 4].copy_from_slice(&offset.to_be_bytes());
+        }
+        out
+    }
+
+    // Every nul terminated string referenced from the tables.
+    fn strings(&self) -> BTreeSet<&str> {
+        let mut strings = BTreeSet::new();
+        for (a, b) in &self.aliases {
+            strings.extend([a.essence_str(), b.essence_str()]);
+        }
+        for (mime, parents) in &self.parents {
+            strings.insert(mime.essence_str());
+            strings.extend(parents.iter().map(|p| p.essence_str()));
+        }
+        for glob in self
+            .literals
+            .iter()
+            .chain(&self.globs)
+            .chain(&self.suffixes)
+        {
+            strings.extend([&glob.pattern[..], glob.mime.essence_str()]);
+        }
+        for m in &self.magic {
+            strings.insert(m.mime.essence_str());
+        }
+        for ns in &self.namespaces {
+            strings.extend([&ns.uri[..], &ns.local_name[..], ns.mime.essence_str()]);
+        }
+        for (mime, icon) in self.icons.iter().chain(&self.generic_icons) {
+            strings.extend([mime.essence_str(), &icon[..]]);
+        }
+        strings
+    }
+
+    // Magic values and masks, which are raw bytes rather than strings.
+    fn blobs(&self) -> BTreeSet<&[u8]> {
+        fn walk<'a>(matchlets: &'a [Matchlet], blobs: &mut BTreeSet<&'a [u8]>) {
+            for m in matchlets {
+                blobs.insert(&m.value);
+                if let Some(mask) = &m.mask {
+                    blobs.insert(mask);
+                }
+                walk(&m.children, blobs);
+            }
+        }
+        let mut blobs = BTreeSet::new();
+        for m in &self.magic {
+            walk(&m.matchlets, &mut blobs);
+        }
+        blobs
+    }
+}
+
+fn read_string(data: CacheReader<'_>, offset: usize) -> Result<String, Error> {
+    data.cstr_at(offset).map(str::to_string)
+}
+
+fn read_mime(data: CacheReader<'_>, offset: usize) -> Result<MimeType, Error> {
+    read_string(data, offset).map(MimeType::unchecked)
+}
+
+// AliasList, IconsList and GenericIconsList:
+// 4			CARD32		N_ENTRIES
+// 8*N_ENTRIES	(CARD32 KEY_OFFSET, CARD32 VALUE_OFFSET)
+fn read_pairs(data: CacheReader<'_>, start: u32) -> Result<Vec<(String, String)>, Error> {
+    let start = start as usize;
+    (0..data.usize(start)?)
+        .map(|i| {
+            let data = data.entry(i);
+            let ptr = data.nth(start + 4, i, 8)?;
+            Ok((read_string(data, ptr)?, read_string(data, ptr + 4)?))
+        })
+        .collect()
+}
+
+// ParentList:
+// 4			CARD32		N_ENTRIES
+// 8*N_ENTRIES	(CARD32 MIME_TYPE_OFFSET, CARD32 PARENTS_OFFSET)
+//
+// Parents:
+// 4			CARD32		N_PARENTS
+// 4*N_PARENTS	CARD32		MIME_TYPE_OFFSET
+fn read_parents(
+    data: CacheReader<'_>,
+    start: u32,
+) -> Result<Vec<(MimeType, Vec<MimeType>)>, Error> {
+    let start = start as usize;
+    (0..data.usize(start)?)
+        .map(|i| {
+            let data = data.entry(i);
+            let ptr = data.nth(start + 4, i, 8)?;
+            let parents_ptr = data.usize(ptr + 4)?;
+            let parents = (0..data.usize(parents_ptr)?)
+                .map(|j| read_mime(data, data.nth(parents_ptr + 4, j, 4)?))
+                .collect::<Result<_, _>>()?;
+            Ok((read_mime(data, ptr)?, parents))
+        })
+        .collect()
+}
+
+// LiteralList and GlobList:
+// 4			CARD32		N_ENTRIES
+// 12*N_ENTRIES	(CARD32 PATTERN_OFFSET, CARD32 MIME_TYPE_OFFSET, CARD32 WEIGHT_AND_FLAGS)
+pub(crate) fn read_glob_list(data: CacheReader<'_>, start: u32) -> Result<Vec<GlobRecord>, Error> {
+    let start = start as usize;
+    (0..data.usize(start)?)
+        .map(|i| {
+            let data = data.entry(i);
+            let ptr = data.nth(start + 4, i, 12)?;
+            let meta = data.u32(ptr + 8)?;
+            Ok(GlobRecord {
+                pattern: read_string(data, ptr)?,
+                mime: read_mime(data, ptr + 4)?,
+                weight: (meta & 0xFF) as u8,
+                case_sensitive: meta & CASE_SENSITIVE_FLAG != 0,
+            })
+        })
+        .collect()
+}
+
+// ReverseSuffixTree:
+// 4			CARD32		N_ROOTS
+// 4			CARD32		FIRST_ROOT_OFFSET
+//
+// ReverseSuffixTreeNode:
+// 4			CARD32		CHARACTER
+// 4			CARD32		N_CHILDREN
+// 4			CARD32		FIRST_CHILD_OFFSET
+//
+// ReverseSuffixTreeLeafNode:
+// 4			CARD32		0
+// 4			CARD32		MIME_TYPE_OFFSET
+// 4			CARD32		WEIGHT_AND_FLAGS
+pub(crate) fn read_suffix_tree(
+    data: CacheReader<'_>,
+    start: u32,
+) -> Result<Vec<GlobRecord>, Error> {
+    read_suffix_tree_where(data, start, |_| true)
+}
+
+/// The globs of the suffix tree whose suffix, read backwards as the tree stores it, `keep`
+/// accepts. Only the kept globs are copied out.
+pub(crate) fn read_suffix_tree_where(
+    data: CacheReader<'_>,
+    start: u32,
+    keep: impl Fn(&[char]) -> bool,
+) -> Result<Vec<GlobRecord>, Error> {
+    fn walk(
+        data: CacheReader<'_>,
+        count: usize,
+        first: usize,
+        reversed: &mut Vec<char>,
+        keep: &dyn Fn(&[char]) -> bool,
+        out: &mut Vec<GlobRecord>,
+    ) -> Result<(), Error> {
+        if reversed.len() > MAX_NESTING {
+            return Err(data.corrupt(first, Corruption::TooDeep));
+        }
+        for i in 0..count {
+            let ptr = data.nth(first, i, 12)?;
+            let character = data.u32(ptr)?;
+            if character == 0 {
+                if !keep(reversed) {
+                    continue;
+                }
+                let meta = data.u32(ptr + 8)?;
+                out.push(GlobRecord {
+                    pattern: std::iter::once('*')
+                        .chain(reversed.iter().rev().copied())
+                        .collect(),
+                    mime: read_mime(data, ptr + 4)?,
+                    weight: (meta & 0xFF) as u8,
+                    case_sensitive: meta & CASE_SENSITIVE_FLAG != 0,
+                });
+            } else {
+                reversed.push(
+                    char::from_u32(character)
+                        .ok_or(data.corrupt(ptr, Corruption::InvalidCharacter))?,
+                );
+                walk(
+                    data,
+                    data.usize(ptr + 4)?,
+                    data.usize(ptr + 8)?,
+                    reversed,
+                    keep,
+                    out,
+                )?;
+                reversed.pop();
+            }
+        }
+        Ok(())
+    }
+
+    let start = start as usize;
+    let mut out = Vec::new();
+    walk(
+        data,
+        data.usize(start)?,
+        data.usize(start + 4)?,
+        &mut Vec::new(),
+        &keep,
+        &mut out,
+    )?;
+    Ok(out)
+}
+
+/// The type, weight and case sensitivity of each glob that is exactly `*` followed by a suffix, found by following
+/// the suffix's characters, given last first as `reversed`, down the tree. Children are sorted
+/// by character, so each step is a binary search, and the type names are borrowed from the
+/// cache.
+#[cfg(feature = "globs")]
+pub(crate) fn lookup_suffix<'a>(
+    data: CacheReader<'a>,
+    start: u32,
+    reversed: impl Iterator<Item = char>,
+) -> Result<Vec<(&'a str, u8, bool)>, Error> {
+    let start = start as usize;
+    let (mut count, mut first) = (data.usize(start)?, data.usize(start + 4)?);
+    for c in reversed {
+        let found = crate::reader::bsearch(count, |i| {
+            let ptr = data.nth(first, i, 12)?;
+            Ok(data.u32(ptr)?.cmp(&(c as u32)))
+        })?;
+        let Some(i) = found else {
+            return Ok(Vec::new());
+        };
+        let ptr = data.nth(first, i, 12)?;
+        (count, first) = (data.usize(ptr + 4)?, data.usize(ptr + 8)?);
+    }
+    // Leaves come first, as their character (0) sorts lowest
+    let mut leaves = Vec::new();
+    for i in 0..count {
+        let ptr = data.nth(first, i, 12)?;
+        if data.u32(ptr)? != 0 {
+            break;
+        }
+        let meta = data.u32(ptr + 8)?;
+        leaves.push((
+            data.cstr_at(ptr + 4)?,
+            (meta & 0xFF) as u8,
+            meta & CASE_SENSITIVE_FLAG != 0,
+        ));
+    }
+    Ok(leaves)
+}
+
+// See magic.rs for the layout.
+fn read_magic(data: CacheReader<'_>, start: u32) -> Result<Vec<MagicMatch>, Error> {
+    fn matchlets(
+        data: CacheReader<'_>,
+        count: usize,
+        first: usize,
+        depth: usize,
+    ) -> Result<Vec<Matchlet>, Error> {
+        if depth > MAX_NESTING {
+            return Err(data.corrupt(first, Corruption::TooDeep));
+        }
+        (0..count)
+            .map(|i| {
+                let ptr = data.nth(first, i, 32)?;
+                let value_length = data.usize(ptr + 12)?;
+                let bytes = |offset: usize| data.bytes(offset, value_length).map(<[u8]>::to_vec);
+                let mask_offset = data.usize(ptr + 20)?;
+                Ok(Matchlet {
+                    range_start: data.u32(ptr)?,
+                    range_length: data.u32(ptr + 4)?,
+                    word_size: data.u32(ptr + 8)?,
+                    value: bytes(data.usize(ptr + 16)?)?,
+                    mask: match mask_offset {
+                        0 => None,
+                        offset => Some(bytes(offset)?),
+                    },
+                    children: matchlets(
+                        data,
+                        data.usize(ptr + 24)?,
+                        data.usize(ptr + 28)?,
+                        depth + 1,
+                    )?,
+                })
+            })
+            .collect()
+    }
+
+    let start = start as usize;
+    let first = data.usize(start + 8)?;
+    (0..data.usize(start)?)
+        .map(|i| {
+            let data = data.entry(i);
+            let ptr = data.nth(first, i, 16)?;
+            Ok(MagicMatch {
+                priority: data.u32(ptr)?,
+                mime: read_mime(data, ptr + 4)?,
+                matchlets: matchlets(data, data.usize(ptr + 8)?, data.usize(ptr + 12)?, 0)?,
+            })
+        })
+        .collect()
+}
+
+// NamespaceList:
+// 4			CARD32		N_NAMESPACES
+// 12*N_NAMESPACES	(CARD32 NAMESPACE_URI_OFFSET, CARD32 LOCAL_NAME_OFFSET, CARD32 MIME_TYPE_OFFSET)
+fn read_namespaces(data: CacheReader<'_>, start: u32) -> Result<Vec<NamespaceRecord>, Error> {
+    let start = start as usize;
+    (0..data.usize(start)?)
+        .map(|i| {
+            let data = data.entry(i);
+            let ptr = data.nth(start + 4, i, 12)?;
+            Ok(NamespaceRecord {
+                uri: read_string(data, ptr)?,
+                local_name: read_string(data, ptr + 4)?,
+                mime: read_mime(data, ptr + 8)?,
+            })
+        })
+        .collect()
+}
+
+/// Lays a cache out as header, string pool, magic byte pool, then the sections in header order.
+///
+/// Tables are written with placeholder offsets that get patched once the data they point to has
+/// been appended. Everything after the pools is 4 byte aligned.
+struct Writer<'a> {
+    buf: Vec<u8>,
+    strings: BTreeMap<&'a str, u32>,
+    blobs: BTreeMap<&'a [u8], u32>,
+}
+
+#[derive(Default)]
+struct SuffixNode {
+    leaves: Vec<(u32, u32)>,
+    children: BTreeMap<char, SuffixNode>,
+}
+
+impl<'a> Writer<'a> {
+    fn new(strings: BTreeSet<&'a str>, blobs: BTreeSet<&'a [u8]>) -> Self {
+        let mut writer = Writer {
+            buf: vec![0; HEADER_LEN],
+            strings: BTreeMap::new(),
+            blobs: BTreeMap::new(),
+        };
+        for s in strings {
+            writer.strings.insert(s, writer.pos());
+            writer.buf.extend_from_slice(s.as_bytes());
+            writer.buf.push(0);
+        }
+        for b in blobs {
+            writer.blobs.insert(b, writer.pos());
+            writer.buf.extend_from_slice(b);
+        }
+        writer.align();
+        writer
+    }
+
+    fn pos(&self) -> u32 {
+        self.buf.len() as u32
+    }
+
+    fn align(&mut self) {
+        while self.buf.len() % 4 != 0 {
+            self.buf.push(0);
+        }
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.buf.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn string(&mut self, s: &str) {
+        let offset = self.strings[s];
+        self.u32(offset);
+    }
+
+    fn patch(&mut self, at: u32, value: u32) {
+        let at = at as usize;
+        self.buf[at..at + 4].copy_from_slice(&value.to_be_bytes());
+    }
+
+    fn pairs<'b>(&mut self, pairs: impl Iterator<Item = (&'b str, &'b str)>) -> u32 {
+        let mut pairs: Vec<_> = pairs.collect();
+        pairs.sort_by_key(|(key, _)| *key);
+
+        let start = self.pos();
+        self.u32(pairs.len() as u32);
+        for (key, value) in pairs {
+            self.string(key);
+            self.string(value);
+        }
+        start
+    }
+
+    fn parents(&mut self, parents: &[(MimeType, Vec<MimeType>)]) -> u32 {
+        let mut sorted: Vec<_> = parents.iter().collect();
+        sorted.sort_by_key(|(mime, _)| mime);
+
+        let start = self.pos();
+        self.u32(sorted.len() as u32);
+        let mut slots = Vec::new();
+        for (mime, _) in &sorted {
+            self.string(mime.essence_str());
+            slots.push(self.pos());
+            self.u32(0);
+        }
+        for ((_, parents), slot) in sorted.iter().zip(slots) {
+            let list = self.pos();
+            self.patch(slot, list);
+            self.u32(parents.len() as u32);
+            for parent in parents {
+                self.string(parent.essence_str());
+            }
+        }
+        start
+    }
+
+    fn glob_list(&mut self, globs: &[GlobRecord], sort: bool) -> u32 {
+        let mut sorted: Vec<_> = globs.iter().collect();
+        if sort {
+            sorted.sort_by_key(|glob| &glob.pattern);
+        }
+
+        let start = self.pos();
+        self.u32(sorted.len() as u32);
+        for glob in sorted {
+            self.string(&glob.pattern);
+            self.string(glob.mime.essence_str());
+            self.u32(weight_and_flags(glob));
+        }
+        start
+    }
+
+    fn suffix_tree(&mut self, suffixes: &[GlobRecord]) -> u32 {
+        let mut root = SuffixNode::default();
+        for glob in suffixes {
+            let suffix = glob.pattern.strip_prefix('*').unwrap_or(&glob.pattern);
+            let node = suffix
+                .chars()
+                .rev()
+                .fold(&mut root, |node, c| node.children.entry(c).or_default());
+            node.leaves.push((
+                self.strings[glob.mime.essence_str()],
+                weight_and_flags(glob),
+            ));
+        }
+
+        let start = self.pos();
+        self.u32(root.children.len() as u32);
+        let first = self.pos();
+        self.u32(0);
+        self.suffix_nodes(&root, first);
+        start
+    }
+
+    // Writes the children of `node` as one contiguous array, leaves first since their character
+    // (0) sorts lowest, and points `slot` at it.
+    fn suffix_nodes(&mut self, node: &SuffixNode, slot: u32) {
+        let first = self.pos();
+        self.patch(slot, first);
+
+        for &(mime, meta) in &node.leaves {
+            self.u32(0);
+            self.u32(mime);
+            self.u32(meta);
+        }
+        let mut slots = Vec::new();
+        for (c, child) in &node.children {
+            self.u32(*c as u32);
+            self.u32((child.leaves.len() + child.children.len()) as u32);
+            slots.push(self.pos());
+            self.u32(0);
+        }
+        for (child, slot) in node.children.values().zip(slots) {
+            self.suffix_nodes(child, slot);
+        }
+    }
+
+    fn magic(&mut self, matches: &[MagicMatch]) -> u32 {
+        let mut sorted: Vec<_> = matches.iter().collect();
+        sorted.sort_by_key(|m| std::cmp::Reverse(m.priority));
+
+        let start = self.pos();
+        self.u32(sorted.len() as u32);
+        self.u32(sorted.iter().map(|m| m.extent()).max().unwrap_or(0));
+        self.u32(start + 12);
+
+        let mut slots = Vec::new();
+        for m in &sorted {
+            self.u32(m.priority);
+            self.string(m.mime.essence_str());
+            self.u32(m.matchlets.len() as u32);
+            slots.push(self.pos());
+            self.u32(0);
+        }
+        for (m, slot) in sorted.iter().zip(slots) {
+            self.matchlets(&m.matchlets, slot);
+        }
+        start
+    }
+
+    fn matchlets(&mut self, matchlets: &[Matchlet], slot: u32) {
+        let first = self.pos();
+        self.patch(slot, first);
+
+        let mut slots = Vec::new();
+        for m in matchlets {
+            self.u32(m.range_start);
+            self.u32(m.range_length);
+            self.u32(m.word_size);
+            self.u32(m.value.len() as u32);
+            self.u32(self.blobs[&m.value[..]]);
+            self.u32(m.mask.as_ref().map_or(0, |mask| self.blobs[&mask[..]]));
+            self.u32(m.children.len() as u32);
+            slots.push(self.pos());
+            self.u32(0);
+        }
+        for (m, slot) in matchlets.iter().zip(slots) {
+            self.matchlets(&m.children, slot);
+        }
+    }
+
+    fn namespaces(&mut self, namespaces: &[NamespaceRecord]) -> u32 {
+        let mut sorted: Vec<_> = namespaces.iter().collect();
+        sorted.sort_by_key(|ns| (&ns.uri, &ns.local_name));
+
+        let start = self.pos();
+        self.u32(sorted.len() as u32);
+        for ns in sorted {
+            self.string(&ns.uri);
+            self.string(&ns.local_name);
+            self.string(ns.mime.essence_str());
+        }
+        start
+    }
+}
+
+fn weight_and_flags(glob: &GlobRecord) -> u32 {
+    glob.weight as u32
+        | if glob.case_sensitive {
+            CASE_SENSITIVE_FLAG
+        } else {
+            0
+        }
+}
@@ -0,0 +1,173 @@
+//! Telling apart the formats stored in OLE2 compound files.
+//!
+//! Legacy Office documents, Windows Installer packages and a few others are all compound files,
+//! a little FAT file system in a file. Their magic is identical, so mime.cache can only say
+//! `application/x-ole-storage`. What they hold is given by the CLSID of the root directory
+//! entry, or failing that by the names of the streams in it.
+
+// Layout, all numbers little endian:
+//
+// Header (the first 512 bytes):
+// 8			SIGNATURE		D0 CF 11 E0 A1 B1 1A E1
+// at 0x1E	2	SECTOR_SHIFT	9 or 12, for 512 or 4096 byte sectors
+// at 0x30	4	FIRST_DIRECTORY_SECTOR
+// at 0x4C	4*109	DIFAT		the sectors holding the FAT, in order
+//
+// Sector N starts at (N + 1) << SECTOR_SHIFT. The FAT is an array of 4 byte entries giving the
+// sector after each sector in its chain.
+//
+// DirectoryEntry (128 bytes):
+// 64		NAME		UTF-16, nul terminated
+// 2		NAME_LENGTH	in bytes, including the nul
+// 1		TYPE		1 = storage, 2 = stream, 5 = root
+// at 0x50	16	CLSID
+
+use crate::{ContentProbe, MimeType};
+
+const SIGNATURE: [u8; 8] = [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+const DIFAT_ENTRIES: usize = 109;
+const ENTRY_SIZE: usize = 128;
+const ROOT: u8 = 5;
+/// How many directory sectors to read before giving up, which also stops cyclic chains.
+const MAX_DIRECTORY_SECTORS: usize = 64;
+
+/// Refines `application/x-ole-storage` into the format stored in the compound file: Word, Excel,
+/// PowerPoint, Visio or Publisher documents, or Windows Installer packages.
+///
+/// Every searcher runs this after the probes registered with
+/// [`MimeSearcher::register_probe`](crate::MimeSearcher::register_probe). It needs the file's
+/// directory, which is usually in its first few KB but can be anywhere, so it finds nothing in
+/// data cut short before it.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OleProbe;
+
+const TRIGGERS: &[MimeType] = &[MimeType::constant("application/x-ole-storage")];
+
+impl ContentProbe for OleProbe {
+    fn triggers(&self) -> &[MimeType] {
+        TRIGGERS
+    }
+
+    fn probe(&self, _matched: &MimeType, data: &[u8]) -> Option<MimeType> {
+        let file = CompoundFile::parse(data)?;
+        let mut entries = file.directory();
+        let root = entries.next().filter(|root| root[0x42] == ROOT)?;
+        if let Some((_, mime)) = CLSIDS
+            .iter()
+            .find(|(clsid, _)| root[0x50..0x60] == clsid[..])
+        {
+            return Some(MimeType::constant(mime));
+        }
+        entries.find_map(|entry| {
+            let name = entry_name(entry)?;
+            let (_, mime) = STREAMS.iter().find(|(stream, _)| *stream == name)?;
+            Some(MimeType::constant(mime))
+        })
+    }
+}
+
+/// The root CLSIDs of the formats told apart.
+const CLSIDS: &[([u8; 16], &str)] = &[
+    (ole_clsid(0x0002_0906), "application/msword"),
+    (ole_clsid(0x0002_0900), "application/msword"),
+    (ole_clsid(0x0002_0820), "application/vnd.ms-excel"),
+    (ole_clsid(0x0002_0810), "application/vnd.ms-excel"),
+    (
+        clsid(
+            0x6481_8D10,
+            0x4F9B,
+            0x11CF,
+            [0x86, 0xEA, 0x00, 0xAA, 0x00, 0xB9, 0x29, 0xE8],
+        ),
+        "application/vnd.ms-powerpoint",
+    ),
+    (ole_clsid(0x000C_1084), "application/x-msi"),
+    (ole_clsid(0x0002_1A14), "application/vnd.visio"),
+    (ole_clsid(0x0002_1201), "application/vnd.ms-publisher"),
+];
+
+/// Streams that identify a format whose root CLSID is missing, as some writers leave it zeroed.
+const STREAMS: &[(&str, &str)] = &[
+    ("WordDocument", "application/msword"),
+    ("Workbook", "application/vnd.ms-excel"),
+    ("Book", "application/vnd.ms-excel"),
+    ("PowerPoint Document", "application/vnd.ms-powerpoint"),
+    ("VisioDocument", "application/vnd.visio"),
+    ("Quill", "application/vnd.ms-publisher"),
+];
+
+/// A CLSID in the order it is stored, with its first three fields little endian.
+const fn clsid(d1: u32, d2: u16, d3: u16, d4: [u8; 8]) -> [u8; 16] {
+    let (d1, d2, d3) = (d1.to_le_bytes(), d2.to_le_bytes(), d3.to_le_bytes());
+    [
+        d1[0], d1[1], d1[2], d1[3], d2[0], d2[1], d3[0], d3[1], d4[0], d4[1], d4[2], d4[3], d4[4],
+        d4[5], d4[6], d4[7],
+    ]
+}
+
+/// One of the `XXXXXXXX-0000-0000-C000-000000000046` CLSIDs Microsoft registered for OLE.
+const fn ole_clsid(d1: u32) -> [u8; 16] {
+    clsid(d1, 0, 0, [0xC0, 0, 0, 0, 0, 0, 0, 0x46])
+}
+
+struct CompoundFile<'a> {
+    data: &'a [u8],
+    sector_size: usize,
+}
+
+impl<'a> CompoundFile<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        if !data.starts_with(&SIGNATURE) {
+            return None;
+        }
+        let sector_size = match u16_at(data, 0x1E)? {
+            9 => 512,
+            12 => 4096,
+            _ => return None,
+        };
+        Some(CompoundFile { data, sector_size })
+    }
+
+    fn sector(&self, n: u32) -> Option<&'a [u8]> {
+        let start = (n as usize).checked_add(1)?.checked_mul(self.sector_size)?;
+        self.data.get(start..start.checked_add(self.sector_size)?)
+    }
+
+    /// The sector after `n` in its chain, if the FAT says there is one and it is in `data`.
+    fn next_sector(&self, n: u32) -> Option<u32> {
+        let per_sector = self.sector_size / 4;
+        let difat = n as usize / per_sector;
+        if difat >= DIFAT_ENTRIES {
+            return None;
+        }
+        let fat = self.sector(u32_at(self.data, 0x4C + 4 * difat)?)?;
+        u32_at(fat, n as usize % per_sector * 4)
+    }
+
+    /// The directory entries, the root first, as far as the directory chain can be followed.
+    fn directory(&self) -> impl Iterator<Item = &'a [u8]> + '_ {
+        let first = u32_at(self.data, 0x30);
+        std::iter::successors(first, |&n| self.next_sector(n))
+            .take(MAX_DIRECTORY_SECTORS)
+            .map_while(|n| self.sector(n))
+            .flat_map(|sector| sector.chunks_exact(ENTRY_SIZE))
+    }
+}
+
+fn entry_name(entry: &[u8]) -> Option<String> {
+    let len = (u16_at(entry, 0x40)? as usize).checked_sub(2)?;
+    let units = entry.get(..len.min(64))?.chunks_exact(2);
+    char::decode_utf16(units.map(|unit| u16::from_le_bytes([unit[0], unit[1]])))
+        .collect::<Result<_, _>>()
+        .ok()
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
@@ -0,0 +1,413 @@
+//! Compiling the XML package files under `packages/` into a [`MimeDatabaseModel`], as
+//! update-mime-database does before writing mime.cache.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    Error, GlobRecord, MagicMatch, Matchlet, MimeDatabaseModel, MimeType, NO_GLOBS_MARKER,
+    NO_MAGIC_MARKER, NamespaceRecord,
+    xml::{Event, Reader},
+};
+
+const DEFAULT_GLOB_WEIGHT: u8 = 50;
+const DEFAULT_MAGIC_PRIORITY: u32 = 50;
+/// The package update-mime-database reads last, so that local tweaks win.
+const OVERRIDE_PACKAGE: &str = "Override.xml";
+
+impl MimeDatabaseModel {
+    /// Compiles the package files in `dir/packages`, where `dir` is a database directory such as
+    /// `~/.local/share/mime`, into the model update-mime-database would write as `dir/mime.cache`.
+    ///
+    /// Packages are read in name order with `Override.xml` last. A `<glob-deleteall/>` or
+    /// `<magic-deleteall/>` drops what earlier packages defined for its type and leaves a marker
+    /// hiding that type's globs or magic in less important directories too.
+    ///
+    /// Fails with [`Error::InvalidPackage`] for every package that is malformed, and
+    /// [`Error::Io`] for any that can't be read.
+    pub fn from_packages(dir: &Path) -> Result<Self, Error> {
+        let mut model = MimeDatabaseModel::default();
+        let mut errors = Vec::new();
+        let packages = package_files(dir).map_err(|e| Error::io(&dir.join("packages"), e))?;
+        for path in packages {
+            let parsed = fs::read_to_string(&path)
+                .map_err(|e| Error::io(&path, e))
+                .and_then(|xml| {
+                    read_package(&xml, &mut model).map_err(|(line, problem)| {
+                        Error::InvalidPackage {
+                            path: path.clone(),
+                            line,
+                            problem,
+                        }
+                    })
+                });
+            if let Err(e) = parsed {
+                errors.push(e);
+            }
+        }
+        match Error::combine(errors) {
+            Some(error) => Err(error),
+            None => Ok(model),
+        }
+    }
+}
+
+/// The `*.xml` files in `dir/packages`, in the order update-mime-database reads them.
+pub(crate) fn package_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir.join("packages"))? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "xml") {
+            files.push(path);
+        }
+    }
+    files.sort_by_key(|path| {
+        (
+            path.file_name() == Some(OVERRIDE_PACKAGE.as_ref()),
+            path.clone(),
+        )
+    });
+    Ok(files)
+}
+
+/// Adds the definitions in one package to `model`, returning the types it defines, or failing
+/// with the line and what's wrong.
+pub(crate) fn read_package(
+    xml: &str,
+    model: &mut MimeDatabaseModel,
+) -> Result<Vec<MimeType>, (usize, String)> {
+    let mut reader = Reader::new(xml);
+    let mut types = Vec::new();
+    let mut open: Vec<&str> = Vec::new();
+    let mut mime: Option<MimeType> = None;
+    let mut magic: Option<MagicMatch> = None;
+    // Matchlets still open, innermost last
+    let mut matchlets: Vec<Matchlet> = Vec::new();
+
+    while let Some(event) = reader.next() {
+        let event = event.map_err(|e| (e.line, e.problem.to_string()))?;
+        let fail = |problem: String| (reader.line(), problem);
+        match event {
+            Event::Start { name, attributes } => {
+                let get = |key| attribute(&attributes, key);
+                let required = |key| {
+                    get(key).ok_or_else(|| fail(format!("<{name}> needs a `{key}` attribute")))
+                };
+                match (open.last().copied(), name, &mime) {
+                    (None, "mime-info", _) => {}
+                    (None, _, _) => return Err(fail("the root must be <mime-info>".to_string())),
+                    (Some("mime-info"), "mime-type", _) => {
+                        let defined = parse_mime(required("type")?).map_err(fail)?;
+                        if !types.contains(&defined) {
+                            types.push(defined.clone());
+                        }
+                        mime = Some(defined);
+                    }
+                    (Some("mime-type"), "glob", Some(mime)) => {
+                        let pattern = required("pattern")?;
+                        let weight = match get("weight") {
+                            Some(weight) => {
+                                weight.parse::<u8>().ok().filter(|&w| w <= 100).ok_or_else(
+                                    || fail(format!("invalid glob weight `{weight}`")),
+                                )?
+                            }
+                            None => DEFAULT_GLOB_WEIGHT,
+                        };
+                        let case_sensitive = get("case-sensitive") == Some("true");
+                        add_glob(model, mime, pattern, weight, case_sensitive);
+                    }
+                    (Some("mime-type"), "glob-deleteall", Some(mime)) => {
+                        for globs in [&mut model.literals, &mut model.suffixes, &mut model.globs] {
+                            globs.retain(|glob| glob.mime != *mime);
+                        }
+                        // Case sensitive so that it keeps the spelling readers look for
+                        add_glob(model, mime, NO_GLOBS_MARKER, 0, true);
+                    }
+                    (Some("mime-type"), "magic", Some(mime)) => {
+                        let priority = match get("priority") {
+                            Some(priority) => priority
+                                .parse::<u32>()
+                                .ok()
+                                .filter(|&p| p <= 100)
+                                .ok_or_else(|| {
+                                    fail(format!("invalid magic priority `{priority}`"))
+                                })?,
+                            None => DEFAULT_MAGIC_PRIORITY,
+                        };
+                        magic = Some(MagicMatch {
+                            priority,
+                            mime: mime.clone(),
+                            matchlets: Vec::new(),
+                        });
+                    }
+                    (Some("mime-type"), "magic-deleteall", Some(mime)) => {
+                        model.magic.retain(|rule| rule.mime != *mime);
+                        model.magic.push(MagicMatch {
+                            priority: 0,
+                            mime: mime.clone(),
+                            matchlets: vec![Matchlet {
+                                range_start: 0,
+                                range_length: 1,
+                                word_size: 1,
+                                value: NO_MAGIC_MARKER.to_vec(),
+                                mask: None,
+                                children: Vec::new(),
+                            }],
+                        });
+                    }
+                    (Some("magic" | "match"), "match", _) => {
+                        matchlets.push(
+                            parse_matchlet(
+                                required("type")?,
+                                required("offset")?,
+                                required("value")?,
+                                get("mask"),
+                            )
+                            .map_err(fail)?,
+                        );
+                    }
+                    (Some("mime-type"), "alias", Some(mime)) => {
+                        let alias = parse_mime(required("type")?).map_err(fail)?;
+                        model.aliases.retain(|(a, _)| *a != alias);
+                        model.aliases.push((alias, mime.clone()));
+                    }
+                    (Some("mime-type"), "sub-class-of", Some(mime)) => {
+                        let parent = parse_mime(required("type")?).map_err(fail)?;
+                        match model.parents.iter_mut().find(|(m, _)| m == mime) {
+                            Some((_, parents)) if parents.contains(&parent) => {}
+                            Some((_, parents)) => parents.push(parent),
+                            None => model.parents.push((mime.clone(), vec![parent])),
+                        }
+                    }
+                    (Some("mime-type"), "root-XML", Some(mime)) => {
+                        let record = NamespaceRecord {
+                            uri: required("namespaceURI")?.to_string(),
+                            local_name: required("localName")?.to_string(),
+                            mime: mime.clone(),
+                        };
+                        model.namespaces.retain(|ns| {
+                            (&ns.uri, &ns.local_name) != (&record.uri, &record.local_name)
+                        });
+                        model.namespaces.push(record);
+                    }
+                    (Some("mime-type"), "icon", Some(mime)) => {
+                        set_icon(&mut model.icons, mime, required("name")?);
+                    }
+                    (Some("mime-type"), "generic-icon", Some(mime)) => {
+                        set_icon(&mut model.generic_icons, mime, required("name")?);
+                    }
+                    // Comments, acronyms, tree magic and anything newer aren't in mime.cache
+                    _ => {}
+                }
+                open.push(name);
+            }
+            Event::End { name } => {
+                open.pop();
+                match name {
+                    "match" if open.last() == Some(&"magic") || open.last() == Some(&"match") => {
+                        let Some(done) = matchlets.pop() else {
+                            return Err(fail("</match> without its <match>".to_string()));
+                        };
+                        match (matchlets.last_mut(), &mut magic) {
+                            (Some(parent), _) => parent.children.push(done),
+                            (None, Some(magic)) => magic.matchlets.push(done),
+                            (None, None) => {}
+                        }
+                    }
+                    "magic" if open.last() == Some(&"mime-type") => {
+                        model.magic.extend(magic.take());
+                    }
+                    "mime-type" if open.last() == Some(&"mime-info") => mime = None,
+                    _ => {}
+                }
+            }
+            Event::Text(_) => {}
+        }
+    }
+    Ok(types)
+}
+
+fn attribute<'a>(attributes: &'a [(&str, String)], key: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|(name, _)| *name == key)
+        .map(|(_, value)| value.as_str())
+}
+
+fn parse_mime(mime: &str) -> Result<MimeType, String> {
+    MimeType::new(mime).map_err(|_| format!("`{mime}` is not a valid mime type"))
+}
+
+// Files the glob in the list the cache keeps it in, replacing an earlier definition of the same
+// pattern for the same type. Patterns are stored lowercased unless they are case sensitive.
+fn add_glob(
+    model: &mut MimeDatabaseModel,
+    mime: &MimeType,
+    pattern: &str,
+    weight: u8,
+    case_sensitive: bool,
+) {
+    let pattern = if case_sensitive {
+        pattern.to_string()
+    } else {
+        pattern.to_lowercase()
+    };
+    let is_wild = |c| matches!(c, '*' | '?' | '[');
+    let list = if !pattern.contains(is_wild) {
+        &mut model.literals
+    } else if pattern
+        .strip_prefix('*')
+        .is_some_and(|suffix| !suffix.contains(is_wild))
+    {
+        &mut model.suffixes
+    } else {
+        &mut model.globs
+    };
+    list.retain(|glob| !(glob.pattern == pattern && glob.mime == *mime));
+    list.push(GlobRecord {
+        pattern,
+        mime: mime.clone(),
+        weight,
+        case_sensitive,
+    });
+}
+
+fn set_icon(icons: &mut Vec<(MimeType, String)>, mime: &MimeType, name: &str) {
+    icons.retain(|(m, _)| m != mime);
+    icons.push((mime.clone(), name.to_string()));
+}
+
+/// A `<match>` element, with values laid out as update-mime-database writes them: numbers big
+/// or little endian as their type says, and `host` ones big endian with their size as the word
+/// size, for readers to swap on little endian machines.
+fn parse_matchlet(
+    kind: &str,
+    offset: &str,
+    value: &str,
+    mask: Option<&str>,
+) -> Result<Matchlet, String> {
+    let (range_start, range_end) = match offset.split_once(':') {
+        Some((start, end)) => (parse_number(start), parse_number(end)),
+        None => (parse_number(offset), parse_number(offset)),
+    };
+    let (Some(range_start), Some(range_end)) = (range_start, range_end) else {
+        return Err(format!("invalid match offset `{offset}`"));
+    };
+    let range_start = u32::try_from(range_start).map_err(|_| "match offset out of range")?;
+    let range_end = u32::try_from(range_end).map_err(|_| "match offset out of range")?;
+    if range_end < range_start {
+        return Err(format!("match offset `{offset}` ends before it starts"));
+    }
+
+    let (width, big_endian, word_size) = match kind {
+        "string" => (0, true, 1),
+        "byte" => (1, true, 1),
+        "big16" => (2, true, 1),
+        "big32" => (4, true, 1),
+        "little16" => (2, false, 1),
+        "little32" => (4, false, 1),
+        "host16" => (2, true, 2),
+        "host32" => (4, true, 4),
+        _ => return Err(format!("unknown match type `{kind}`")),
+    };
+    let encode = |number: &str| -> Result<Vec<u8>, String> {
+        let invalid = || format!("invalid {kind} value `{number}`");
+        let n = parse_number(number).ok_or_else(invalid)?;
+        if width < 8 && n >> (width * 8) != 0 {
+            return Err(invalid());
+        }
+        let bytes = n.to_be_bytes();
+        let mut bytes = bytes[bytes.len() - width..].to_vec();
+        if !big_endian {
+            bytes.reverse();
+        }
+        Ok(bytes)
+    };
+    let value = match width {
+        0 => unescape_string(value).ok_or_else(|| format!("invalid string value `{value}`"))?,
+        _ => encode(value)?,
+    };
+    let mask = match (mask, width) {
+        (None, _) => None,
+        (Some(mask), 0) => Some(parse_hex_mask(mask, value.len())?),
+        (Some(mask), _) => Some(encode(mask)?),
+    };
+    if value.is_empty() {
+        return Err("empty match value".to_string());
+    }
+    let range_length = (range_end - range_start)
+        .checked_add(1)
+        .ok_or("match offset out of range")?;
+    Ok(Matchlet {
+        range_start,
+        range_length,
+        word_size,
+        value,
+        mask,
+        children: Vec::new(),
+    })
+}
+
+/// A number written as in C: `0x` hexadecimal, `0` octal or decimal.
+fn parse_number(text: &str) -> Option<u64> {
+    let text = text.trim();
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok()
+    } else if text.len() > 1 && text.starts_with('0') {
+        u64::from_str_radix(&text[1..], 8).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+/// The bytes of a string match value, which may use C escapes such as `\x7f`, `\177` or `\n`.
+fn unescape_string(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&b, after)) = rest.split_first() {
+        rest = after;
+        if b != b'\\' {
+            out.push(b);
+            continue;
+        }
+        let (&escaped, after) = rest.split_first()?;
+        let (radix, max_digits, digits) = match escaped {
+            b'x' => (16, 2, after),
+            b'0'..=b'7' => (8, 3, rest),
+            _ => {
+                rest = after;
+                out.push(match escaped {
+                    b'n' => b'\n',
+                    b'r' => b'\r',
+                    b't' => b'\t',
+                    other => other,
+                });
+                continue;
+            }
+        };
+        let len = digits
+            .iter()
+            .take(max_digits)
+            .take_while(|d| (**d as char).is_digit(radix))
+            .count();
+        let number = std::str::from_utf8(&digits[..len]).ok()?;
+        out.push(u8::try_from(u32::from_str_radix(number, radix).ok()?).ok()?);
+        rest = &digits[len..];
+    }
+    Some(out)
+}
+
+/// A string match mask, written as `0x` followed by a hex byte for each byte of the value.
+fn parse_hex_mask(mask: &str, len: usize) -> Result<Vec<u8>, String> {
+    let invalid = || format!("invalid string mask `{mask}`");
+    let hex = mask
+        .strip_prefix("0x")
+        .or_else(|| mask.strip_prefix("0X"))
+        .filter(|hex| hex.len() == len * 2 && hex.is_ascii())
+        .ok_or_else(invalid)?;
+    (0..len)
+        .map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| invalid()))
+        .collect()
+}
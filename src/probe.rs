@@ -0,0 +1,19 @@
+//! Hooks for refining content sniffing results.
+
+use crate::MimeType;
+
+/// A container inspector that refines a magic match into a more specific type.
+///
+/// Magic rules can only say "this is an OLE2 compound file" or "this is a Matroska stream". A
+/// probe registered with [`MimeSearcher::register_probe`](crate::MimeSearcher::register_probe)
+/// gets to look at the data whenever magic matching produced one of its trigger types, and can
+/// answer with something more precise (e.g. `application/msword` or `video/webm`).
+pub trait ContentProbe: Send + Sync {
+    /// The mime types that cause this probe to run.
+    fn triggers(&self) -> &[MimeType];
+
+    /// Inspects `data`, which magic matching identified as `matched`.
+    ///
+    /// Returns `None` to keep the magic result.
+    fn probe(&self, matched: &MimeType, data: &[u8]) -> Option<MimeType>;
+}
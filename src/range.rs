@@ -0,0 +1,81 @@
+//! Magic matching against sources that are read a byte range at a time.
+
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    io::{self, Read, Seek, SeekFrom},
+};
+
+use crate::Error;
+
+/// A seekable source that magic matching fetches byte ranges from, instead of needing the
+/// start of the file in memory.
+///
+/// Pass one to [`MimeSearcher::find_mimetype_from_reader`](crate::MimeSearcher::find_mimetype_from_reader)
+/// to only fetch the ranges the magic rules actually look at. This matters for remote objects,
+/// where each read is a request, and large block devices. Anything that is [`Read`] and
+/// [`Seek`], such as a [`File`](std::fs::File), already is one.
+pub trait RangeReader {
+    /// Reads up to `len` bytes starting at `offset`.
+    ///
+    /// Returning fewer bytes means the source ends early, which makes rules looking past its
+    /// end fail rather than erroring.
+    fn read_at(&mut self, offset: u64, len: usize) -> io::Result<Vec<u8>>;
+}
+
+impl<T: Read + Seek + ?Sized> RangeReader for T {
+    fn read_at(&mut self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        self.seek(SeekFrom::Start(offset))?;
+        let mut buf = Vec::new();
+        self.take(len as u64).read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+/// Where magic matching gets the bytes a matchlet looks at: an in-memory buffer, or a
+/// [`RangeReader`] fetched from as needed.
+///
+/// [`MimeDatabase`](crate::MimeDatabase) backends evaluate their rules against one of these.
+pub trait MagicSource {
+    /// Up to `len` bytes at `offset`, fewer if the data ends first.
+    fn range(&mut self, offset: usize, len: usize) -> Result<&[u8], Error>;
+}
+
+impl MagicSource for &[u8] {
+    fn range(&mut self, offset: usize, len: usize) -> Result<&[u8], Error> {
+        let rest = self.get(offset..).unwrap_or_default();
+        Ok(&rest[..len.min(rest.len())])
+    }
+}
+
+/// A [`RangeReader`] that remembers every range it fetched during one lookup, as many rules
+/// look at the same bytes. Nothing past `limit` is fetched, the same way a slice source is cut
+/// to the magic extent.
+pub(crate) struct Fetcher<'r> {
+    reader: &'r mut dyn RangeReader,
+    limit: usize,
+    fetched: HashMap<(usize, usize), Vec<u8>>,
+}
+
+impl<'r> Fetcher<'r> {
+    pub(crate) fn new(reader: &'r mut dyn RangeReader, limit: usize) -> Self {
+        Fetcher {
+            reader,
+            limit,
+            fetched: HashMap::new(),
+        }
+    }
+}
+
+impl MagicSource for Fetcher<'_> {
+    fn range(&mut self, offset: usize, len: usize) -> Result<&[u8], Error> {
+        let len = len.min(self.limit.saturating_sub(offset));
+        if len == 0 {
+            return Ok(&[]);
+        }
+        let bytes = match self.fetched.entry((offset, len)) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(self.reader.read_at(offset as u64, len)?),
+        };
+        Ok(bytes)
+    }
+}
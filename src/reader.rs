@@ -0,0 +1,226 @@
+//! Bounds-checked reads from the raw bytes of a mime.cache.
+
+use std::{cmp::Ordering, ffi::CStr};
+
+use crate::{CacheSection, Corruption, Error};
+
+/// How deep the suffix tree and nested magic matchlets may go. Real caches stay far below this,
+/// a corrupt one whose children point back at their parents would otherwise recurse forever.
+pub(crate) const MAX_NESTING: usize = 256;
+
+/// A view of a mime.cache whose reads fail with [`Error::CorruptCache`] rather than panicking,
+/// so a truncated or corrupt file can only ever produce errors.
+///
+/// The reader knows which section, and which entry of it, is being read, so that errors can say
+/// where the cache is broken. All numbers in the cache are big endian, and all offsets are from
+/// the start of the file.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CacheReader<'a> {
+    data: &'a [u8],
+    section: CacheSection,
+    entry: Option<usize>,
+}
+
+impl<'a> CacheReader<'a> {
+    pub(crate) fn new(data: &'a [u8], section: CacheSection) -> Self {
+        CacheReader {
+            data,
+            section,
+            entry: None,
+        }
+    }
+
+    /// The same data, for reading entry `index` of the current section.
+    pub(crate) fn entry(self, index: usize) -> Self {
+        CacheReader {
+            entry: Some(index),
+            ..self
+        }
+    }
+
+    /// An error for a problem at `offset` in the current section and entry.
+    pub(crate) fn corrupt(self, offset: usize, problem: Corruption) -> Error {
+        Error::CorruptCache {
+            section: self.section,
+            entry: self.entry,
+            offset,
+            problem,
+        }
+    }
+
+    /// The `len` bytes starting at `offset`.
+    pub(crate) fn bytes(self, offset: usize, len: usize) -> Result<&'a [u8], Error> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or(self.corrupt(offset, Corruption::OutOfRange))
+    }
+
+    /// The CARD32 at `offset`.
+    pub(crate) fn u32(self, offset: usize) -> Result<u32, Error> {
+        let bytes = self
+            .data
+            .get(offset..)
+            .and_then(<[u8]>::first_chunk)
+            .ok_or(self.corrupt(offset, Corruption::OutOfRange))?;
+        Ok(u32::from_be_bytes(*bytes))
+    }
+
+    /// The CARD32 at `offset`, for counts and offsets that index into the file.
+    pub(crate) fn usize(self, offset: usize) -> Result<usize, Error> {
+        self.u32(offset).map(|n| n as usize)
+    }
+
+    /// The nul terminated string starting at `offset`.
+    pub(crate) fn cstr(self, offset: usize) -> Result<&'a str, Error> {
+        let rest = self
+            .data
+            .get(offset..)
+            .ok_or(self.corrupt(offset, Corruption::OutOfRange))?;
+        CStr::from_bytes_until_nul(rest)
+            .map_err(|_| self.corrupt(offset, Corruption::UnterminatedString))?
+            .to_str()
+            .map_err(|_| self.corrupt(offset, Corruption::InvalidUtf8))
+    }
+
+    /// The string pointed to by the CARD32 offset at `offset`.
+    pub(crate) fn cstr_at(self, offset: usize) -> Result<&'a str, Error> {
+        self.cstr(self.usize(offset)?)
+    }
+
+    /// The offset of entry `index` of a table of `stride` byte entries starting at `first`.
+    pub(crate) fn nth(self, first: usize, index: usize, stride: usize) -> Result<usize, Error> {
+        index
+            .checked_mul(stride)
+            .and_then(|n| n.checked_add(first))
+            .ok_or(self.corrupt(first, Corruption::OutOfRange))
+    }
+
+    /// Binary searches a list sorted by the string its entries start with, returning the
+    /// position of the entry whose string equals `key`.
+    ///
+    /// The list is a CARD32 entry count followed by `stride` byte entries, as used by every
+    /// sorted list in mime.cache.
+    pub(crate) fn bsearch_list(
+        self,
+        list_offset: usize,
+        stride: usize,
+        key: &str,
+    ) -> Result<Option<usize>, Error> {
+        let first = list_offset + 4;
+        let found = bsearch(self.usize(list_offset)?, |i| {
+            let ptr = self.nth(first, i, stride)?;
+            Ok(self.entry(i).cstr_at(ptr)?.cmp(key))
+        })?;
+        found.map(|i| self.nth(first, i, stride)).transpose()
+    }
+}
+
+/// Binary searches `len` sorted entries for one that `compare` says is equal to what is looked
+/// for, returning its index. `compare` orders the entry at an index against the key, as
+/// [`slice::binary_search_by`] does, and may fail on a corrupt entry.
+///
+/// Only indices below `len` are ever compared, so nothing past either end of a list is read,
+/// and the first and last entries are found like any other.
+pub(crate) fn bsearch(
+    len: usize,
+    mut compare: impl FnMut(usize) -> Result<Ordering, Error>,
+) -> Result<Option<usize>, Error> {
+    let (mut low, mut high) = (0, len);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        match compare(mid)? {
+            Ordering::Less => low = mid + 1,
+            Ordering::Greater => high = mid,
+            Ordering::Equal => return Ok(Some(mid)),
+        }
+    }
+    Ok(None)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+/// The offsets of a mime.cache's sections, from the header at its start.
+pub(crate) struct MimeCacheHeader {
+    pub(crate) major_version: u16,
+    pub(crate) minor_version: u16,
+    pub(crate) alias_list_offset: u32,
+    pub(crate) parent_list_offset: u32,
+    pub(crate) literal_list_offset: u32,
+    pub(crate) reverse_suffix_tree_offset: u32,
+    pub(crate) glob_list_offset: u32,
+    pub(crate) magic_list_offset: u32,
+    pub(crate) namespace_list_offset: u32,
+    pub(crate) icons_list_offset: u32,
+    pub(crate) generic_icons_list_offset: u32,
+}
+
+// Header:
+// 2			CARD16		MAJOR_VERSION	1
+// 2			CARD16		MINOR_VERSION	2
+// 4			CARD32		ALIAS_LIST_OFFSET
+// 4			CARD32		PARENT_LIST_OFFSET
+// 4			CARD32		LITERAL_LIST_OFFSET
+// 4			CARD32		REVERSE_SUFFIX_TREE_OFFSET
+// 4			CARD32		GLOB_LIST_OFFSET
+// 4			CARD32		MAGIC_LIST_OFFSET
+// 4			CARD32		NAMESPACE_LIST_OFFSET
+// 4			CARD32		ICONS_LIST_OFFSET
+// 4			CARD32		GENERIC_ICONS_LIST_OFFSET
+// sum = 4*9 + 4 = 40
+impl MimeCacheHeader {
+    /// The only major version there is, a new one would be free to change any layout.
+    const SUPPORTED_MAJOR_VERSION: u16 = 1;
+    /// The oldest minor version that can be read, as for xdgmime. 1.0 predates the icon lists.
+    const OLDEST_MINOR_VERSION: u16 = 1;
+    /// The newest minor version whose layout is known.
+    const SUPPORTED_MINOR_VERSION: u16 = 2;
+
+    /// Reads the header at the start of a cache file.
+    ///
+    /// 1.1 has the same layout as 1.2, it just never sets the case-sensitive flag on globs.
+    /// Newer minor versions only ever append fields to the header, so those are read by their
+    /// known prefix and the rest is ignored. Anything else fails with
+    /// [`Error::UnsupportedCacheVersion`] rather than misreading offsets.
+    pub(crate) fn parse(data: &[u8]) -> Result<MimeCacheHeader, Error> {
+        let header = Self::read_header(
+            data.get(0..40)
+                .ok_or(Error::MissingHeader)?
+                .try_into()
+                .expect("cant fail"),
+        );
+        if header.major_version != Self::SUPPORTED_MAJOR_VERSION
+            || header.minor_version < Self::OLDEST_MINOR_VERSION
+        {
+            return Err(Error::UnsupportedCacheVersion {
+                major: header.major_version,
+                minor: header.minor_version,
+            });
+        }
+        if header.minor_version > Self::SUPPORTED_MINOR_VERSION {
+            log::warn!(
+                "mime.cache version {}.{} is newer than {}.{}, ignoring unknown header fields",
+                header.major_version,
+                header.minor_version,
+                header.major_version,
+                Self::SUPPORTED_MINOR_VERSION
+            );
+        }
+        Ok(header)
+    }
+
+    fn read_header(input: &[u8; 40]) -> MimeCacheHeader {
+        MimeCacheHeader {
+            major_version: u16::from_be_bytes(input[0..2].try_into().unwrap()),
+            minor_version: u16::from_be_bytes(input[2..4].try_into().unwrap()),
+            alias_list_offset: u32::from_be_bytes(input[4..8].try_into().unwrap()),
+            parent_list_offset: u32::from_be_bytes(input[8..12].try_into().unwrap()),
+            literal_list_offset: u32::from_be_bytes(input[12..16].try_into().unwrap()),
+            reverse_suffix_tree_offset: u32::from_be_bytes(input[16..20].try_into().unwrap()),
+            glob_list_offset: u32::from_be_bytes(input[20..24].try_into().unwrap()),
+            magic_list_offset: u32::from_be_bytes(input[24..28].try_into().unwrap()),
+            namespace_list_offset: u32::from_be_bytes(input[28..32].try_into().unwrap()),
+            icons_list_offset: u32::from_be_bytes(input[32..36].try_into().unwrap()),
+            generic_icons_list_offset: u32::from_be_bytes(input[36..40].try_into().unwrap()),
+        }
+    }
+}
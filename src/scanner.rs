@@ -0,0 +1,298 @@
+//! Recursive classification of directory trees.
+
+use std::{
+    collections::HashSet,
+    fs::Metadata,
+    path::{Path, PathBuf},
+};
+
+use crate::{Error, Guess, MimeSearcher, MimeType};
+
+type ContentCallback<'a> = Box<dyn Fn(&Path, &Guess, &[u8]) + Send + Sync + 'a>;
+
+/// Walks a directory tree and [`guess`](MimeSearcher::guess)es the type of every file in it.
+///
+/// Each file is opened once and only its first
+/// [`max_magic_extent`](MimeSearcher::max_magic_extent) bytes are read. Symlinks to files are
+/// classified, symlinks to directories are only descended into with
+/// [`follow_symlinks`](Self::follow_symlinks). Every directory is walked at most once, however
+/// many links lead to it, so symlink and bind mount cycles end the walk rather than loop.
+pub struct Scanner<'a> {
+    searcher: &'a MimeSearcher,
+    content_hooks: Vec<ContentHook<'a>>,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    follow_symlinks: bool,
+    max_depth: usize,
+    same_file_system: bool,
+}
+
+/// What a single [`Scanner::scan`] has seen so far.
+#[derive(Default)]
+struct Walk {
+    visited: HashSet<DirId>,
+    #[cfg(unix)]
+    device: Option<u64>,
+}
+
+#[cfg(unix)]
+type DirId = (u64, u64);
+#[cfg(not(unix))]
+type DirId = PathBuf;
+
+struct ContentHook<'a> {
+    patterns: Vec<String>,
+    callback: ContentCallback<'a>,
+}
+
+/// A file still to classify, or a path that couldn't be walked.
+enum Found {
+    File(PathBuf),
+    Failed(ScanEntry),
+}
+
+/// The outcome for one file found by a [`Scanner`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ScanEntry {
+    pub path: PathBuf,
+    pub result: Result<Guess, Error>,
+}
+
+impl<'a> Scanner<'a> {
+    pub fn new(searcher: &'a MimeSearcher) -> Self {
+        Scanner {
+            searcher,
+            content_hooks: Vec::new(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            follow_symlinks: false,
+            max_depth: usize::MAX,
+            same_file_system: false,
+        }
+    }
+
+    /// Descends into symlinks to directories as well.
+    pub fn follow_symlinks(mut self, follow: bool) -> Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    /// Stops descending `depth` levels below the root, so 1 only classifies the root's direct
+    /// children and 0 only the root itself.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Doesn't descend into directories on a different file system than the root, such as
+    /// mount points below it. Only supported on Unix, elsewhere this does nothing.
+    pub fn same_file_system(mut self, same: bool) -> Self {
+        self.same_file_system = same;
+        self
+    }
+
+    /// Only reports files whose type matches one of the include patterns, and none of the
+    /// exclude patterns, which are prefixed with `!`.
+    ///
+    /// Patterns have the same forms as for [`on_content`](Self::on_content), e.g.
+    /// `&["image/*", "!image/svg+xml"]`. Without any include patterns every type not excluded
+    /// is reported. Files that could not be classified are always reported, and content hooks
+    /// only run for files that are.
+    pub fn filter(mut self, patterns: &[&str]) -> Self {
+        for pattern in patterns {
+            match pattern.strip_prefix('!') {
+                Some(excluded) => self.exclude.push(excluded.to_string()),
+                None => self.include.push(pattern.to_string()),
+            }
+        }
+        self
+    }
+
+    /// Calls `callback` with the path, guess and the bytes already read for every file whose
+    /// type matches one of `patterns`.
+    ///
+    /// Patterns are full types (`application/pdf`), media wildcards (`application/*`) or `*/*`.
+    /// This lets content scanners inspect files without reading them a second time.
+    pub fn on_content<F>(mut self, patterns: &[&str], callback: F) -> Self
+    where
+        F: Fn(&Path, &Guess, &[u8]) + Send + Sync + 'a,
+    {
+        self.content_hooks.push(ContentHook {
+            patterns: patterns.iter().map(|p| p.to_string()).collect(),
+            callback: Box::new(callback),
+        });
+        self
+    }
+
+    /// Classifies every file below `root`, or `root` itself if it is a file.
+    ///
+    /// Files are classified in parallel, on rayon's pool with the `rayon` feature and on one
+    /// scoped thread per core otherwise, so content hooks may run concurrently. Entries are
+    /// still sorted by path, with each directory's contents listed in full before its next
+    /// sibling.
+    pub fn scan(&self, root: &Path) -> Vec<ScanEntry> {
+        let mut found = Vec::new();
+        self.visit(root, 0, &mut Walk::default(), &mut found);
+
+        let files: Vec<&Path> = found
+            .iter()
+            .filter_map(|found| match found {
+                Found::File(path) => Some(path.as_path()),
+                Found::Failed(_) => None,
+            })
+            .collect();
+        let mut results = self.classify_all(&files).into_iter();
+
+        found
+            .into_iter()
+            .filter_map(|found| match found {
+                Found::File(path) => results
+                    .next()
+                    .flatten()
+                    .map(|result| ScanEntry { path, result }),
+                Found::Failed(entry) => Some(entry),
+            })
+            .collect()
+    }
+
+    fn visit(&self, path: &Path, depth: usize, walk: &mut Walk, found: &mut Vec<Found>) {
+        let metadata = match path.symlink_metadata() {
+            Ok(metadata) => metadata,
+            Err(e) => return found.push(Found::Failed(ScanEntry::failed(path, e))),
+        };
+        // A dangling link is skipped, as it would be when not following links
+        let metadata = if metadata.is_symlink() && self.follow_symlinks {
+            match path.metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => return,
+            }
+        } else {
+            metadata
+        };
+
+        if metadata.is_dir() {
+            if depth >= self.max_depth || !self.enter(path, &metadata, walk) {
+                return;
+            }
+            let children = match std::fs::read_dir(path) {
+                Ok(children) => children,
+                Err(e) => return found.push(Found::Failed(ScanEntry::failed(path, e))),
+            };
+            let mut children: Vec<_> = children
+                .filter_map(|child| child.ok().map(|child| child.path()))
+                .collect();
+            children.sort();
+            for child in children {
+                self.visit(&child, depth + 1, walk, found);
+            }
+        } else if path.is_file() {
+            found.push(Found::File(path.to_path_buf()));
+        }
+    }
+
+    // Whether the directory is new to this walk and, if asked, on the root's file system
+    #[cfg(unix)]
+    fn enter(&self, _path: &Path, metadata: &Metadata, walk: &mut Walk) -> bool {
+        use std::os::unix::fs::MetadataExt;
+
+        let device = *walk.device.get_or_insert(metadata.dev());
+        if self.same_file_system && metadata.dev() != device {
+            return false;
+        }
+        walk.visited.insert((metadata.dev(), metadata.ino()))
+    }
+
+    #[cfg(not(unix))]
+    fn enter(&self, path: &Path, _metadata: &Metadata, walk: &mut Walk) -> bool {
+        path.canonicalize()
+            .is_ok_and(|path| walk.visited.insert(path))
+    }
+
+    #[cfg(feature = "rayon")]
+    fn classify_all(&self, paths: &[&Path]) -> Vec<Option<Result<Guess, Error>>> {
+        use rayon::prelude::*;
+
+        paths.par_iter().map(|path| self.classify(path)).collect()
+    }
+
+    // Workers take the next unclaimed path until none are left, so one slow file doesn't hold
+    // up a whole chunk
+    #[cfg(not(feature = "rayon"))]
+    fn classify_all(&self, paths: &[&Path]) -> Vec<Option<Result<Guess, Error>>> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let workers = std::thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .min(paths.len());
+        let next = AtomicUsize::new(0);
+        let mut results: Vec<_> = paths.iter().map(|_| None).collect();
+
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut done = Vec::new();
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(path) = paths.get(i) else {
+                                return done;
+                            };
+                            done.push((i, self.classify(path)));
+                        }
+                    })
+                })
+                .collect();
+            for handle in handles {
+                let done = handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+                for (i, result) in done {
+                    results[i] = result;
+                }
+            }
+        });
+        results
+    }
+
+    // `None` if the file was filtered out
+    fn classify(&self, path: &Path) -> Option<Result<Guess, Error>> {
+        let mut data = Vec::new();
+        if let Err(e) = self.searcher.read_head(path, &mut data) {
+            return Some(Err(e));
+        }
+
+        let guess = match self.searcher.guess(path, &data) {
+            Ok(guess) => guess,
+            Err(e) => return Some(Err(e)),
+        };
+        if !self.wanted(&guess.mime) {
+            return None;
+        }
+        for hook in &self.content_hooks {
+            if hook
+                .patterns
+                .iter()
+                .any(|pattern| guess.mime.matches(pattern))
+            {
+                (hook.callback)(path, &guess, &data);
+            }
+        }
+        Some(Ok(guess))
+    }
+
+    fn wanted(&self, mime: &MimeType) -> bool {
+        let matches = |pattern: &String| mime.matches(pattern);
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+}
+
+impl ScanEntry {
+    fn failed(path: &Path, error: std::io::Error) -> Self {
+        ScanEntry {
+            path: path.to_path_buf(),
+            result: Err(Error::io(path, error)),
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! Finding types from what a user types into a search box.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{CacheSection, Error, MimeSearcher, MimeType};
+
+/// How well a candidate matched, best first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Match {
+    Exact,
+    Prefix,
+    Substring,
+    /// The query's characters appear in order, but not together.
+    Scattered,
+}
+
+/// What a query matched, in order of preference when two types match equally well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Field {
+    Name,
+    Alias,
+    Comment,
+}
+
+impl MimeSearcher {
+    /// The known types whose name or an alias matches `query`, best matches first.
+    ///
+    /// The query is compared ignoring case against whole names and against subtypes, so `png`
+    /// finds `image/png` first. Names equal to the query rank highest, then names starting with
+    /// it, then names containing it, then names containing its characters in order, such as
+    /// `sprsht` for a spreadsheet. Matches on a type's own name beat matches on an alias, and
+    /// types matching equally well are sorted by name. An empty query finds every type.
+    ///
+    /// ```no_run
+    /// # use shared_mime_info as smi;
+    /// let searcher = smi::MimeSearcher::new().unwrap();
+    /// for mime in searcher.search_types("sprea").unwrap().iter().take(5) {
+    ///     println!("{mime}");
+    /// }
+    /// ```
+    pub fn search_types(&self, query: &str) -> Result<Vec<MimeType>, Error> {
+        self.search(query, false)
+    }
+
+    /// Like [`search_types`](Self::search_types), but also matches the types'
+    /// [descriptions](Self::comment) in the user's language, so `spreadsheet` finds types
+    /// whose names don't say so. Descriptions are only matched as whole or in part, never by
+    /// scattered characters, and rank below names and aliases matching as well.
+    ///
+    /// This reads the description of every type from disk.
+    pub fn search_types_with_comments(&self, query: &str) -> Result<Vec<MimeType>, Error> {
+        self.search(query, true)
+    }
+
+    fn search(&self, query: &str, comments: bool) -> Result<Vec<MimeType>, Error> {
+        let query = query.trim().to_lowercase();
+        let mut aliases: BTreeMap<MimeType, BTreeSet<MimeType>> = BTreeMap::new();
+        for database in self.databases.iter() {
+            for (alias, mime) in database.to_model_of(&[CacheSection::AliasList])?.aliases {
+                aliases.entry(mime).or_default().insert(alias);
+            }
+        }
+
+        let mut found = Vec::new();
+        for mime in self.known_types()? {
+            let name = |mime: &MimeType| {
+                let full = rank(&query, mime.essence_str(), true);
+                let subtype = rank(&query, mime.subtype(), true);
+                full.into_iter().chain(subtype).min()
+            };
+            let best = [
+                name(&mime).map(|m| (m, Field::Name)),
+                aliases
+                    .get(&mime)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(name)
+                    .min()
+                    .map(|m| (m, Field::Alias)),
+                comments
+                    .then(|| self.comment(&mime))
+                    .flatten()
+                    .and_then(|comment| rank(&query, &comment, false))
+                    .map(|m| (m, Field::Comment)),
+            ]
+            .into_iter()
+            .flatten()
+            .min();
+            if let Some(best) = best {
+                found.push((best, mime));
+            }
+        }
+        found.sort();
+        Ok(found.into_iter().map(|(_, mime)| mime).collect())
+    }
+}
+
+/// How `candidate` matches the lowercased `query`, if it does at all.
+fn rank(query: &str, candidate: &str, scattered: bool) -> Option<Match> {
+    let candidate = candidate.to_lowercase();
+    if candidate == query {
+        Some(Match::Exact)
+    } else if candidate.starts_with(query) {
+        Some(Match::Prefix)
+    } else if candidate.contains(query) {
+        Some(Match::Substring)
+    } else if scattered && is_subsequence(query, &candidate) {
+        Some(Match::Scattered)
+    } else {
+        None
+    }
+}
+
+fn is_subsequence(query: &str, candidate: &str) -> bool {
+    let mut chars = candidate.chars();
+    query.chars().all(|c| chars.any(|other| other == c))
+}
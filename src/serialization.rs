@@ -0,0 +1,20 @@
+//! Serializing mime types as their plain string, so that stored results read like the
+//! databases and `Content-Type` headers they came from.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error as _};
+
+use crate::MimeType;
+
+impl Serialize for MimeType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.essence_str())
+    }
+}
+
+/// Fails on strings that aren't a valid `media/subtype` pair, as [`MimeType::new`] does.
+impl<'de> Deserialize<'de> for MimeType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let essence = String::deserialize(deserializer)?;
+        MimeType::try_from(essence).map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,88 @@
+//! A searcher that long-running services can swap for a newer one while it is in use.
+
+use std::sync::{Mutex, MutexGuard, PoisonError, RwLock};
+
+use crate::{Error, MimeSearcher};
+
+/// A [`MimeSearcher`] shared by many threads that can be replaced, typically by a
+/// [reloaded](Self::reload) one, without stopping them.
+///
+/// Lookups go through a [`snapshot`](Self::snapshot), a clone of the current searcher that
+/// keeps answering from the databases it was taken from even if they are replaced meanwhile.
+/// Taking one is cheap, and neither it nor a lookup ever waits for databases to load: those are
+/// loaded aside and swapped in when ready.
+///
+/// ```no_run
+/// # use std::sync::Arc;
+/// # use shared_mime_info as smi;
+/// let shared = Arc::new(smi::SharedMimeSearcher::new(smi::MimeSearcher::new().unwrap()));
+/// std::thread::spawn({
+///     let shared = shared.clone();
+///     move || loop {
+///         std::thread::sleep(std::time::Duration::from_secs(60));
+///         let _ = shared.reload();
+///     }
+/// });
+/// # #[cfg(feature = "globs")]
+/// let mime = shared.snapshot().find_mimetype_from_filepath("foo.pdf".as_ref());
+/// ```
+#[derive(Debug)]
+pub struct SharedMimeSearcher {
+    current: RwLock<MimeSearcher>,
+    /// Held while reloading, so that nothing swapped in meanwhile is lost.
+    reloading: Mutex<()>,
+}
+
+impl SharedMimeSearcher {
+    pub fn new(searcher: MimeSearcher) -> Self {
+        SharedMimeSearcher {
+            current: RwLock::new(searcher),
+            reloading: Mutex::new(()),
+        }
+    }
+
+    /// The current searcher, which stays as it is when this one is replaced.
+    pub fn snapshot(&self) -> MimeSearcher {
+        self.current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Makes `searcher` the current one, returning the one it replaces. Snapshots already
+    /// taken keep using the old one. Waits for any reload underway, so that it doesn't
+    /// overwrite `searcher`.
+    pub fn replace(&self, searcher: MimeSearcher) -> MimeSearcher {
+        let _reloading = self.lock_reloading();
+        self.swap(searcher)
+    }
+
+    fn swap(&self, searcher: MimeSearcher) -> MimeSearcher {
+        let mut current = self.current.write().unwrap_or_else(PoisonError::into_inner);
+        std::mem::replace(&mut current, searcher)
+    }
+
+    fn lock_reloading(&self) -> MutexGuard<'_, ()> {
+        self.reloading
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// [Reloads](MimeSearcher::reload) a copy of the current searcher and swaps it in once
+    /// loaded. Lookups carry on with the current one in the meantime.
+    ///
+    /// Fails as [`MimeSearcher::reload`] does, in which case the current searcher stays.
+    pub fn reload(&self) -> Result<(), Error> {
+        let _reloading = self.lock_reloading();
+        let mut searcher = self.snapshot();
+        searcher.reload()?;
+        self.swap(searcher);
+        Ok(())
+    }
+}
+
+impl From<MimeSearcher> for SharedMimeSearcher {
+    fn from(searcher: MimeSearcher) -> Self {
+        SharedMimeSearcher::new(searcher)
+    }
+}
@@ -0,0 +1,142 @@
+//! Pinning the databases a searcher uses, so that tests and CI give the same answers whatever
+//! the host has installed.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
+
+use crate::{
+    CacheStorage, Error, MimeCache, MimeDatabaseModel, MimeSearcher, MimeType, NO_GLOBS_MARKER,
+};
+
+impl MimeSearcher {
+    /// Every database of this searcher merged into one mime.cache, for
+    /// [`from_snapshot`](Self::from_snapshot).
+    ///
+    /// The databases are merged as lookups combine them: a glob pattern, the magic rules,
+    /// parents or icons of a type, an alias or a namespace come from the most important
+    /// database defining them, and `__NOGLOBS__` drops a type's globs from the databases after
+    /// its own. [Temporary types](Self::register_temporary_type) are included. A globs2 without
+    /// a mime.cache beside it isn't, as only caches are kept once loaded.
+    ///
+    /// The snapshot is a mime.cache rather than, say, the model as JSON, so that it needs no
+    /// `serde` feature and loads without being parsed: lookups read it in place, from a file or
+    /// from a slice baked in with [`include_bytes!`]. It is also a database any shared-mime-info
+    /// reader understands, and [`MimeDatabaseModel::from_cache_bytes`] turns it back into a
+    /// model to inspect or diff.
+    ///
+    /// ```no_run
+    /// # use shared_mime_info as smi;
+    /// // Once, on a machine with the database the tests were written against
+    /// let snapshot = smi::MimeSearcher::new().unwrap().export_snapshot().unwrap();
+    /// std::fs::write("tests/mime.snapshot", snapshot).unwrap();
+    /// ```
+    ///
+    /// Fails with the error of the first database that can't be read.
+    pub fn export_snapshot(&self) -> Result<Vec<u8>, Error> {
+        let models = self
+            .databases
+            .iter()
+            .map(|database| database.to_model())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(merge_databases(models).to_cache_bytes())
+    }
+
+    /// A searcher using only a database [exported](Self::export_snapshot) earlier, such as one
+    /// checked into a repository or read with [`include_bytes!`].
+    ///
+    /// No data directory is read, as with [`embedded`](Self::embedded), so lookups give the
+    /// answers of the machine the snapshot was taken on.
+    ///
+    /// ```no_run
+    /// # use shared_mime_info as smi;
+    /// let snapshot = std::fs::read("tests/mime.snapshot").unwrap();
+    /// let searcher = smi::MimeSearcher::from_snapshot(snapshot).unwrap();
+    /// ```
+    ///
+    /// Fails with [`Error::MissingHeader`] or [`Error::UnsupportedCacheVersion`] if `snapshot`
+    /// isn't a mime.cache this crate can read.
+    pub fn from_snapshot(snapshot: impl CacheStorage + 'static) -> Result<MimeSearcher, Error> {
+        MimeSearcher::builder()
+            .data_dirs(std::iter::empty::<PathBuf>())
+            .add_database(MimeCache::from_storage(snapshot)?)
+            .build()
+    }
+}
+
+/// One model answering lookups as `models`, most important first, do together.
+fn merge_databases(models: Vec<MimeDatabaseModel>) -> MimeDatabaseModel {
+    let mut merged = MimeDatabaseModel::default();
+    // The database each pattern was claimed by, and the types whose globs are deleted
+    let mut claimed: HashMap<String, usize> = HashMap::new();
+    let mut deleted = HashSet::new();
+    let mut magic = HashSet::new();
+    let mut aliases = HashSet::new();
+    let mut parents = HashSet::new();
+    let mut namespaces = HashSet::new();
+    let mut icons = HashSet::new();
+    let mut generic_icons = HashSet::new();
+
+    for (rank, model) in models.into_iter().enumerate() {
+        let mut markers = Vec::new();
+        for (globs, kept) in [
+            (model.literals, &mut merged.literals),
+            (model.suffixes, &mut merged.suffixes),
+            (model.globs, &mut merged.globs),
+        ] {
+            for glob in globs {
+                if glob.pattern == NO_GLOBS_MARKER {
+                    markers.push(glob.mime);
+                } else if !deleted.contains(&glob.mime)
+                    && *claimed.entry(glob.pattern.clone()).or_insert(rank) == rank
+                {
+                    kept.push(glob);
+                }
+            }
+        }
+        // Markers only delete globs from the databases after their own
+        deleted.extend(markers);
+
+        // A type's rules all come from one database, even if that one only deletes the others
+        let types: HashSet<_> = model.magic.iter().map(|m| m.mime.clone()).collect();
+        merged
+            .magic
+            .extend(model.magic.into_iter().filter(|m| !magic.contains(&m.mime)));
+        magic.extend(types);
+
+        let first = |seen: &mut HashSet<MimeType>, mime: &MimeType| seen.insert(mime.clone());
+        merged.aliases.extend(
+            model
+                .aliases
+                .into_iter()
+                .filter(|(alias, _)| first(&mut aliases, alias)),
+        );
+        // A database listing no parents for a type leaves it to the next one, as lookups do
+        merged.parents.extend(
+            model
+                .parents
+                .into_iter()
+                .filter(|(mime, list)| !list.is_empty() && first(&mut parents, mime)),
+        );
+        merged.namespaces.extend(
+            model
+                .namespaces
+                .into_iter()
+                .filter(|ns| namespaces.insert((ns.uri.clone(), ns.local_name.clone()))),
+        );
+        merged.icons.extend(
+            model
+                .icons
+                .into_iter()
+                .filter(|(mime, _)| first(&mut icons, mime)),
+        );
+        merged.generic_icons.extend(
+            model
+                .generic_icons
+                .into_iter()
+                .filter(|(mime, _)| first(&mut generic_icons, mime)),
+        );
+    }
+    merged
+}
@@ -0,0 +1,115 @@
+//! Classifying files and streams from async code, without blocking the runtime on reads.
+//!
+//! Each runtime only supplies the reads: the [`ReadDriver`] shared with
+//! [`MimeSearcher::sniff_reader`] decides how much to read and when to stop.
+
+#[cfg(feature = "tokio")]
+use std::{io, path::Path};
+
+use crate::{
+    Classifier, Error, Guess, MimeSearcher,
+    classifier::{ReadDriver, ReadStep},
+};
+#[cfg(feature = "tokio")]
+use crate::{DetectionMethod, inode};
+
+impl MimeSearcher {
+    /// [`sniff_file`](Self::sniff_file) read through tokio so that a server classifying uploads
+    /// doesn't stall its other tasks on the file system.
+    ///
+    /// As with a [`Classifier`], the file isn't opened when its name settles the type, and
+    /// otherwise only as much of it as the magic rules look at is read. Only regular files are
+    /// read, as with `sniff_file`.
+    ///
+    /// ```no_run
+    /// # use shared_mime_info as smi;
+    /// # async fn upload(searcher: &smi::MimeSearcher) {
+    /// let guess = searcher.sniff_file_async("upload.bin").await.unwrap();
+    /// println!("{}", guess.mime);
+    /// # }
+    /// ```
+    ///
+    /// Fails with [`Error::Io`] if the file has to be read and can't be, and
+    /// [`Error::SniffAborted`] if reading it outlasts the
+    /// [sniff timeout](crate::MimeSearcherBuilder::sniff_timeout).
+    #[cfg(feature = "tokio")]
+    pub async fn sniff_file_async(&self, path: impl AsRef<Path>) -> Result<Guess, Error> {
+        let path = path.as_ref();
+        let mut driver = ReadDriver::new(Classifier::new(self).with_name(path))?;
+        if let ReadStep::Done(guess) = driver.next() {
+            return Ok(guess);
+        }
+        let metadata = tokio::fs::metadata(path)
+            .await
+            .map_err(|e| Error::io(path, e))?;
+        if let Some(mime) = inode::non_regular_type(&metadata) {
+            return Ok(Guess::new(mime, DetectionMethod::Inode));
+        }
+        let file = tokio::fs::File::open(path)
+            .await
+            .map_err(|e| Error::io(path, e))?;
+        drive_tokio(driver, file, |e| Error::io(path, e)).await
+    }
+
+    /// [`sniff_reader`](Self::sniff_reader) for a tokio stream, such as a request body.
+    ///
+    /// Fails with [`Error::Io`] if reading fails, and [`Error::SniffAborted`] if it outlasts
+    /// the [sniff timeout](crate::MimeSearcherBuilder::sniff_timeout).
+    #[cfg(feature = "tokio")]
+    pub async fn sniff_async_reader<R: tokio::io::AsyncRead + Unpin>(
+        &self,
+        reader: R,
+    ) -> Result<Guess, Error> {
+        let driver = ReadDriver::new(Classifier::new(self))?;
+        drive_tokio(driver, reader, Error::from).await
+    }
+
+    /// [`sniff_reader`](Self::sniff_reader) for a stream of the `futures` crate, which
+    /// async-std, smol and most other runtimes read through.
+    ///
+    /// ```no_run
+    /// # use shared_mime_info as smi;
+    /// # async fn upload(searcher: &smi::MimeSearcher, body: &[u8]) {
+    /// let guess = searcher.sniff_futures_reader(body).await.unwrap();
+    /// println!("{}", guess.mime);
+    /// # }
+    /// ```
+    ///
+    /// Fails with [`Error::Io`] if reading fails, and [`Error::SniffAborted`] if it outlasts
+    /// the [sniff timeout](crate::MimeSearcherBuilder::sniff_timeout).
+    #[cfg(feature = "futures")]
+    pub async fn sniff_futures_reader<R: futures_io::AsyncRead + Unpin>(
+        &self,
+        mut reader: R,
+    ) -> Result<Guess, Error> {
+        use std::pin::Pin;
+
+        let mut driver = ReadDriver::new(Classifier::new(self))?;
+        loop {
+            let read = match driver.next() {
+                ReadStep::Read(buffer) => {
+                    std::future::poll_fn(|cx| Pin::new(&mut reader).poll_read(cx, buffer)).await?
+                }
+                ReadStep::Done(guess) => return Ok(guess),
+            };
+            driver.filled(read)?;
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+async fn drive_tokio<R: tokio::io::AsyncRead + Unpin>(
+    mut driver: ReadDriver<'_>,
+    mut reader: R,
+    io_error: impl Fn(io::Error) -> Error,
+) -> Result<Guess, Error> {
+    use tokio::io::AsyncReadExt;
+
+    loop {
+        let read = match driver.next() {
+            ReadStep::Read(buffer) => reader.read(buffer).await.map_err(&io_error)?,
+            ReadStep::Done(guess) => return Ok(guess),
+        };
+        driver.filled(read)?;
+    }
+}
@@ -0,0 +1,41 @@
+//! Where a searcher's databases came from, for tools that need to say which one answered.
+
+use std::{path::PathBuf, time::SystemTime};
+
+use crate::MimeSearcher;
+
+/// A database directory a searcher loaded, and the files it read there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct DatabaseSource {
+    /// The directory, such as `/usr/share/mime`.
+    pub dir: PathBuf,
+    /// The mime.cache loaded, if the directory has one.
+    pub cache: Option<PathBuf>,
+    /// The `(major, minor)` version of the cache's format.
+    pub cache_version: Option<(u16, u16)>,
+    /// When the cache was last modified, if the file system keeps track.
+    pub cache_modified: Option<SystemTime>,
+    /// The globs2 read, if the directory has one and the searcher uses globs.
+    pub globs2: Option<PathBuf>,
+}
+
+impl MimeSearcher {
+    /// The database directories loaded, including those of the searcher this one was
+    /// [derived](Self::derive) from, most important first. Directories with neither a
+    /// mime.cache nor a globs2 are left out, and so are databases
+    /// [added](crate::MimeSearcherBuilder::add_database) or
+    /// [registered](Self::register_temporary_type) without a directory.
+    ///
+    /// ```no_run
+    /// # use shared_mime_info as smi;
+    /// let searcher = smi::MimeSearcher::new().unwrap();
+    /// for source in searcher.sources() {
+    ///     println!("{}: {:?}", source.dir.display(), source.cache_version);
+    /// }
+    /// ```
+    pub fn sources(&self) -> &[DatabaseSource] {
+        &self.sources
+    }
+}
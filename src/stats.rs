@@ -0,0 +1,92 @@
+//! Counting what the loaded databases define, for diagnostics.
+
+use std::collections::BTreeSet;
+
+#[cfg(feature = "globs")]
+use crate::{CacheLayer, GlobEntry, Globber, IndexedExtension, MimeType, NO_GLOBS_MARKER, Span};
+use crate::{CacheSection, Error, MimeSearcher};
+
+/// How much the loaded databases define, from [`MimeSearcher::stats`].
+///
+/// The counts are of definitions as installed, across every database, so comparing them between
+/// two versions of a distribution's database shows what was added or dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Stats {
+    /// Canonical types, as listed by
+    /// [`list_types_matching`](MimeSearcher::list_types_matching).
+    pub types: usize,
+    /// Globs, as listed by [`all_globs`](MimeSearcher::all_globs) but without deletion markers.
+    pub globs: usize,
+    /// Magic rules, each one a type's rules at one priority, summed over the databases.
+    pub magic_rules: usize,
+    /// Distinct alias names.
+    pub aliases: usize,
+    /// Types with an icon of their own.
+    pub icons: usize,
+    /// Types with a generic icon.
+    pub generic_icons: usize,
+    /// Roughly how many bytes the file name index takes up, including that of any searcher this
+    /// one was derived from. The databases themselves aren't counted. The index is built for
+    /// this if no lookup has needed it yet.
+    pub index_bytes: usize,
+}
+
+impl MimeSearcher {
+    /// Counts the types, globs, magic rules, aliases and icons the loaded databases define, and
+    /// estimates the memory taken by the indices built from them.
+    ///
+    /// This reads every database in full. Fails with the error of the first database that can't
+    /// be read.
+    pub fn stats(&self) -> Result<Stats, Error> {
+        let mut stats = Stats {
+            types: self.known_types()?.len(),
+            ..Stats::default()
+        };
+        let mut aliases = BTreeSet::new();
+        let mut icons = BTreeSet::new();
+        let mut generic_icons = BTreeSet::new();
+        for database in self.databases.iter() {
+            let model = database.to_model_of(&[
+                CacheSection::MagicList,
+                CacheSection::AliasList,
+                CacheSection::IconsList,
+                CacheSection::GenericIconsList,
+            ])?;
+            stats.magic_rules += model.magic.len();
+            aliases.extend(model.aliases.into_iter().map(|(alias, _)| alias));
+            icons.extend(model.icons.into_iter().map(|(mime, _)| mime));
+            generic_icons.extend(model.generic_icons.into_iter().map(|(mime, _)| mime));
+        }
+        stats.aliases = aliases.len();
+        stats.icons = icons.len();
+        stats.generic_icons = generic_icons.len();
+        #[cfg(feature = "globs")]
+        {
+            let globs = self.all_globs()?;
+            stats.globs = globs
+                .iter()
+                .filter(|glob| glob.pattern != NO_GLOBS_MARKER)
+                .count();
+            stats.index_bytes = self.globber().layers().map(Globber::heap_size).sum();
+        }
+        Ok(stats)
+    }
+}
+
+#[cfg(feature = "globs")]
+impl Globber {
+    /// An estimate of the heap memory this layer holds, leaving out its parents and the caches
+    /// it looks extensions up in.
+    fn heap_size(&self) -> usize {
+        use std::mem::size_of;
+
+        self.names.capacity()
+            + self.extensions.capacity() * size_of::<IndexedExtension>()
+            + self.extension_entries.capacity() * size_of::<GlobEntry>()
+            + self.complex_globs.capacity() * size_of::<(Span, GlobEntry)>()
+            + self.caches.capacity() * size_of::<CacheLayer>()
+            + self.deleted.capacity() * size_of::<(MimeType, usize)>()
+    }
+}
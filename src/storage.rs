@@ -0,0 +1,18 @@
+//! Where the bytes of a loaded mime.cache live.
+
+use std::fmt;
+
+/// The bytes of a mime.cache, which hold its string pool along with everything else.
+///
+/// A [`MimeCache`](crate::MimeCache) reads type names, globs and icon names straight out of
+/// these bytes rather than copying them, so where they live decides how much heap the cache
+/// itself costs. [`MimeCache::load`](crate::MimeCache::load) reads the file into a `Vec<u8>`,
+/// while a program can hand [`MimeCache::from_storage`](crate::MimeCache::from_storage) a
+/// memory mapping, a `'static` slice baked into the binary, or an `Arc<[u8]>` shared with other
+/// caches. The glob index of a searcher is apart from this: it copies the patterns it indexes,
+/// as [`IndexProfile`](crate::IndexProfile) describes, wherever the cache lives.
+///
+/// Anything that is `AsRef<[u8]>`, `Debug`, `Send` and `Sync` already is one.
+pub trait CacheStorage: AsRef<[u8]> + fmt::Debug + Send + Sync {}
+
+impl<T: AsRef<[u8]> + fmt::Debug + Send + Sync + ?Sized> CacheStorage for T {}
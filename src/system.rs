@@ -0,0 +1,31 @@
+//! One searcher for the whole program, for libraries that look up a type now and then and
+//! shouldn't each load the databases themselves.
+
+use std::sync::OnceLock;
+
+use crate::MimeSearcher;
+
+/// The searcher for the system's databases, loaded as by [`MimeSearcher::new`] the first time
+/// this is called and shared by every caller after that.
+///
+/// ```
+/// # #[cfg(all(feature = "globs", feature = "icons"))] {
+/// use shared_mime_info as smi;
+///
+/// let mime = smi::system().find_mimetype_from_filepath("foo.pdf".as_ref());
+/// let icon = mime.and_then(|mime| smi::system().find_icon_for_mimetype(mime).ok());
+/// # }
+/// ```
+///
+/// Never fails: if the databases can't be loaded, the error is logged and the searcher has
+/// none, so that every lookup finds nothing. Programs that want to handle the error, reload
+/// the databases or change settings should build their own searcher instead.
+pub fn system() -> &'static MimeSearcher {
+    static SYSTEM: OnceLock<MimeSearcher> = OnceLock::new();
+    SYSTEM.get_or_init(|| {
+        MimeSearcher::new().unwrap_or_else(|e| {
+            log::warn!("lookups will find nothing, as the mime databases can't be loaded: {e}");
+            MimeSearcher::empty()
+        })
+    })
+}
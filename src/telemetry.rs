@@ -0,0 +1,21 @@
+//! Recording of file extensions the database knows nothing about.
+
+use std::{collections::BTreeSet, sync::Mutex};
+
+/// The lowercased extensions of file names that no glob matched.
+#[derive(Debug, Default)]
+pub(crate) struct UnknownExtensions {
+    seen: Mutex<BTreeSet<String>>,
+}
+
+impl UnknownExtensions {
+    pub(crate) fn record(&self, ext: &str) {
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        seen.insert(ext.to_lowercase());
+    }
+
+    pub(crate) fn list(&self) -> Vec<String> {
+        let seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        seen.iter().cloned().collect()
+    }
+}
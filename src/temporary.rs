@@ -0,0 +1,129 @@
+//! Types that exist only in one searcher, for as long as the application wants them.
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+#[cfg(feature = "globs")]
+use crate::{Layer, LazyGlobber};
+use crate::{MimeDatabase, MimeDatabaseModel, MimeSearcher};
+
+/// Identifies registrations across all searchers, so a handle can't revoke another searcher's.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A registration made with [`MimeSearcher::register_temporary_type`], needed to revoke it.
+///
+/// Dropping the handle leaves the types registered.
+#[derive(Debug, PartialEq, Eq, Hash)]
+#[must_use = "without the handle the types can't be revoked"]
+pub struct TemporaryType {
+    id: u64,
+}
+
+impl MimeSearcher {
+    /// Adds the types in `definition` to this searcher only, taking precedence over every
+    /// database, until revoked with [`revoke_temporary_type`](Self::revoke_temporary_type).
+    ///
+    /// Nothing is written to disk, so an application can teach its searcher about formats that
+    /// only it knows, such as project files declared by plugins loaded at runtime. Later
+    /// registrations win over earlier ones. Searchers [derived](Self::derive) while the types
+    /// are registered keep them for good.
+    ///
+    /// ```no_run
+    /// # #[cfg(feature = "globs")] {
+    /// # use shared_mime_info as smi;
+    /// let mut searcher = smi::MimeSearcher::new().unwrap();
+    /// let project = smi::MimeType::new("application/x-myapp-project").unwrap();
+    /// let handle = searcher.register_temporary_type(smi::MimeDatabaseModel {
+    ///     suffixes: vec![smi::GlobRecord {
+    ///         pattern: "*.myproj".to_string(),
+    ///         mime: project.clone(),
+    ///         weight: 50,
+    ///         case_sensitive: false,
+    ///     }],
+    ///     ..Default::default()
+    /// });
+    /// assert_eq!(searcher.find_mimetype_from_filepath("a.myproj".as_ref()), Some(project));
+    /// searcher.revoke_temporary_type(handle);
+    /// # }
+    /// ```
+    pub fn register_temporary_type(&mut self, definition: MimeDatabaseModel) -> TemporaryType {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        self.update_temporary(|temporary| temporary.push((id, Arc::new(definition))));
+        TemporaryType { id }
+    }
+
+    /// Removes types registered with
+    /// [`register_temporary_type`](Self::register_temporary_type), returning whether `handle`
+    /// was registered with this searcher.
+    pub fn revoke_temporary_type(&mut self, handle: TemporaryType) -> bool {
+        let mut found = false;
+        self.update_temporary(|temporary| {
+            let before = temporary.len();
+            temporary.retain(|(id, _)| *id != handle.id);
+            found = temporary.len() != before;
+        });
+        found
+    }
+
+    // Changes the registered types and rebuilds what depends on them. They sit in front of the
+    // loaded databases, and their globs in an index whose parent is the loaded one.
+    pub(crate) fn update_temporary(
+        &mut self,
+        change: impl FnOnce(&mut Vec<(u64, Arc<dyn MimeDatabase>)>),
+    ) {
+        // The index the temporary globs sit over, which is the searcher's own while there are none
+        #[cfg(feature = "globs")]
+        let base = match &self.glob_index.parent {
+            Some(parent) if !self.temporary.is_empty() => parent.clone(),
+            _ => self.glob_index.clone(),
+        };
+        let databases = Arc::make_mut(&mut self.databases);
+        databases.drain(..self.temporary.len());
+        change(Arc::make_mut(&mut self.temporary));
+
+        // Most recent first, so it wins
+        let temporary: Vec<_> = self
+            .temporary
+            .iter()
+            .rev()
+            .map(|(_, database)| database.clone())
+            .collect();
+        #[cfg(feature = "globs")]
+        {
+            self.glob_index = if temporary.is_empty() {
+                base
+            } else {
+                let layers = temporary
+                    .iter()
+                    .filter(|_| self.options.globs)
+                    .map(|database| Layer {
+                        source: None,
+                        database: Some(database.clone()),
+                        cache: None,
+                        globs2: None,
+                    })
+                    .collect();
+                Arc::new(LazyGlobber::new(layers, Some(base)))
+            };
+        }
+        databases.splice(0..0, temporary);
+        #[cfg(any(feature = "hierarchy", feature = "icons"))]
+        if self.type_index.is_some() {
+            // A database that can't be read leaves lookups to search them in place instead
+            self.type_index = crate::index::TypeIndex::new(&self.databases)
+                .ok()
+                .map(Arc::new);
+        }
+        // Fresh caches rather than cleared ones, as clones of this searcher may share the old
+        #[cfg(feature = "magic")]
+        if let Some(memo) = &mut self.sniff_memo {
+            *memo = Arc::new(crate::memo::SniffMemo::new(memo.capacity()));
+        }
+        #[cfg(any(feature = "globs", feature = "icons"))]
+        if let Some(cache) = &mut self.lookup_cache {
+            *cache = Arc::new(crate::lru::LookupCache::new(cache.capacity()));
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Reloading a searcher when the databases it was loaded from change on disk.
+
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, mpsc},
+    thread,
+    time::Duration,
+};
+
+use notify::Watcher;
+
+use crate::{Error, MimeSearcher, SharedMimeSearcher};
+
+/// How long the directories have to be quiet before reloading. update-mime-database rewrites
+/// a dozen files in a row, and reloading after each would be wasted work.
+const SETTLE: Duration = Duration::from_millis(200);
+
+/// A [`SharedMimeSearcher`] [reloaded](SharedMimeSearcher::reload) in the background whenever
+/// a mime.cache or globs2 in its directories changes, made by [`MimeSearcher::watch`].
+///
+/// Watching stops when this is dropped.
+pub struct WatchedSearcher {
+    shared: Arc<SharedMimeSearcher>,
+    messages: mpsc::Sender<Message>,
+}
+
+enum Message {
+    Changed(notify::Result<notify::Event>),
+    Stop,
+}
+
+impl MimeSearcher {
+    /// Watches the directories this searcher was loaded from, and reloads it when their
+    /// databases change, so that a long-running program such as a desktop shell picks up newly
+    /// installed types without restarting.
+    ///
+    /// `on_reload` is called from the watching thread after each reload, with its outcome, so
+    /// that the program can forget whatever it worked out from the old databases. Lookups carry
+    /// on with the old databases while the new ones load, and a failed reload leaves them in
+    /// use. Directories that don't exist yet are watched for being created.
+    ///
+    /// ```no_run
+    /// # use shared_mime_info as smi;
+    /// let (reloaded, on_reload) = std::sync::mpsc::channel();
+    /// let watched = smi::MimeSearcher::new()
+    ///     .unwrap()
+    ///     .watch(move |outcome| {
+    ///         let _ = reloaded.send(outcome);
+    ///     })
+    ///     .unwrap();
+    /// # #[cfg(feature = "globs")]
+    /// let mime = watched.snapshot().find_mimetype_from_filepath("foo.pdf".as_ref());
+    /// // Elsewhere, after a package installs new types
+    /// on_reload.recv().unwrap().unwrap();
+    /// ```
+    ///
+    /// Fails with [`Error::Io`] if the directories can't be watched.
+    pub fn watch(
+        self,
+        mut on_reload: impl FnMut(Result<(), Error>) + Send + 'static,
+    ) -> Result<WatchedSearcher, Error> {
+        let dirs = self.origin.dirs.clone();
+        let (messages, received) = mpsc::channel();
+        let sender = messages.clone();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(Message::Changed(event));
+        })
+        .map_err(|e| watch_error(Path::new(""), e))?;
+        let mut watched = HashSet::new();
+        watch_dirs(&mut watcher, &dirs, &mut watched)?;
+
+        let shared = Arc::new(SharedMimeSearcher::new(self));
+        let reloaded = shared.clone();
+        thread::spawn(move || {
+            while let Ok(Message::Changed(event)) = received.recv() {
+                if !event.is_ok_and(|event| event.paths.iter().any(|p| is_database(p, &dirs))) {
+                    continue;
+                }
+                // Waits for the rest of the update to land
+                loop {
+                    match received.recv_timeout(SETTLE) {
+                        Ok(Message::Changed(_)) => {}
+                        Ok(Message::Stop) | Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                        Err(mpsc::RecvTimeoutError::Timeout) => break,
+                    }
+                }
+                // Directories created since are watched themselves from now on
+                let _ = watch_dirs(&mut watcher, &dirs, &mut watched);
+                let outcome = reloaded.reload();
+                if let Err(e) = &outcome {
+                    log::warn!("the mime databases changed but can't be reloaded: {e}");
+                }
+                on_reload(outcome);
+            }
+        });
+        Ok(WatchedSearcher { shared, messages })
+    }
+}
+
+impl WatchedSearcher {
+    /// The searcher as of the last reload, as [`SharedMimeSearcher::snapshot`].
+    pub fn snapshot(&self) -> MimeSearcher {
+        self.shared.snapshot()
+    }
+
+    /// The searcher being reloaded, for handing to other threads. It stops being reloaded
+    /// when this is dropped.
+    pub fn shared(&self) -> &Arc<SharedMimeSearcher> {
+        &self.shared
+    }
+}
+
+impl Drop for WatchedSearcher {
+    fn drop(&mut self) {
+        let _ = self.messages.send(Message::Stop);
+    }
+}
+
+impl std::fmt::Debug for WatchedSearcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WatchedSearcher")
+            .field("shared", &self.shared)
+            .finish()
+    }
+}
+
+/// Watches each of `dirs` not in `watched` yet, or the directory above it while it doesn't
+/// exist.
+fn watch_dirs(
+    watcher: &mut impl Watcher,
+    dirs: &[PathBuf],
+    watched: &mut HashSet<PathBuf>,
+) -> Result<(), Error> {
+    for dir in dirs {
+        let target = if dir.is_dir() {
+            dir.as_path()
+        } else {
+            match dir.parent() {
+                Some(parent) if parent.is_dir() => parent,
+                _ => continue,
+            }
+        };
+        if watched.contains(target) {
+            continue;
+        }
+        watcher
+            .watch(target, notify::RecursiveMode::NonRecursive)
+            .map_err(|e| watch_error(target, e))?;
+        watched.insert(target.to_path_buf());
+    }
+    Ok(())
+}
+
+/// Whether `path` is a database file in one of `dirs`, or one of `dirs` itself being created.
+fn is_database(path: &Path, dirs: &[PathBuf]) -> bool {
+    let in_dirs = |dir: &Path| dirs.iter().any(|d| d == dir);
+    let is_database_file = matches!(
+        path.file_name().and_then(|name| name.to_str()),
+        Some("mime.cache" | "globs2")
+    );
+    in_dirs(path) || (is_database_file && path.parent().is_some_and(in_dirs))
+}
+
+fn watch_error(path: &Path, error: notify::Error) -> Error {
+    match error.kind {
+        notify::ErrorKind::Io(e) => Error::io(path, e),
+        kind => Error::io(path, io::Error::other(notify::Error::new(kind))),
+    }
+}
@@ -0,0 +1,225 @@
+//! A small pull parser for the XML package files mime databases are compiled from.
+//!
+//! It handles what those files use: elements, attributes, text, CDATA, comments, processing
+//! instructions and a DOCTYPE to skip, and the predefined and numeric character references.
+//! Namespace prefixes are kept as part of names. Element nesting is checked, so a truncated or
+//! mangled file fails rather than being half read.
+
+/// What the parser found next in the document.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Event<'a> {
+    /// An element opened, with its attributes in document order and their values unescaped.
+    Start {
+        name: &'a str,
+        attributes: Vec<(&'a str, String)>,
+    },
+    /// An element closed. Empty elements such as `<glob/>` are followed by this too.
+    End { name: &'a str },
+    /// Character data between tags, unescaped, including whitespace-only runs.
+    Text(String),
+}
+
+/// Why a document couldn't be parsed, and on which line.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct XmlError {
+    pub(crate) line: usize,
+    pub(crate) problem: &'static str,
+}
+
+pub(crate) struct Reader<'a> {
+    input: &'a str,
+    pos: usize,
+    open: Vec<&'a str>,
+    /// The name of an empty element whose end is still to be reported.
+    pending_end: Option<&'a str>,
+    failed: bool,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(input: &'a str) -> Self {
+        Reader {
+            input,
+            pos: 0,
+            open: Vec::new(),
+            pending_end: None,
+            failed: false,
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    /// The line the parser has got to, counting from 1.
+    pub(crate) fn line(&self) -> usize {
+        self.input[..self.pos].matches('\n').count() + 1
+    }
+
+    fn error(&mut self, problem: &'static str) -> XmlError {
+        self.failed = true;
+        XmlError {
+            line: self.line(),
+            problem,
+        }
+    }
+
+    /// Moves past `end`, returning what came before it.
+    fn take_until(&mut self, end: &str, problem: &'static str) -> Result<&'a str, XmlError> {
+        let Some(at) = self.rest().find(end) else {
+            return Err(self.error(problem));
+        };
+        let taken = &self.rest()[..at];
+        self.pos += at + end.len();
+        Ok(taken)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn name(&mut self) -> Result<&'a str, XmlError> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '/' | '>' | '='))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.error("expected a name"));
+        }
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+
+    fn next_event(&mut self) -> Result<Option<Event<'a>>, XmlError> {
+        if let Some(name) = self.pending_end.take() {
+            return Ok(Some(Event::End { name }));
+        }
+        loop {
+            let rest = self.rest();
+            if rest.is_empty() {
+                return if self.open.is_empty() {
+                    Ok(None)
+                } else {
+                    Err(self.error("unclosed element"))
+                };
+            }
+            if !rest.starts_with('<') {
+                let len = rest.find('<').unwrap_or(rest.len());
+                self.pos += len;
+                return match unescape(&rest[..len]) {
+                    Some(text) => Ok(Some(Event::Text(text))),
+                    None => Err(self.error("unknown character reference")),
+                };
+            }
+            if rest.starts_with("<!--") {
+                self.take_until("-->", "unterminated comment")?;
+            } else if rest.starts_with("<?") {
+                self.take_until("?>", "unterminated processing instruction")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.pos += "<![CDATA[".len();
+                let text = self.take_until("]]>", "unterminated CDATA section")?;
+                return Ok(Some(Event::Text(text.to_string())));
+            } else if rest.starts_with("<!") {
+                // A DOCTYPE, whose internal subset may itself contain `>`
+                let subset = rest
+                    .find('[')
+                    .filter(|&at| at < rest.find('>').unwrap_or(0));
+                let end = if subset.is_some() { "]>" } else { ">" };
+                self.take_until(end, "unterminated declaration")?;
+            } else if rest.starts_with("</") {
+                self.pos += 2;
+                let name = self.name()?;
+                self.skip_whitespace();
+                if !self.rest().starts_with('>') {
+                    return Err(self.error("expected `>`"));
+                }
+                self.pos += 1;
+                if self.open.pop() != Some(name) {
+                    return Err(self.error("end tag doesn't match the open element"));
+                }
+                return Ok(Some(Event::End { name }));
+            } else {
+                self.pos += 1;
+                return self.start_tag().map(Some);
+            }
+        }
+    }
+
+    fn start_tag(&mut self) -> Result<Event<'a>, XmlError> {
+        let name = self.name()?;
+        let mut attributes = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            if rest.starts_with("/>") {
+                self.pos += 2;
+                self.pending_end = Some(name);
+                break;
+            }
+            if rest.starts_with('>') {
+                self.pos += 1;
+                self.open.push(name);
+                break;
+            }
+            let attribute = self.name()?;
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return Err(self.error("expected `=` after an attribute name"));
+            }
+            self.pos += 1;
+            self.skip_whitespace();
+            let quote = match self.rest().chars().next() {
+                Some(quote @ ('"' | '\'')) => quote,
+                _ => return Err(self.error("expected a quoted attribute value")),
+            };
+            self.pos += 1;
+            let raw = self.take_until(
+                if quote == '"' { "\"" } else { "'" },
+                "unterminated attribute value",
+            )?;
+            let Some(value) = unescape(raw) else {
+                return Err(self.error("unknown character reference"));
+            };
+            attributes.push((attribute, value));
+        }
+        Ok(Event::Start { name, attributes })
+    }
+}
+
+impl<'a> Iterator for Reader<'a> {
+    type Item = Result<Event<'a>, XmlError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        self.next_event().transpose()
+    }
+}
+
+/// `text` with its character references replaced, or `None` if one isn't known.
+fn unescape(text: &str) -> Option<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('&') {
+        out.push_str(&rest[..at]);
+        let (reference, after) = rest[at + 1..].split_once(';')?;
+        out.push(match reference {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = match reference.strip_prefix("#x") {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => reference.strip_prefix('#')?.parse().ok()?,
+                };
+                char::from_u32(code)?
+            }
+        });
+        rest = after;
+    }
+    out.push_str(rest);
+    Some(out)
+}